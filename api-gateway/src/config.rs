@@ -1,30 +1,805 @@
-use std::env;
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use axum::http::{HeaderName, Method};
+use chrono::{DateTime, NaiveDate, Utc};
+use ipnet::IpNet;
+use serde::Serialize;
+
+const DEFAULT_CORS_METHODS: &str = "GET,POST,PUT,PATCH,DELETE,OPTIONS";
+const DEFAULT_CORS_HEADERS: &str =
+    "authorization,content-type,accept-version,idempotency-key,if-none-match,x-request-id,x-canary";
+const DEFAULT_CORS_EXPOSED_HEADERS: &str =
+    "x-request-id,api-version,deprecation,sunset,etag,x-cache,idempotent-replayed,x-next-cursor,x-prev-cursor";
+const DEFAULT_PRIVATE_NETWORKS: &str =
+    "10.0.0.0/8,172.16.0.0/12,192.168.0.0/16,127.0.0.0/8,::1/128,fc00::/7";
+const DEFAULT_PRIVATE_PATHS: &str = "/internal/{*path},/health/upstreams";
+const DEFAULT_DOCS_CSP: &str = "default-src 'none'; \
+    script-src 'self' 'unsafe-inline' https://unpkg.com; \
+    style-src 'self' 'unsafe-inline' https://unpkg.com; \
+    img-src 'self' data: https://unpkg.com; \
+    connect-src 'self'; \
+    frame-ancestors 'none'";
 
 #[derive(Clone)]
 pub struct Config {
     pub port: u16,
+    pub tls: Option<TlsConfig>,
+    pub admin: Option<AdminConfig>,
     pub jwt_secret: String,
-    pub auth_service_url: String,
-    pub social_service_url: String,
-    pub messaging_service_url: String,
+    pub auth_service: UpstreamConfig,
+    pub social_service: UpstreamConfig,
+    pub messaging_service: UpstreamConfig,
+    pub retry: RetryConfig,
+    pub circuit_breaker: CircuitBreakerConfig,
+    pub health_check: HealthCheckConfig,
+    pub realtime: RealtimeConfig,
+    pub bff: BffConfig,
+    pub cache: CacheConfig,
+    pub limits: LimitsConfig,
+    pub versioning: VersioningConfig,
+    pub canary: CanaryConfig,
+    pub cors: CorsConfig,
+    pub ip_filter: IpFilterConfig,
+    pub security_headers: SecurityHeadersConfig,
+    pub internal_token: Option<String>,
+    pub openapi_cache_ttl: Duration,
+    pub redis_url: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    pub reload_interval: Duration,
+    pub redirect_port: Option<u16>,
+    pub http2: bool,
+}
+
+#[derive(Clone)]
+pub struct AdminConfig {
+    pub addr: SocketAddr,
+    pub token: String,
+}
+
+#[derive(Clone)]
+pub struct UpstreamConfig {
+    pub name: String,
+    pub urls: Vec<String>,
+    pub timeout: Duration,
+    pub strategy: LoadBalancingStrategy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadBalancingStrategy {
+    RoundRobin,
+    LeastConnections,
+}
+
+#[derive(Clone)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+#[derive(Clone)]
+pub struct CircuitBreakerConfig {
+    pub failure_threshold: u32,
+    pub open_duration: Duration,
+    pub half_open_max_requests: u32,
+}
+
+#[derive(Clone)]
+pub struct HealthCheckConfig {
+    pub path: String,
+    pub interval: Duration,
+    pub timeout: Duration,
+    /// Consecutive failed probes before an instance is marked unhealthy.
+    pub failure_threshold: u32,
+    pub ejection_threshold: u32,
+    pub ejection_duration: Duration,
+}
+
+#[derive(Clone)]
+pub struct RealtimeConfig {
+    pub idle_timeout: Duration,
+    pub max_connections: usize,
+    pub max_connections_per_user: usize,
+}
+
+#[derive(Clone)]
+pub struct BffConfig {
+    pub section_timeout: Duration,
+    /// Largest upstream body buffered for one section; bigger ones fail the section.
+    pub max_section_bytes: usize,
+}
+
+#[derive(Clone)]
+pub struct CacheConfig {
+    pub max_entries: usize,
+    /// Largest response body stored; bigger ones are passed through uncached.
+    pub max_entry_bytes: usize,
+    pub rules: Vec<CacheRule>,
+}
+
+#[derive(Clone)]
+pub struct CacheRule {
+    pub pattern: String,
+    pub ttl: Duration,
+    pub tags: Vec<String>,
+    pub vary: Vec<String>,
+    /// Share entries between users; otherwise each user gets their own entries.
+    pub shared: bool,
+}
+
+#[derive(Clone)]
+pub struct LimitsConfig {
+    pub max_body_bytes: usize,
+    pub allowed_content_types: Vec<String>,
+    pub max_json_depth: usize,
+    pub header_read_timeout: Duration,
+    pub body_read_timeout: Duration,
+    /// HTTP/2 has no header read timeout in hyper: peers that stop answering pings
+    /// are dropped and each connection holds a bounded number of streams.
+    pub http2_keep_alive_interval: Duration,
+    pub http2_keep_alive_timeout: Duration,
+    pub http2_max_concurrent_streams: u32,
+    pub rules: Vec<LimitRule>,
+}
+
+#[derive(Clone)]
+pub struct LimitRule {
+    pub pattern: String,
+    pub max_body_bytes: usize,
+    pub content_types: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct IpFilterConfig {
+    pub allow: Vec<IpNet>,
+    pub deny: Vec<IpNet>,
+    pub trusted_proxies: Vec<IpNet>,
+    pub private_networks: Vec<IpNet>,
+    pub private_paths: Vec<String>,
+    pub sync_interval: Duration,
+}
+
+#[derive(Clone)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<Method>,
+    pub allowed_headers: Vec<HeaderName>,
+    pub exposed_headers: Vec<HeaderName>,
+    pub max_age: Duration,
+    pub allow_credentials: bool,
+}
+
+#[derive(Clone)]
+pub struct SecurityHeadersConfig {
+    pub hsts_max_age: Duration,
+    pub referrer_policy: String,
+    pub docs_csp: String,
+}
+
+#[derive(Clone)]
+pub struct VersioningConfig {
+    pub default_version: String,
+    pub versions: Vec<ApiVersionConfig>,
+}
+
+#[derive(Clone)]
+pub struct CanaryConfig {
+    pub header: HeaderName,
+    pub cookie: String,
+    pub rules: Vec<CanaryRule>,
+    pub upstreams: Vec<UpstreamConfig>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct CanaryRule {
+    pub pattern: String,
+    pub weight: u32,
+}
+
+#[derive(Clone)]
+pub struct ApiVersionConfig {
+    pub name: String,
+    pub deprecated_at: Option<DateTime<Utc>>,
+    pub sunset_at: Option<DateTime<Utc>>,
+    pub upstreams: Vec<UpstreamConfig>,
 }
 
 impl Config {
     pub fn from_env() -> Self {
         dotenvy::dotenv().ok();
+        Self::load(Source::Process)
+    }
+
+    /// The `from_env` defaults, without reading the process environment or `.env`,
+    /// so tests don't depend on the developer's setup.
+    pub fn for_tests() -> Self {
+        Self::load(Source::Vars(&[("JWT_SECRET", "test-secret")]))
+    }
+
+    fn load(source: Source) -> Self {
+        let default_timeout_ms = parse_env(source, "UPSTREAM_TIMEOUT_MS", 5000);
 
         Self {
-            port: env::var("PORT")
+            port: source
+                .var("PORT")
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .expect("PORT must be a number"),
-            jwt_secret: env::var("JWT_SECRET").expect("JWT_SECRET must be set"),
-            auth_service_url: env::var("AUTH_SERVICE_URL")
-                .unwrap_or_else(|_| "http://localhost:8081".to_string()),
-            social_service_url: env::var("SOCIAL_SERVICE_URL")
-                .unwrap_or_else(|_| "http://localhost:8083".to_string()),
-            messaging_service_url: env::var("MESSAGING_SERVICE_URL")
-                .unwrap_or_else(|_| "http://localhost:8082".to_string()),
+            tls: TlsConfig::from_env(source),
+            admin: AdminConfig::from_env(source),
+            jwt_secret: source.var("JWT_SECRET").expect("JWT_SECRET must be set"),
+            auth_service: UpstreamConfig::from_env(
+                source,
+                "auth",
+                "AUTH_SERVICE",
+                "http://localhost:8081",
+                default_timeout_ms,
+            ),
+            social_service: UpstreamConfig::from_env(
+                source,
+                "social",
+                "SOCIAL_SERVICE",
+                "http://localhost:8083",
+                default_timeout_ms,
+            ),
+            messaging_service: UpstreamConfig::from_env(
+                source,
+                "messaging",
+                "MESSAGING_SERVICE",
+                "http://localhost:8082",
+                default_timeout_ms,
+            ),
+            retry: RetryConfig {
+                max_retries: parse_env(source, "UPSTREAM_MAX_RETRIES", 2),
+                base_delay: Duration::from_millis(parse_env(
+                    source,
+                    "UPSTREAM_RETRY_BASE_DELAY_MS",
+                    50,
+                )),
+                max_delay: Duration::from_millis(parse_env(
+                    source,
+                    "UPSTREAM_RETRY_MAX_DELAY_MS",
+                    1000,
+                )),
+            },
+            circuit_breaker: CircuitBreakerConfig {
+                failure_threshold: parse_env(source, "CIRCUIT_BREAKER_FAILURE_THRESHOLD", 5),
+                open_duration: Duration::from_secs(parse_env(
+                    source,
+                    "CIRCUIT_BREAKER_OPEN_SECS",
+                    30,
+                )),
+                half_open_max_requests: parse_env(source, "CIRCUIT_BREAKER_HALF_OPEN_REQUESTS", 1),
+            },
+            health_check: HealthCheckConfig {
+                path: source
+                    .var("HEALTH_CHECK_PATH")
+                    .unwrap_or_else(|_| "/health".to_string()),
+                interval: Duration::from_secs(parse_env(source, "HEALTH_CHECK_INTERVAL_SECS", 10)),
+                timeout: Duration::from_millis(parse_env(source, "HEALTH_CHECK_TIMEOUT_MS", 2000)),
+                failure_threshold: parse_env(source, "HEALTH_CHECK_FAILURE_THRESHOLD", 3),
+                ejection_threshold: parse_env(source, "UPSTREAM_EJECTION_THRESHOLD", 3),
+                ejection_duration: Duration::from_secs(parse_env(
+                    source,
+                    "UPSTREAM_EJECTION_SECS",
+                    30,
+                )),
+            },
+            realtime: RealtimeConfig {
+                idle_timeout: Duration::from_secs(parse_env(
+                    source,
+                    "REALTIME_IDLE_TIMEOUT_SECS",
+                    120,
+                )),
+                max_connections: parse_env(source, "REALTIME_MAX_CONNECTIONS", 10000),
+                max_connections_per_user: parse_env(source, "REALTIME_MAX_CONNECTIONS_PER_USER", 5),
+            },
+            bff: BffConfig {
+                section_timeout: Duration::from_millis(parse_env(
+                    source,
+                    "BFF_SECTION_TIMEOUT_MS",
+                    2000,
+                )),
+                max_section_bytes: parse_env(source, "BFF_MAX_SECTION_BYTES", 1024 * 1024),
+            },
+            cache: CacheConfig {
+                max_entries: parse_env(source, "CACHE_MAX_ENTRIES", 10000),
+                max_entry_bytes: parse_env(source, "CACHE_MAX_ENTRY_BYTES", 1024 * 1024),
+                rules: source
+                    .var("CACHE_ROUTES")
+                    .map(|routes| parse_cache_rules(&routes))
+                    .unwrap_or_default(),
+            },
+            limits: LimitsConfig {
+                max_body_bytes: parse_env(source, "MAX_BODY_BYTES", 1024 * 1024),
+                allowed_content_types: parse_list(
+                    source,
+                    "ALLOWED_CONTENT_TYPES",
+                    "application/json",
+                ),
+                max_json_depth: parse_env(source, "MAX_JSON_DEPTH", 32),
+                header_read_timeout: Duration::from_secs(parse_env(
+                    source,
+                    "HEADER_READ_TIMEOUT_SECS",
+                    10,
+                )),
+                body_read_timeout: Duration::from_secs(parse_env(
+                    source,
+                    "BODY_READ_TIMEOUT_SECS",
+                    30,
+                )),
+                http2_keep_alive_interval: Duration::from_secs(parse_env(
+                    source,
+                    "HTTP2_KEEP_ALIVE_INTERVAL_SECS",
+                    20,
+                )),
+                http2_keep_alive_timeout: Duration::from_secs(parse_env(
+                    source,
+                    "HTTP2_KEEP_ALIVE_TIMEOUT_SECS",
+                    10,
+                )),
+                http2_max_concurrent_streams: parse_env(
+                    source,
+                    "HTTP2_MAX_CONCURRENT_STREAMS",
+                    100,
+                ),
+                rules: source
+                    .var("ROUTE_LIMITS")
+                    .map(|routes| parse_limit_rules(&routes))
+                    .unwrap_or_default(),
+            },
+            versioning: VersioningConfig::from_env(source, default_timeout_ms),
+            canary: CanaryConfig::from_env(source, default_timeout_ms),
+            cors: CorsConfig::from_env(source),
+            ip_filter: IpFilterConfig {
+                allow: parse_cidr_list(source, "IP_ALLOW_LIST", ""),
+                deny: parse_cidr_list(source, "IP_DENY_LIST", ""),
+                trusted_proxies: parse_cidr_list(source, "TRUSTED_PROXIES", ""),
+                private_networks: parse_cidr_list(
+                    source,
+                    "PRIVATE_NETWORKS",
+                    DEFAULT_PRIVATE_NETWORKS,
+                ),
+                private_paths: parse_list(source, "PRIVATE_PATHS", DEFAULT_PRIVATE_PATHS),
+                sync_interval: Duration::from_secs(parse_env(source, "IP_FILTER_SYNC_SECS", 10)),
+            },
+            security_headers: SecurityHeadersConfig {
+                hsts_max_age: Duration::from_secs(parse_env(
+                    source,
+                    "HSTS_MAX_AGE_SECS",
+                    31_536_000,
+                )),
+                referrer_policy: source
+                    .var("REFERRER_POLICY")
+                    .unwrap_or_else(|_| "strict-origin-when-cross-origin".to_string()),
+                docs_csp: source
+                    .var("DOCS_CONTENT_SECURITY_POLICY")
+                    .unwrap_or_else(|_| DEFAULT_DOCS_CSP.to_string()),
+            },
+            internal_token: source
+                .var("INTERNAL_API_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
+            openapi_cache_ttl: Duration::from_secs(parse_env(source, "OPENAPI_CACHE_TTL_SECS", 60)),
+            redis_url: source.var("REDIS_URL").ok().filter(|url| !url.is_empty()),
         }
     }
 }
+
+impl TlsConfig {
+    fn from_env(source: Source) -> Option<Self> {
+        let cert_path = source
+            .var("TLS_CERT_PATH")
+            .ok()
+            .filter(|path| !path.is_empty());
+        let key_path = source
+            .var("TLS_KEY_PATH")
+            .ok()
+            .filter(|path| !path.is_empty());
+
+        let (cert_path, key_path) = match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+            (None, None) => return None,
+            _ => panic!("TLS_CERT_PATH and TLS_KEY_PATH must be set together"),
+        };
+
+        Some(Self {
+            cert_path: PathBuf::from(cert_path),
+            key_path: PathBuf::from(key_path),
+            reload_interval: Duration::from_secs(parse_env(source, "TLS_RELOAD_INTERVAL_SECS", 30)),
+            redirect_port: source.var("TLS_REDIRECT_HTTP_PORT").ok().map(|port| {
+                port.parse()
+                    .expect("TLS_REDIRECT_HTTP_PORT must be a number")
+            }),
+            http2: source.var("HTTP2_ENABLED").map_or(true, |v| v != "false"),
+        })
+    }
+}
+
+impl AdminConfig {
+    fn from_env(source: Source) -> Option<Self> {
+        let port: u16 = source
+            .var("ADMIN_PORT")
+            .ok()
+            .filter(|port| !port.is_empty())?
+            .parse()
+            .expect("ADMIN_PORT must be a number");
+        let token = source
+            .var("ADMIN_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty())
+            .expect("ADMIN_TOKEN must be set when ADMIN_PORT is set");
+        let host: IpAddr = source
+            .var("ADMIN_BIND_ADDR")
+            .unwrap_or_else(|_| "127.0.0.1".to_string())
+            .parse()
+            .expect("ADMIN_BIND_ADDR must be an IP address");
+
+        Some(Self {
+            addr: SocketAddr::new(host, port),
+            token,
+        })
+    }
+}
+
+impl UpstreamConfig {
+    fn from_env(
+        source: Source,
+        name: &str,
+        prefix: &str,
+        default_url: &str,
+        default_timeout_ms: u64,
+    ) -> Self {
+        let urls = source
+            .var(format!("{}_URLS", prefix))
+            .or_else(|_| source.var(format!("{}_URL", prefix)))
+            .unwrap_or_else(|_| default_url.to_string())
+            .split(',')
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .collect::<Vec<_>>();
+
+        if urls.is_empty() {
+            panic!("{}_URLS must contain at least one instance", prefix);
+        }
+
+        Self {
+            name: name.to_string(),
+            urls,
+            timeout: Duration::from_millis(parse_env(
+                source,
+                &format!("{}_TIMEOUT_MS", prefix),
+                default_timeout_ms,
+            )),
+            strategy: match source.var(format!("{}_LB_STRATEGY", prefix)).as_deref() {
+                Ok("least_connections") => LoadBalancingStrategy::LeastConnections,
+                Ok("round_robin") | Err(_) => LoadBalancingStrategy::RoundRobin,
+                Ok(other) => panic!("Unknown load balancing strategy: {}", other),
+            },
+        }
+    }
+}
+
+impl CorsConfig {
+    fn from_env(source: Source) -> Self {
+        let allowed_origins = parse_list(source, "CORS_ALLOWED_ORIGINS", "");
+        let allow_credentials = source
+            .var("CORS_ALLOW_CREDENTIALS")
+            .is_ok_and(|v| v == "true");
+        if allow_credentials && allowed_origins.iter().any(|origin| origin == "*") {
+            panic!("CORS_ALLOWED_ORIGINS cannot contain * when CORS_ALLOW_CREDENTIALS is true");
+        }
+
+        Self {
+            allowed_origins,
+            allowed_methods: parse_list(source, "CORS_ALLOWED_METHODS", DEFAULT_CORS_METHODS)
+                .iter()
+                .map(|method| {
+                    Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                        .unwrap_or_else(|_| panic!("Invalid CORS method: {}", method))
+                })
+                .collect(),
+            allowed_headers: parse_header_list(
+                source,
+                "CORS_ALLOWED_HEADERS",
+                DEFAULT_CORS_HEADERS,
+            ),
+            exposed_headers: parse_header_list(
+                source,
+                "CORS_EXPOSED_HEADERS",
+                DEFAULT_CORS_EXPOSED_HEADERS,
+            ),
+            max_age: Duration::from_secs(parse_env(source, "CORS_MAX_AGE_SECS", 600)),
+            allow_credentials,
+        }
+    }
+}
+
+impl CanaryConfig {
+    fn from_env(source: Source, default_timeout_ms: u64) -> Self {
+        let upstreams = [
+            ("auth", "AUTH_SERVICE"),
+            ("social", "SOCIAL_SERVICE"),
+            ("messaging", "MESSAGING_SERVICE"),
+        ]
+        .iter()
+        .filter(|(_, prefix)| source.var(format!("{}_CANARY_URLS", prefix)).is_ok())
+        .map(|(name, prefix)| {
+            UpstreamConfig::from_env(
+                source,
+                &format!("{}-canary", name),
+                &format!("{}_CANARY", prefix),
+                "",
+                default_timeout_ms,
+            )
+        })
+        .collect();
+
+        Self {
+            header: source
+                .var("CANARY_HEADER")
+                .unwrap_or_else(|_| "x-canary".to_string())
+                .parse()
+                .expect("CANARY_HEADER must be a valid header name"),
+            cookie: source
+                .var("CANARY_COOKIE")
+                .unwrap_or_else(|_| "canary".to_string()),
+            rules: source
+                .var("CANARY_ROUTES")
+                .map(|routes| parse_canary_rules(&routes))
+                .unwrap_or_default(),
+            upstreams,
+        }
+    }
+}
+
+impl VersioningConfig {
+    fn from_env(source: Source, default_timeout_ms: u64) -> Self {
+        let versions = source
+            .var("API_VERSIONS")
+            .unwrap_or_else(|_| "v1".to_string())
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| ApiVersionConfig::from_env(source, name, default_timeout_ms))
+            .collect::<Vec<_>>();
+
+        if versions.is_empty() {
+            panic!("API_VERSIONS must contain at least one version");
+        }
+
+        let default_version = source
+            .var("API_DEFAULT_VERSION")
+            .map(|name| name.trim().to_ascii_lowercase())
+            .unwrap_or_else(|_| versions[0].name.clone());
+        if !versions
+            .iter()
+            .any(|version| version.name == default_version)
+        {
+            panic!(
+                "API_DEFAULT_VERSION {} is not listed in API_VERSIONS",
+                default_version
+            );
+        }
+
+        Self {
+            default_version,
+            versions,
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ApiVersionConfig> {
+        self.versions.iter().find(|version| version.name == name)
+    }
+
+    pub fn default_config(&self) -> &ApiVersionConfig {
+        self.find(&self.default_version)
+            .expect("default API version must be configured")
+    }
+}
+
+impl ApiVersionConfig {
+    fn from_env(source: Source, name: String, default_timeout_ms: u64) -> Self {
+        let prefix = format!("API_{}", name.to_ascii_uppercase());
+        let upstreams = [
+            ("auth", "AUTH_SERVICE"),
+            ("social", "SOCIAL_SERVICE"),
+            ("messaging", "MESSAGING_SERVICE"),
+        ]
+        .into_iter()
+        .filter_map(|(service, service_prefix)| {
+            let override_prefix = format!("{}_{}", prefix, service_prefix);
+            let configured = source.var(format!("{}_URLS", override_prefix)).is_ok()
+                || source.var(format!("{}_URL", override_prefix)).is_ok();
+            configured.then(|| {
+                UpstreamConfig::from_env(source, service, &override_prefix, "", default_timeout_ms)
+            })
+        })
+        .collect();
+
+        Self {
+            deprecated_at: parse_date_env(source, &format!("{}_DEPRECATED_AT", prefix)),
+            sunset_at: parse_date_env(source, &format!("{}_SUNSET_AT", prefix)),
+            upstreams,
+            name,
+        }
+    }
+}
+
+/// Where settings are read from. Every default lives in the loaders below, so
+/// `Config::for_tests` gets the same defaults as `Config::from_env`.
+#[derive(Clone, Copy)]
+enum Source {
+    Process,
+    Vars(&'static [(&'static str, &'static str)]),
+}
+
+impl Source {
+    fn var(self, key: impl AsRef<str>) -> Result<String, env::VarError> {
+        let key = key.as_ref();
+        match self {
+            Source::Process => env::var(key),
+            Source::Vars(vars) => vars
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+                .ok_or(env::VarError::NotPresent),
+        }
+    }
+}
+
+fn parse_date_env(source: Source, key: &str) -> Option<DateTime<Utc>> {
+    let value = source.var(key).ok().filter(|value| !value.is_empty())?;
+
+    let parsed = DateTime::parse_from_rfc3339(&value)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        })
+        .unwrap_or_else(|_| panic!("{} must be a YYYY-MM-DD or RFC 3339 date", key));
+
+    Some(parsed)
+}
+
+fn parse_cache_rules(routes: &str) -> Vec<CacheRule> {
+    routes
+        .split(';')
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .map(|rule| {
+            let mut fields = rule.split('|').map(str::trim);
+            let pattern = fields.next().unwrap_or_default().to_string();
+            let ttl = fields
+                .next()
+                .and_then(|ttl| ttl.parse().ok())
+                .unwrap_or_else(|| panic!("CACHE_ROUTES rule {} must have a TTL", rule));
+            let list = |field: Option<&str>| -> Vec<String> {
+                field
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+                    .collect()
+            };
+
+            CacheRule {
+                pattern,
+                ttl: Duration::from_secs(ttl),
+                tags: list(fields.next()),
+                vary: list(fields.next()),
+                shared: fields.next() == Some("shared"),
+            }
+        })
+        .collect()
+}
+
+fn parse_canary_rules(routes: &str) -> Vec<CanaryRule> {
+    routes
+        .split(';')
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .map(|rule| {
+            let mut fields = rule.split('|').map(str::trim);
+            let pattern = fields.next().unwrap_or_default().to_string();
+            let weight = fields
+                .next()
+                .and_then(|weight| weight.parse().ok())
+                .filter(|weight| *weight <= 100)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "CANARY_ROUTES rule {} must have a weight between 0 and 100",
+                        rule
+                    )
+                });
+
+            CanaryRule { pattern, weight }
+        })
+        .collect()
+}
+
+fn parse_limit_rules(routes: &str) -> Vec<LimitRule> {
+    routes
+        .split(';')
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .map(|rule| {
+            let mut fields = rule.split('|').map(str::trim);
+            let pattern = fields.next().unwrap_or_default().to_string();
+            let max_body_bytes = fields
+                .next()
+                .and_then(|max| max.parse().ok())
+                .unwrap_or_else(|| panic!("ROUTE_LIMITS rule {} must have a max body size", rule));
+            let content_types = fields
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .map(|value| value.trim().to_ascii_lowercase())
+                .filter(|value| !value.is_empty())
+                .collect();
+
+            LimitRule {
+                pattern,
+                max_body_bytes,
+                content_types,
+            }
+        })
+        .collect()
+}
+
+fn parse_list(source: Source, key: &str, default: &str) -> Vec<String> {
+    source
+        .var(key)
+        .unwrap_or_else(|_| default.to_string())
+        .split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+fn parse_cidr_list(source: Source, key: &str, default: &str) -> Vec<IpNet> {
+    parse_list(source, key, default)
+        .iter()
+        .map(|cidr| {
+            parse_cidr(cidr).unwrap_or_else(|| panic!("{} contains an invalid CIDR: {}", key, cidr))
+        })
+        .collect()
+}
+
+pub fn parse_cidr(value: &str) -> Option<IpNet> {
+    value
+        .parse::<IpNet>()
+        .ok()
+        .or_else(|| value.parse::<std::net::IpAddr>().ok().map(IpNet::from))
+        .map(|net| net.trunc())
+}
+
+fn parse_header_list(source: Source, key: &str, default: &str) -> Vec<HeaderName> {
+    parse_list(source, key, default)
+        .iter()
+        .map(|name| {
+            HeaderName::try_from(name.as_str())
+                .unwrap_or_else(|_| panic!("{} contains an invalid header name: {}", key, name))
+        })
+        .collect()
+}
+
+fn parse_env<T: std::str::FromStr>(source: Source, key: &str, default: T) -> T {
+    match source.var(key) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a number", key)),
+        Err(_) => default,
+    }
+}
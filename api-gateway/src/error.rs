@@ -0,0 +1,29 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use shared::ErrorBody;
+
+#[derive(Debug)]
+pub struct GatewayError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl GatewayError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for GatewayError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody::new(self.status.as_u16(), self.message);
+
+        (self.status, Json(body)).into_response()
+    }
+}
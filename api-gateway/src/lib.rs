@@ -0,0 +1,6 @@
+pub mod config;
+pub mod error;
+pub mod middleware;
+pub mod routes;
+pub mod services;
+pub mod state;
@@ -1,33 +1,200 @@
-use axum::{routing::any, Router};
-use std::sync::Arc;
-use tower_http::cors::CorsLayer;
-use tracing_subscriber;
+use std::net::SocketAddr;
 
-mod config;
-mod middleware;
-mod routes;
+use axum::{
+    routing::{any, get, post},
+    Router,
+};
+use axum_server::{Handle, Server};
+use hyper_util::rt::TokioTimer;
+use shared::RequestIdLayer;
+use tower::ServiceBuilder;
 
-use config::Config;
+use api_gateway::{
+    config::{Config, LimitsConfig},
+    middleware,
+    routes::{self, admin::AdminState},
+    services::{cache, health_check, ip_filter, security, tls},
+    state::AppState,
+};
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
 
-    let config = Arc::new(Config::from_env());
+    let state = AppState::new(Config::from_env());
+    health_check::spawn(state.clone());
+    ip_filter::spawn_sync(state.clone());
+    cache::spawn_purge_listener(state.clone());
 
-    let auth_routes = Router::new().route("/auth/{*path}", any(routes::proxy_to_auth));
+    // The admin route table lists the same paths, so both are built from `routes::*_PATHS`.
+    let auth_routes = routes::AUTH_PATHS
+        .iter()
+        .fold(Router::new(), |router, path| {
+            router.route(path, any(routes::proxy_to_auth))
+        })
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::canary::canary_middleware,
+        ));
+
+    let messaging_routes = routes::MESSAGING_PATHS
+        .iter()
+        .fold(Router::new(), |router, path| {
+            router.route(path, any(routes::proxy_to_messaging))
+        });
+
+    let social_routes = routes::SOCIAL_PATHS
+        .iter()
+        .fold(Router::new(), |router, path| {
+            router.route(path, any(routes::proxy_to_social))
+        });
+
+    let bff_routes = Router::new().route("/bff/home", get(routes::bff::home));
+
+    let protected_routes = Router::new()
+        .merge(bff_routes)
+        .merge(messaging_routes)
+        .merge(social_routes)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::cache::cache_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::canary::canary_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::auth::auth_middleware,
+        ));
 
     let app = Router::new()
+        .route("/health", get(shared::health_check))
+        .route("/openapi.json", get(routes::docs::openapi_json))
+        .route("/docs", get(routes::docs::docs_page))
+        .route("/health/upstreams", get(routes::upstreams_health))
+        .route("/internal/cache/purge", post(routes::internal::purge_cache))
+        .route(
+            "/internal/ip-rules",
+            get(routes::internal::ip_rules)
+                .post(routes::internal::add_ip_rule)
+                .delete(routes::internal::remove_ip_rule),
+        )
         .merge(auth_routes)
-        .layer(CorsLayer::permissive())
-        .with_state(config.clone());
+        .merge(protected_routes)
+        .with_state(state.clone());
+
+    // Versioning rewrites `/v1/...` paths, so it has to run before routing.
+    let app = ServiceBuilder::new()
+        .layer(RequestIdLayer::new())
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::security::security_headers_middleware,
+        ))
+        .layer(security::cors_layer(&state.config.cors))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::versioning::versioning_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::ip_filter::ip_filter_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::limits::limits_middleware,
+        ))
+        .service(app);
+    // axum-server hands requests over with hyper's body type; the router adapts them.
+    let app = Router::new().fallback_service(app);
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], state.config.port));
+
+    let limits = state.config.limits.clone();
+    let handle = Handle::new();
+
+    if let Some(admin_config) = state.config.admin.clone() {
+        let admin = routes::admin::router(AdminState {
+            app: state.clone(),
+            server: handle.clone(),
+        });
+        let listener = tokio::net::TcpListener::bind(&admin_config.addr)
+            .await
+            .expect("Failed to bind admin listener");
+
+        tracing::info!("Admin API listening on {}", admin_config.addr);
 
-    let addr = format!("0.0.0.0:{}", config.port);
-    let listener = tokio::net::TcpListener::bind(&addr)
+        tokio::spawn(async move {
+            axum::serve(listener, admin)
+                .await
+                .expect("Admin server error");
+        });
+    }
+
+    let Some(tls_config) = state.config.tls.clone() else {
+        tracing::info!("API Gateway listening on {}", addr);
+
+        let mut server = axum_server::bind(addr).handle(handle);
+        configure_http(&mut server, &limits);
+        server
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .expect("Server error");
+        return;
+    };
+
+    let rustls = tls::load(&tls_config)
         .await
-        .expect("Failed to bind server");
+        .expect("Failed to load TLS certificate");
+    tls::spawn_reloader(rustls.clone(), tls_config.clone());
+
+    if let Some(redirect_port) = tls_config.redirect_port {
+        let https_port = state.config.port;
+        let redirect = Router::new()
+            .fallback(move |req| tls::redirect_to_https(https_port, req))
+            .layer(RequestIdLayer::new());
+        let redirect_addr = SocketAddr::from(([0, 0, 0, 0], redirect_port));
+        let listener = tokio::net::TcpListener::bind(&redirect_addr)
+            .await
+            .expect("Failed to bind HTTP redirect listener");
+
+        tracing::info!("Redirecting HTTP on {} to HTTPS", redirect_addr);
 
-    tracing::info!("API Gateway listening on {}", addr);
+        tokio::spawn(async move {
+            axum::serve(listener, redirect)
+                .await
+                .expect("Redirect server error");
+        });
+    }
+
+    tracing::info!(
+        "API Gateway listening on {} (TLS, HTTP/2 {})",
+        addr,
+        if tls_config.http2 {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+
+    let mut server = axum_server::bind_rustls(addr, rustls).handle(handle);
+    configure_http(&mut server, &limits);
+    server
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .expect("Server error");
+}
 
-    axum::serve(listener, app).await.expect("Server error");
+fn configure_http<A>(server: &mut Server<A>, limits: &LimitsConfig) {
+    let builder = server.http_builder();
+    builder
+        .http1()
+        .timer(TokioTimer::new())
+        .header_read_timeout(limits.header_read_timeout);
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .keep_alive_interval(limits.http2_keep_alive_interval)
+        .keep_alive_timeout(limits.http2_keep_alive_timeout)
+        .max_concurrent_streams(limits.http2_max_concurrent_streams);
 }
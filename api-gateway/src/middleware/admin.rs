@@ -0,0 +1,30 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{error::GatewayError, routes::admin::AdminState, services::security};
+
+pub async fn admin_auth_middleware(
+    State(state): State<AdminState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(admin) = state.app.config.admin.as_ref() else {
+        return GatewayError::new(StatusCode::FORBIDDEN, "Admin API is disabled").into_response();
+    };
+
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    if !provided.is_some_and(|provided| security::token_matches(provided, &admin.token)) {
+        return GatewayError::new(StatusCode::UNAUTHORIZED, "Invalid admin token").into_response();
+    }
+
+    next.run(req).await
+}
@@ -1,26 +1,81 @@
 use axum::{
-    extract::Request,
-    http::{header, StatusCode},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
     middleware::Next,
     response::Response,
 };
-use shared::validate_token;
+use shared::{validate_token, Claims};
 
-pub async fn auth_middleware(mut req: Request, next: Next) -> Result<Response, StatusCode> {
-    let auth_header = req
-        .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok())
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+use crate::{services::realtime, state::AppState};
 
-    let token = auth_header
-        .strip_prefix("Bearer ")
-        .ok_or(StatusCode::UNAUTHORIZED)?;
+const QUERY_TOKEN_PARAMS: [&str; 2] = ["access_token", "token"];
 
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-    let claims = validate_token(token, &jwt_secret).map_err(|_| StatusCode::UNAUTHORIZED)?;
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let claims = authenticate(&mut req, &state.config.jwt_secret)?;
 
     req.extensions_mut().insert(claims);
 
     Ok(next.run(req).await)
 }
+
+pub fn authenticate(req: &mut Request, jwt_secret: &str) -> Result<Claims, StatusCode> {
+    if let Some(token) = bearer_token(req) {
+        return validate_token(&token, jwt_secret).map_err(|_| StatusCode::UNAUTHORIZED);
+    }
+
+    if !realtime::is_realtime_request(req.headers()) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let token = query_token(req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let claims = validate_token(&token, jwt_secret).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let value = HeaderValue::from_str(&format!("Bearer {}", token))
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    req.headers_mut().insert(header::AUTHORIZATION, value);
+
+    Ok(claims)
+}
+
+fn bearer_token(req: &Request) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string)
+}
+
+fn query_token(req: &Request) -> Option<String> {
+    req.uri()
+        .query()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| QUERY_TOKEN_PARAMS.contains(key))
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Removes query-string credentials so they are not forwarded upstream or logged there.
+pub fn strip_query_token(path_and_query: &str) -> String {
+    let Some((path, query)) = path_and_query.split_once('?') else {
+        return path_and_query.to_string();
+    };
+
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split_once('=').map_or(*pair, |(key, _)| key);
+            !QUERY_TOKEN_PARAMS.contains(&key)
+        })
+        .collect();
+
+    if kept.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, kept.join("&"))
+    }
+}
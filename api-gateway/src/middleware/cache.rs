@@ -0,0 +1,182 @@
+use std::time::Instant;
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use shared::{Claims, REQUEST_ID_HEADER};
+
+use crate::{
+    error::GatewayError,
+    services::{
+        cache::{self, CacheControl, CachedResponse},
+        canary::Track,
+        versioning::ApiVersion,
+    },
+    state::AppState,
+};
+
+const CACHE_TAG_HEADER: HeaderName = HeaderName::from_static("cache-tag");
+
+pub async fn cache_middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if req.method() != Method::GET {
+        return next.run(req).await;
+    }
+
+    let Some(route) = state.cache.match_route(req.uri().path()) else {
+        return next.run(req).await;
+    };
+
+    let version = req
+        .extensions()
+        .get::<ApiVersion>()
+        .map(|version| version.0.as_str())
+        .unwrap_or_default();
+    // Canary responses must never be served to users on the stable track.
+    let track = match req.extensions().get::<Track>() {
+        Some(Track::Canary) => "+canary",
+        _ => "",
+    };
+    let Some(key) = cache::cache_key(
+        route.rule,
+        &format!("{}{}", version, track),
+        req.uri()
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or_default(),
+        req.extensions()
+            .get::<Claims>()
+            .map(|claims| claims.sub.as_str()),
+    ) else {
+        return next.run(req).await;
+    };
+    let request_cache_control = CacheControl::parse(req.headers());
+
+    if !request_cache_control.no_cache && !request_cache_control.no_store {
+        if let Some(hit) = state.cache.lookup(&key, req.headers()) {
+            return hit;
+        }
+    }
+
+    let request_headers = req.headers().clone();
+    let response = next.run(req).await;
+
+    let response_cache_control = CacheControl::parse(response.headers());
+    let ttl = cache::ttl_for(route.rule, &response_cache_control);
+
+    let mut vary: Vec<HeaderName> = route
+        .rule
+        .vary
+        .iter()
+        .filter_map(|name| HeaderName::try_from(name.as_str()).ok())
+        .collect();
+    let mut vary_any = false;
+    for value in response
+        .headers()
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+    {
+        match value.trim() {
+            "*" => vary_any = true,
+            name => {
+                if let Ok(name) = HeaderName::try_from(name) {
+                    if !vary.contains(&name) {
+                        vary.push(name);
+                    }
+                }
+            }
+        }
+    }
+
+    let cacheable = response.status() == StatusCode::OK
+        && !request_cache_control.no_store
+        && !response_cache_control.no_store
+        && !response_cache_control.private
+        && !vary_any
+        && !ttl.is_zero();
+    if !cacheable {
+        return response;
+    }
+
+    let max_entry_bytes = state.cache.max_entry_bytes();
+    let (mut parts, body) = response.into_parts();
+    // Bodies too large to cache are passed through rather than buffered.
+    if body.size_hint().lower() > max_entry_bytes as u64 {
+        return Response::from_parts(parts, body);
+    }
+
+    let mut stream = body.into_data_stream();
+    let mut chunks = Vec::new();
+    let mut len = 0;
+    while let Some(chunk) = stream.next().await {
+        let Ok(chunk) = chunk else {
+            return GatewayError::new(StatusCode::BAD_GATEWAY, "Failed to read upstream response")
+                .into_response();
+        };
+        len += chunk.len();
+        chunks.push(chunk);
+        if len > max_entry_bytes {
+            let read = futures::stream::iter(chunks.into_iter().map(Ok::<_, axum::Error>));
+            return Response::from_parts(parts, Body::from_stream(read.chain(stream)));
+        }
+    }
+    let body = Bytes::from(chunks.concat());
+
+    let etag = parts
+        .headers
+        .get(header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| cache::compute_etag(&body));
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        parts.headers.insert(header::ETAG, value);
+    }
+
+    let mut tags = route.tags;
+    tags.extend(
+        parts
+            .headers
+            .get_all(&CACHE_TAG_HEADER)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty()),
+    );
+
+    let mut stored_headers = parts.headers.clone();
+    stored_headers.remove(&REQUEST_ID_HEADER);
+    stored_headers.remove(&CACHE_TAG_HEADER);
+
+    let now = Instant::now();
+    state.cache.store(
+        key,
+        &vary,
+        &request_headers,
+        tags,
+        CachedResponse {
+            status: parts.status,
+            headers: stored_headers,
+            body: body.clone(),
+            etag: etag.clone(),
+            stored_at: now,
+            expires_at: now + ttl,
+        },
+    );
+
+    let mut response = if cache::etag_matches(&request_headers, &etag) {
+        cache::not_modified(&etag)
+    } else {
+        parts.headers.remove(&CACHE_TAG_HEADER);
+        Response::from_parts(parts, Body::from(body))
+    };
+    cache::mark_miss(&mut response);
+
+    response
+}
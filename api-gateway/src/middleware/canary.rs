@@ -0,0 +1,44 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use shared::Claims;
+
+use crate::{
+    services::{canary, ip_filter::ClientIp},
+    state::AppState,
+};
+
+pub async fn canary_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    // Authenticated users stay on one track across devices; anonymous callers stick by IP.
+    let sticky_key = req
+        .extensions()
+        .get::<Claims>()
+        .map(|claims| claims.sub.clone())
+        .or_else(|| {
+            req.extensions()
+                .get::<ClientIp>()
+                .map(|ip| ip.0.to_string())
+        });
+
+    let trusted = req
+        .extensions()
+        .get::<ClientIp>()
+        .is_some_and(|ip| state.ip_filter.is_private(ip.0));
+
+    let track = canary::assign(
+        &state.config.canary,
+        req.uri().path(),
+        req.headers(),
+        sticky_key.as_deref(),
+        trusted,
+    );
+
+    req.extensions_mut().insert(track);
+    next.run(req).await
+}
@@ -0,0 +1,38 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    error::GatewayError,
+    services::ip_filter::{ClientIp, Rejection},
+    state::AppState,
+};
+
+pub async fn ip_filter_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some(ConnectInfo(peer)) = req.extensions().get::<ConnectInfo<SocketAddr>>().copied() else {
+        return next.run(req).await;
+    };
+
+    let client_ip = state.ip_filter.client_ip(peer.ip(), req.headers());
+
+    if let Err(rejection) = state.ip_filter.check(client_ip, req.uri().path()) {
+        tracing::warn!(client_ip = %client_ip, path = req.uri().path(), "Blocked request: {:?}", rejection);
+        let message = match rejection {
+            Rejection::Denied | Rejection::NotAllowed => "Access denied",
+            Rejection::PrivateOnly => "This endpoint is only reachable from private networks",
+        };
+        return GatewayError::new(StatusCode::FORBIDDEN, message).into_response();
+    }
+
+    req.extensions_mut().insert(ClientIp(client_ip));
+    next.run(req).await
+}
@@ -0,0 +1,99 @@
+use axum::{
+    body::{Body, HttpBody},
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::LengthLimitError;
+
+use crate::{
+    error::GatewayError,
+    services::limits::{self, BodyLimit},
+    state::AppState,
+};
+
+pub async fn limits_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let config = &state.config.limits;
+    let route = limits::for_path(config, req.uri().path());
+
+    let content_length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if content_length.is_some_and(|length| length > route.max_body_bytes) {
+        return too_large(route.max_body_bytes);
+    }
+
+    // HTTP/2 requests carry neither Content-Length nor Transfer-Encoding, so ask
+    // the body itself whether anything follows the headers.
+    let has_body = !req.body().is_end_stream() && req.body().size_hint().upper() != Some(0);
+    if !has_body {
+        req.extensions_mut().insert(BodyLimit(route.max_body_bytes));
+        return next.run(req).await;
+    }
+
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if !limits::content_type_allowed(route.content_types, content_type) {
+        return GatewayError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!(
+                "Unsupported content type; expected one of: {}",
+                route.content_types.join(", ")
+            ),
+        )
+        .into_response();
+    }
+    let is_json = content_type.is_some_and(|v| limits::is_json(&limits::media_type(v)));
+
+    let (mut parts, body) = req.into_parts();
+    let read = tokio::time::timeout(
+        config.body_read_timeout,
+        axum::body::to_bytes(body, route.max_body_bytes),
+    )
+    .await;
+    let body = match read {
+        Ok(Ok(body)) => body,
+        Ok(Err(e)) => {
+            if e.into_inner().downcast_ref::<LengthLimitError>().is_some() {
+                return too_large(route.max_body_bytes);
+            }
+            return GatewayError::new(StatusCode::BAD_REQUEST, "Failed to read request body")
+                .into_response();
+        }
+        Err(_) => {
+            return GatewayError::new(
+                StatusCode::REQUEST_TIMEOUT,
+                "Timed out reading request body",
+            )
+            .into_response()
+        }
+    };
+
+    if is_json && limits::json_depth_exceeds(&body, config.max_json_depth) {
+        return GatewayError::new(
+            StatusCode::BAD_REQUEST,
+            format!("JSON nesting exceeds {} levels", config.max_json_depth),
+        )
+        .into_response();
+    }
+
+    parts.extensions.insert(BodyLimit(route.max_body_bytes));
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+fn too_large(max_body_bytes: usize) -> Response {
+    GatewayError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("Request body exceeds {} bytes", max_body_bytes),
+    )
+    .into_response()
+}
@@ -1 +1,8 @@
+pub mod admin;
 pub mod auth;
+pub mod cache;
+pub mod canary;
+pub mod ip_filter;
+pub mod limits;
+pub mod security;
+pub mod versioning;
@@ -0,0 +1,17 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{services::security, state::AppState};
+
+pub async fn security_headers_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(req).await;
+    security::apply_security_headers(response.headers_mut(), &state.config.security_headers);
+    response
+}
@@ -0,0 +1,58 @@
+use axum::{
+    extract::{Request, State},
+    http::{StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+
+use crate::{
+    error::GatewayError,
+    services::versioning::{self, ApiVersion},
+    state::AppState,
+};
+
+pub async fn versioning_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let resolved =
+        match versioning::resolve(&state.config.versioning, req.uri().path(), req.headers()) {
+            Ok(resolved) => resolved,
+            Err(e) => return e.into_response(),
+        };
+    let version = resolved.version;
+
+    if versioning::is_sunset(version, Utc::now()) {
+        let mut response = GatewayError::new(
+            StatusCode::GONE,
+            format!("API version {} has been retired", version.name),
+        )
+        .into_response();
+        versioning::apply_headers(response.headers_mut(), version);
+        return response;
+    }
+
+    if let Some(path) = resolved.path {
+        let path_and_query = match req.uri().query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        };
+        match Uri::try_from(path_and_query) {
+            Ok(uri) => *req.uri_mut() = uri,
+            Err(_) => {
+                return GatewayError::new(StatusCode::BAD_REQUEST, "Invalid request path")
+                    .into_response()
+            }
+        }
+    }
+
+    req.extensions_mut()
+        .insert(ApiVersion(version.name.clone()));
+
+    let mut response = next.run(req).await;
+    versioning::apply_headers(response.headers_mut(), version);
+
+    response
+}
@@ -0,0 +1,190 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use axum_server::Handle;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use shared::RequestIdLayer;
+
+use crate::{
+    error::GatewayError,
+    middleware::admin::admin_auth_middleware,
+    routes,
+    services::upstream::{InstanceStatus, UpstreamStatus},
+    state::AppState,
+};
+
+#[derive(Clone)]
+pub struct AdminState {
+    pub app: AppState,
+    pub server: Handle,
+}
+
+#[derive(Serialize)]
+pub struct RouteEntry {
+    pub path: &'static str,
+    pub target: &'static str,
+    pub authenticated: bool,
+}
+
+#[derive(Serialize)]
+pub struct VersionEntry {
+    pub name: String,
+    pub default: bool,
+    pub deprecated_at: Option<DateTime<Utc>>,
+    pub sunset_at: Option<DateTime<Utc>>,
+    pub upstreams: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DrainRequest {
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+pub struct FlushRequest {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub all: bool,
+}
+
+pub fn router(state: AdminState) -> Router {
+    Router::new()
+        .route("/admin/routes", get(route_table))
+        .route("/admin/upstreams", get(upstreams))
+        .route("/admin/upstreams/{name}/drain", post(drain))
+        .route("/admin/upstreams/{name}/undrain", post(undrain))
+        .route("/admin/stats", get(stats))
+        .route("/admin/cache/flush", post(flush_cache))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            admin_auth_middleware,
+        ))
+        .layer(RequestIdLayer::new())
+        .with_state(state)
+}
+
+pub async fn route_table(State(state): State<AdminState>) -> Json<Value> {
+    let groups: [(&[&str], &str, bool); 5] = [
+        (routes::AUTH_PATHS, "auth", false),
+        (routes::MESSAGING_PATHS, "messaging", true),
+        (routes::SOCIAL_PATHS, "social", true),
+        (routes::BFF_PATHS, "bff", true),
+        (routes::GATEWAY_PATHS, "gateway", false),
+    ];
+
+    let routes: Vec<RouteEntry> = groups
+        .iter()
+        .flat_map(|(paths, target, authenticated)| {
+            paths.iter().map(|path| RouteEntry {
+                path,
+                target,
+                authenticated: *authenticated,
+            })
+        })
+        .collect();
+
+    let versioning = &state.app.config.versioning;
+    let versions: Vec<VersionEntry> = versioning
+        .versions
+        .iter()
+        .map(|version| VersionEntry {
+            name: version.name.clone(),
+            default: version.name == versioning.default_version,
+            deprecated_at: version.deprecated_at,
+            sunset_at: version.sunset_at,
+            upstreams: version
+                .upstreams
+                .iter()
+                .map(|upstream| upstream.name.clone())
+                .collect(),
+        })
+        .collect();
+
+    Json(json!({
+        "routes": routes,
+        "versions": versions,
+        "canary": state.app.config.canary.rules,
+    }))
+}
+
+pub async fn upstreams(State(state): State<AdminState>) -> Json<Vec<UpstreamStatus>> {
+    Json(
+        state
+            .app
+            .upstreams
+            .all()
+            .iter()
+            .map(|upstream| upstream.status())
+            .collect(),
+    )
+}
+
+pub async fn drain(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(req): Json<DrainRequest>,
+) -> Result<Json<InstanceStatus>, GatewayError> {
+    set_draining(&state, &name, &req.url, true)
+}
+
+pub async fn undrain(
+    State(state): State<AdminState>,
+    Path(name): Path<String>,
+    Json(req): Json<DrainRequest>,
+) -> Result<Json<InstanceStatus>, GatewayError> {
+    set_draining(&state, &name, &req.url, false)
+}
+
+fn set_draining(
+    state: &AdminState,
+    name: &str,
+    url: &str,
+    draining: bool,
+) -> Result<Json<InstanceStatus>, GatewayError> {
+    let upstream = state
+        .app
+        .upstreams
+        .find(name)
+        .ok_or_else(|| GatewayError::new(StatusCode::NOT_FOUND, "Unknown upstream"))?;
+
+    let url = url.trim_end_matches('/');
+    let instance = upstream
+        .instances
+        .iter()
+        .find(|instance| instance.url == url)
+        .ok_or_else(|| GatewayError::new(StatusCode::NOT_FOUND, "Unknown upstream instance"))?;
+
+    instance.set_draining(draining);
+
+    Ok(Json(instance.status()))
+}
+
+pub async fn stats(State(state): State<AdminState>) -> Json<Value> {
+    Json(json!({
+        "http_connections": state.server.connection_count(),
+        "realtime": state.app.realtime.stats(),
+        "cache": state.app.cache.stats(),
+        "ip_filter": state.app.ip_filter.snapshot(),
+    }))
+}
+
+pub async fn flush_cache(
+    State(state): State<AdminState>,
+    Json(req): Json<FlushRequest>,
+) -> Json<Value> {
+    let flushed = if req.all {
+        state.app.cache.purge_all()
+    } else {
+        state.app.cache.purge_tags(&req.tags)
+    };
+
+    tracing::info!("Flushed {} cached responses from the admin API", flushed);
+
+    Json(json!({ "flushed": flushed }))
+}
@@ -1,49 +1,24 @@
-use std::sync::Arc;
-
 use axum::{
     body::Body,
     extract::{Request, State},
-    response::IntoResponse,
+    response::Response,
+    Extension,
 };
-use reqwest::Client;
 
-use crate::config::Config;
+use crate::{
+    services::{canary::Track, proxy, versioning::ApiVersion},
+    state::AppState,
+};
 
 pub async fn proxy_to_auth(
-    State(config): State<Arc<Config>>,
+    State(state): State<AppState>,
+    Extension(version): Extension<ApiVersion>,
+    Extension(track): Extension<Track>,
     req: Request<Body>,
-) -> impl IntoResponse {
-    let client = Client::new();
-    let uri = req.uri();
-    let path = uri.path();
-
-    let url = format!("{}{}", config.auth_service_url, path);
-
-    let method = req.method().clone();
-    let headers = req.headers().clone();
-    let body = axum::body::to_bytes(req.into_body(), usize::MAX)
-        .await
-        .unwrap_or_default();
-
-    let response = client
-        .request(method, &url)
-        .headers(headers)
-        .body(body)
-        .send()
-        .await;
-
-    match response {
-        Ok(res) => {
-            let status = res.status();
-            let headers = res.headers().clone();
-            let body = res.bytes().await.unwrap_or_default();
-
-            (status, headers, body)
-        }
-        Err(_) => (
-            axum::http::StatusCode::BAD_GATEWAY,
-            axum::http::HeaderMap::new(),
-            axum::body::Bytes::new(),
-        ),
-    }
+) -> Response {
+    let upstream = state
+        .upstreams
+        .for_version(&state.upstreams.auth, &version.0);
+    let upstream = state.upstreams.for_track(upstream, track);
+    proxy::forward(&state, upstream, req).await
 }
@@ -0,0 +1,81 @@
+use axum::{extract::State, http::HeaderMap, Extension, Json};
+use serde_json::{Map, Value};
+use shared::{Claims, ErrorBody};
+
+use crate::{
+    services::{
+        aggregation::{fetch_section, SectionError},
+        canary::Track,
+        versioning::ApiVersion,
+    },
+    state::AppState,
+};
+
+#[utoipa::path(
+    get,
+    path = "/bff/home",
+    tag = "bff",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "User profile, conversations and posts; failed sections are null and described in `errors`", body = Object),
+        (status = 401, description = "Missing or invalid token", body = ErrorBody),
+    )
+)]
+pub async fn home(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Extension(version): Extension<ApiVersion>,
+    Extension(track): Extension<Track>,
+    headers: HeaderMap,
+) -> Json<Value> {
+    let timeout = state.config.bff.section_timeout;
+    let upstreams = &state.upstreams;
+    let section =
+        |upstream| upstreams.for_track(upstreams.for_version(upstream, &version.0), track);
+    let auth = section(&upstreams.auth);
+    let messaging = section(&upstreams.messaging);
+    let social = section(&upstreams.social);
+
+    let conversations_path = format!("/users/{}/conversations", claims.sub);
+    let posts_path = format!("/users/{}/posts", claims.sub);
+
+    let (user, conversations, posts) = tokio::join!(
+        fetch_section(&state, auth, "/auth/me", &headers, timeout),
+        fetch_section(&state, messaging, &conversations_path, &headers, timeout),
+        fetch_section(&state, social, &posts_path, &headers, timeout),
+    );
+
+    let mut payload = Map::new();
+    let mut errors = Map::new();
+    for (name, section) in [
+        ("user", user),
+        ("conversations", conversations),
+        ("posts", posts),
+    ] {
+        insert_section(&mut payload, &mut errors, name, section);
+    }
+    payload.insert("errors".to_string(), Value::Object(errors));
+
+    Json(Value::Object(payload))
+}
+
+fn insert_section(
+    payload: &mut Map<String, Value>,
+    errors: &mut Map<String, Value>,
+    name: &str,
+    section: Result<Value, SectionError>,
+) {
+    match section {
+        Ok(value) => {
+            payload.insert(name.to_string(), value);
+        }
+        Err(error) => {
+            tracing::warn!("BFF section {} failed: {}", name, error.error);
+            payload.insert(name.to_string(), Value::Null);
+            errors.insert(
+                name.to_string(),
+                serde_json::to_value(error).unwrap_or(Value::Null),
+            );
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use axum::{extract::State, response::Html, Json};
+use shared::{openapi::BearerSecurity, ErrorBody};
+use utoipa::OpenApi;
+
+use crate::{routes::bff, state::AppState};
+
+const DOCS_PAGE: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>Staki API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.onload = () => {
+      window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+    };
+  </script>
+</body>
+</html>
+"##;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Staki API"),
+    paths(bff::home),
+    components(schemas(ErrorBody)),
+    modifiers(&BearerSecurity),
+    tags((name = "bff", description = "Aggregated endpoints served by the gateway"))
+)]
+pub struct GatewayApiDoc;
+
+pub async fn openapi_json(State(state): State<AppState>) -> Json<utoipa::openapi::OpenApi> {
+    Json(state.openapi.get(&state, GatewayApiDoc::openapi).await)
+}
+
+pub async fn docs_page() -> Html<&'static str> {
+    Html(DOCS_PAGE)
+}
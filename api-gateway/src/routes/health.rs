@@ -0,0 +1,18 @@
+use axum::{extract::State, Json};
+use serde_json::{json, Value};
+
+use crate::state::AppState;
+
+pub async fn upstreams_health(State(state): State<AppState>) -> Json<Value> {
+    let upstreams: Vec<_> = state
+        .upstreams
+        .all()
+        .iter()
+        .map(|upstream| upstream.status())
+        .collect();
+
+    Json(json!({
+        "upstreams": upstreams,
+        "realtime": state.realtime.stats(),
+    }))
+}
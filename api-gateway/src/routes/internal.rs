@@ -0,0 +1,122 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shared::cache_purge::CachePurge;
+
+use crate::{
+    config::parse_cidr,
+    error::GatewayError,
+    services::{
+        ip_filter::{self, IpFilterSnapshot, IpList},
+        security,
+    },
+    state::AppState,
+};
+
+#[derive(Deserialize)]
+pub struct IpRuleRequest {
+    pub list: IpList,
+    pub cidr: String,
+}
+
+/// Purges the cache of the replica that receives the request only. To reach every
+/// replica, publish the same body on `shared::cache_purge::PURGE_CHANNEL`.
+pub async fn purge_cache(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<CachePurge>,
+) -> Result<Json<Value>, GatewayError> {
+    verify_internal_token(&state, &headers)?;
+
+    let purged = state.cache.apply(&req);
+
+    tracing::info!("Purged {} cached responses", purged);
+
+    Ok(Json(json!({ "purged": purged })))
+}
+
+pub async fn ip_rules(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<IpFilterSnapshot>, GatewayError> {
+    verify_internal_token(&state, &headers)?;
+
+    Ok(Json(state.ip_filter.snapshot()))
+}
+
+pub async fn add_ip_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<IpRuleRequest>,
+) -> Result<Json<IpFilterSnapshot>, GatewayError> {
+    update_ip_rule(&state, &headers, req, true).await
+}
+
+pub async fn remove_ip_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<IpRuleRequest>,
+) -> Result<Json<IpFilterSnapshot>, GatewayError> {
+    update_ip_rule(&state, &headers, req, false).await
+}
+
+async fn update_ip_rule(
+    state: &AppState,
+    headers: &HeaderMap,
+    req: IpRuleRequest,
+    add: bool,
+) -> Result<Json<IpFilterSnapshot>, GatewayError> {
+    verify_internal_token(state, headers)?;
+
+    let net = parse_cidr(&req.cidr)
+        .ok_or_else(|| GatewayError::new(StatusCode::BAD_REQUEST, "Invalid CIDR"))?;
+
+    if let Some(pool) = &state.redis {
+        ip_filter::persist(pool, req.list, net, add)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to persist IP rule: {}", e);
+                GatewayError::new(StatusCode::SERVICE_UNAVAILABLE, "Failed to persist IP rule")
+            })?;
+    }
+
+    if add {
+        state.ip_filter.add(req.list, net);
+    } else {
+        state.ip_filter.remove(req.list, net);
+    }
+    tracing::info!(
+        "{} {} {:?} list",
+        if add { "Added" } else { "Removed" },
+        net,
+        req.list
+    );
+
+    Ok(Json(state.ip_filter.snapshot()))
+}
+
+pub fn verify_internal_token(state: &AppState, headers: &HeaderMap) -> Result<(), GatewayError> {
+    let expected = state
+        .config
+        .internal_token
+        .as_deref()
+        .ok_or_else(|| GatewayError::new(StatusCode::FORBIDDEN, "Internal API is disabled"))?;
+
+    let provided = headers
+        .get("x-internal-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    if !security::token_matches(provided, expected) {
+        return Err(GatewayError::new(
+            StatusCode::UNAUTHORIZED,
+            "Invalid internal token",
+        ));
+    }
+
+    Ok(())
+}
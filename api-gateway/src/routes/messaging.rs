@@ -0,0 +1,24 @@
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    response::Response,
+    Extension,
+};
+
+use crate::{
+    services::{canary::Track, proxy, versioning::ApiVersion},
+    state::AppState,
+};
+
+pub async fn proxy_to_messaging(
+    State(state): State<AppState>,
+    Extension(version): Extension<ApiVersion>,
+    Extension(track): Extension<Track>,
+    req: Request<Body>,
+) -> Response {
+    let upstream = state
+        .upstreams
+        .for_version(&state.upstreams.messaging, &version.0);
+    let upstream = state.upstreams.for_track(upstream, track);
+    proxy::forward(&state, upstream, req).await
+}
@@ -1,3 +1,38 @@
+pub mod admin;
 pub mod auth;
+pub mod bff;
+pub mod docs;
+pub mod health;
+pub mod internal;
+pub mod messaging;
+pub mod social;
 
 pub use auth::proxy_to_auth;
+pub use health::upstreams_health;
+pub use messaging::proxy_to_messaging;
+pub use social::proxy_to_social;
+
+pub const AUTH_PATHS: &[&str] = &["/auth/{*path}"];
+
+pub const MESSAGING_PATHS: &[&str] = &[
+    "/messages",
+    "/messages/{*path}",
+    "/conversations",
+    "/conversations/{*path}",
+    "/users/{user_id}/conversations",
+    "/users/{user_id}/presence",
+    "/users/{user_id}/inbox",
+];
+
+pub const SOCIAL_PATHS: &[&str] = &["/posts", "/posts/{*path}", "/users/{user_id}/posts"];
+
+pub const BFF_PATHS: &[&str] = &["/bff/home"];
+
+pub const GATEWAY_PATHS: &[&str] = &[
+    "/health",
+    "/openapi.json",
+    "/docs",
+    "/health/upstreams",
+    "/internal/cache/purge",
+    "/internal/ip-rules",
+];
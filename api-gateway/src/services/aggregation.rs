@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, Method},
+};
+use serde::Serialize;
+use serde_json::Value;
+use shared::REQUEST_ID_HEADER;
+
+use crate::{
+    services::{proxy, upstream::Upstream},
+    state::AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct SectionError {
+    pub status: u16,
+    pub error: String,
+}
+
+pub async fn fetch_section(
+    state: &AppState,
+    upstream: &Upstream,
+    path: &str,
+    headers: &HeaderMap,
+    timeout: Duration,
+) -> Result<Value, SectionError> {
+    let mut builder = Request::builder().method(Method::GET).uri(path);
+    for name in [&header::AUTHORIZATION, &REQUEST_ID_HEADER] {
+        if let Some(value) = headers.get(name) {
+            builder = builder.header(name, value);
+        }
+    }
+    let req = builder.body(Body::empty()).map_err(|_| SectionError {
+        status: 500,
+        error: "Failed to build upstream request".to_string(),
+    })?;
+
+    // The timeout covers the body too, so a slow upstream can't hold the page.
+    let max_bytes = state.config.bff.max_section_bytes;
+    let (status, body) = tokio::time::timeout(timeout, async {
+        let res = proxy::forward(state, upstream, req).await;
+        let status = res.status();
+        axum::body::to_bytes(res.into_body(), max_bytes)
+            .await
+            .map(|body| (status, body))
+    })
+    .await
+    .map_err(|_| SectionError {
+        status: 504,
+        error: format!("Upstream {} timed out", upstream.config.name),
+    })?
+    .map_err(|_| SectionError {
+        status: 502,
+        error: format!(
+            "Response from {} is unreadable or exceeds {} bytes",
+            upstream.config.name, max_bytes
+        ),
+    })?;
+
+    if !status.is_success() {
+        let error = serde_json::from_slice::<Value>(&body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(str::to_string))
+            .unwrap_or_else(|| String::from_utf8_lossy(&body).into_owned());
+
+        return Err(SectionError {
+            status: status.as_u16(),
+            error,
+        });
+    }
+
+    serde_json::from_slice(&body).map_err(|_| SectionError {
+        status: 502,
+        error: format!("Invalid JSON from {}", upstream.config.name),
+    })
+}
@@ -0,0 +1,424 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use deadpool_redis::redis;
+use futures::StreamExt;
+use lru::LruCache;
+use serde::Serialize;
+use shared::cache_purge::{CachePurge, PURGE_CHANNEL};
+
+use crate::{
+    config::{CacheConfig, CacheRule},
+    services::pattern::match_pattern,
+    state::AppState,
+};
+
+const MAX_VARIANTS_PER_KEY: usize = 16;
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+const X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub etag: String,
+    pub stored_at: Instant,
+    pub expires_at: Instant,
+}
+
+struct Variant {
+    vary: Vec<(HeaderName, Option<HeaderValue>)>,
+    response: CachedResponse,
+}
+
+struct Entry {
+    tags: HashSet<String>,
+    variants: Vec<Variant>,
+}
+
+struct Store {
+    entries: LruCache<String, Entry>,
+    tags: HashMap<String, HashSet<String>>,
+}
+
+pub struct ResponseCache {
+    rules: Vec<CacheRule>,
+    max_entry_bytes: usize,
+    store: Mutex<Store>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+pub struct RouteMatch<'a> {
+    pub rule: &'a CacheRule,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub rules: usize,
+}
+
+#[derive(Default)]
+pub struct CacheControl {
+    pub no_cache: bool,
+    pub no_store: bool,
+    pub private: bool,
+    pub max_age: Option<u64>,
+    pub s_maxage: Option<u64>,
+}
+
+impl CacheControl {
+    pub fn parse(headers: &HeaderMap) -> Self {
+        let mut cache_control = Self::default();
+
+        for directive in headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+        {
+            let directive = directive.trim().to_ascii_lowercase();
+            let (name, value) = match directive.split_once('=') {
+                Some((name, value)) => (name.trim().to_string(), Some(value.trim().to_string())),
+                None => (directive, None),
+            };
+
+            match name.as_str() {
+                "no-cache" => cache_control.no_cache = true,
+                "no-store" => cache_control.no_store = true,
+                "private" => cache_control.private = true,
+                "max-age" => cache_control.max_age = value.and_then(|v| v.parse().ok()),
+                "s-maxage" => cache_control.s_maxage = value.and_then(|v| v.parse().ok()),
+                _ => {}
+            }
+        }
+
+        cache_control
+    }
+}
+
+impl ResponseCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        let capacity = NonZeroUsize::new(config.max_entries).unwrap_or(NonZeroUsize::MIN);
+
+        Self {
+            rules: config.rules.clone(),
+            max_entry_bytes: config.max_entry_bytes,
+            store: Mutex::new(Store {
+                entries: LruCache::new(capacity),
+                tags: HashMap::new(),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn max_entry_bytes(&self) -> usize {
+        self.max_entry_bytes
+    }
+
+    pub fn match_route(&self, path: &str) -> Option<RouteMatch<'_>> {
+        self.rules.iter().find_map(|rule| {
+            let params = match_pattern(&rule.pattern, path)?;
+            let tags = rule
+                .tags
+                .iter()
+                .map(|tag| {
+                    params.iter().fold(tag.clone(), |tag, (name, value)| {
+                        tag.replace(&format!("{{{}}}", name), value)
+                    })
+                })
+                .collect();
+
+            Some(RouteMatch { rule, tags })
+        })
+    }
+
+    pub fn lookup(&self, key: &str, request_headers: &HeaderMap) -> Option<Response> {
+        let mut store = self.store.lock().unwrap();
+        let now = Instant::now();
+
+        let response = store.entries.get_mut(key).and_then(|entry| {
+            entry
+                .variants
+                .retain(|variant| variant.response.expires_at > now);
+            entry
+                .variants
+                .iter()
+                .find(|variant| vary_matches(&variant.vary, request_headers))
+                .map(|variant| variant.response.to_response(request_headers))
+        });
+
+        match response {
+            Some(response) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(response)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn store(
+        &self,
+        key: String,
+        vary: &[HeaderName],
+        request_headers: &HeaderMap,
+        tags: Vec<String>,
+        response: CachedResponse,
+    ) {
+        let vary = vary
+            .iter()
+            .map(|name| (name.clone(), request_headers.get(name).cloned()))
+            .collect::<Vec<_>>();
+
+        let mut store = self.store.lock().unwrap();
+
+        for tag in &tags {
+            store
+                .tags
+                .entry(tag.clone())
+                .or_default()
+                .insert(key.clone());
+        }
+
+        let evicted = match store.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.tags.extend(tags);
+                entry.variants.retain(|variant| variant.vary != vary);
+                if entry.variants.len() >= MAX_VARIANTS_PER_KEY {
+                    entry.variants.remove(0);
+                }
+                entry.variants.push(Variant { vary, response });
+                None
+            }
+            None => store.entries.push(
+                key.clone(),
+                Entry {
+                    tags: tags.into_iter().collect(),
+                    variants: vec![Variant { vary, response }],
+                },
+            ),
+        };
+
+        if let Some((evicted_key, evicted_entry)) = evicted {
+            if evicted_key != key {
+                untag(&mut store.tags, &evicted_key, &evicted_entry.tags);
+            }
+        }
+    }
+
+    pub fn purge_tags(&self, tags: &[String]) -> usize {
+        let mut store = self.store.lock().unwrap();
+        let mut purged = 0;
+
+        for tag in tags {
+            let Some(keys) = store.tags.remove(tag) else {
+                continue;
+            };
+            for key in keys {
+                if let Some(entry) = store.entries.pop(&key) {
+                    untag(&mut store.tags, &key, &entry.tags);
+                    purged += 1;
+                }
+            }
+        }
+
+        purged
+    }
+
+    pub fn purge_all(&self) -> usize {
+        let mut store = self.store.lock().unwrap();
+        let purged = store.entries.len();
+        store.entries.clear();
+        store.tags.clear();
+        purged
+    }
+
+    pub fn apply(&self, purge: &CachePurge) -> usize {
+        if purge.all {
+            self.purge_all()
+        } else {
+            self.purge_tags(&purge.tags)
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.store.lock().unwrap().entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            rules: self.rules.len(),
+        }
+    }
+}
+
+impl CachedResponse {
+    pub fn to_response(&self, request_headers: &HeaderMap) -> Response {
+        let age = self.stored_at.elapsed().as_secs().to_string();
+
+        let mut response = if etag_matches(request_headers, &self.etag) {
+            not_modified(&self.etag)
+        } else {
+            (
+                self.status,
+                self.headers.clone(),
+                Body::from(self.body.clone()),
+            )
+                .into_response()
+        };
+
+        let headers = response.headers_mut();
+        headers.insert(X_CACHE, HeaderValue::from_static("HIT"));
+        if let Ok(age) = HeaderValue::from_str(&age) {
+            headers.insert(header::AGE, age);
+        }
+
+        response
+    }
+}
+
+/// Subscribes to `PURGE_CHANNEL` so purges published by any backend reach this
+/// replica. Without `REDIS_URL` only `POST /internal/cache/purge` purges the cache.
+pub fn spawn_purge_listener(state: AppState) {
+    let Some(uri) = state.config.redis_url.clone() else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut resubscribing = false;
+        loop {
+            if let Err(e) = listen(&uri, &state.cache, resubscribing).await {
+                tracing::warn!("Cache purge subscription lost: {}", e);
+            }
+            resubscribing = true;
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    });
+}
+
+async fn listen(uri: &str, cache: &ResponseCache, resubscribing: bool) -> Result<(), String> {
+    let client = redis::Client::open(uri).map_err(|e| e.to_string())?;
+    let mut pubsub = client
+        .get_async_connection()
+        .await
+        .map_err(|e| e.to_string())?
+        .into_pubsub();
+    pubsub
+        .subscribe(PURGE_CHANNEL)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Purges published while the subscriber was down are lost, so nothing cached
+    // before then can be trusted.
+    if resubscribing {
+        let purged = cache.purge_all();
+        tracing::warn!(
+            "Cache purge subscription restored; purged all {} cached responses",
+            purged
+        );
+    }
+
+    let mut messages = pubsub.on_message();
+    while let Some(msg) = messages.next().await {
+        let Ok(payload) = msg.get_payload::<String>() else {
+            continue;
+        };
+        match serde_json::from_str::<CachePurge>(&payload) {
+            Ok(purge) => {
+                let purged = cache.apply(&purge);
+                tracing::info!("Purged {} cached responses", purged);
+            }
+            Err(e) => tracing::warn!("Ignoring malformed cache purge: {}", e),
+        }
+    }
+
+    Err("connection closed".to_string())
+}
+
+pub fn compute_etag(body: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+pub fn etag_matches(request_headers: &HeaderMap, etag: &str) -> bool {
+    let normalize = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+
+    request_headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|candidate| candidate.trim() == "*" || normalize(candidate) == normalize(etag))
+}
+
+pub fn not_modified(etag: &str) -> Response {
+    let mut response = StatusCode::NOT_MODIFIED.into_response();
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+pub fn mark_miss(response: &mut Response) {
+    response
+        .headers_mut()
+        .insert(X_CACHE, HeaderValue::from_static("MISS"));
+}
+
+/// Entries of non-shared rules are scoped to the user; without one, nothing is cached.
+pub fn cache_key(
+    rule: &CacheRule,
+    prefix: &str,
+    path_and_query: &str,
+    user_id: Option<&str>,
+) -> Option<String> {
+    if rule.shared {
+        return Some(format!("{}:{}", prefix, path_and_query));
+    }
+    user_id.map(|user_id| format!("{}@{}:{}", prefix, user_id, path_and_query))
+}
+
+pub fn ttl_for(rule: &CacheRule, cache_control: &CacheControl) -> Duration {
+    cache_control
+        .s_maxage
+        .or(cache_control.max_age)
+        .map(Duration::from_secs)
+        .unwrap_or(rule.ttl)
+}
+
+fn vary_matches(vary: &[(HeaderName, Option<HeaderValue>)], request_headers: &HeaderMap) -> bool {
+    vary.iter()
+        .all(|(name, value)| request_headers.get(name) == value.as_ref())
+}
+
+fn untag(tags: &mut HashMap<String, HashSet<String>>, key: &str, entry_tags: &HashSet<String>) {
+    for tag in entry_tags {
+        if let Some(keys) = tags.get_mut(tag) {
+            keys.remove(key);
+            if keys.is_empty() {
+                tags.remove(tag);
+            }
+        }
+    }
+}
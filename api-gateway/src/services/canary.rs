@@ -0,0 +1,75 @@
+use axum::http::{header, HeaderMap};
+use serde::Serialize;
+
+use crate::{config::CanaryConfig, services::pattern::match_pattern};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Track {
+    Stable,
+    Canary,
+}
+
+pub fn assign(
+    config: &CanaryConfig,
+    path: &str,
+    headers: &HeaderMap,
+    sticky_key: Option<&str>,
+    trusted: bool,
+) -> Track {
+    let Some(rule) = config
+        .rules
+        .iter()
+        .find(|rule| match_pattern(&rule.pattern, path).is_some())
+    else {
+        return Track::Stable;
+    };
+
+    if let Some(track) = forced_track(config, headers, trusted) {
+        return track;
+    }
+
+    match sticky_key {
+        Some(key) if bucket(key) < rule.weight => Track::Canary,
+        _ => Track::Stable,
+    }
+}
+
+// Testers opt in (or out) with the cookie; internal tools calling from a private
+// network may also use the header, which wins over the cookie.
+fn forced_track(config: &CanaryConfig, headers: &HeaderMap, trusted: bool) -> Option<Track> {
+    if trusted {
+        let header = headers
+            .get(&config.header)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_track);
+        if header.is_some() {
+            return header;
+        }
+    }
+
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == config.cookie)
+        .and_then(|(_, value)| parse_track(value))
+}
+
+fn parse_track(value: &str) -> Option<Track> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "always" => Some(Track::Canary),
+        "0" | "false" | "never" => Some(Track::Stable),
+        _ => None,
+    }
+}
+
+// FNV-1a, so every gateway instance puts a user in the same bucket.
+pub fn bucket(key: &str) -> u32 {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    (hash % 100) as u32
+}
@@ -0,0 +1,161 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::config::CircuitBreakerConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerSnapshot {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub open_for_secs: Option<u64>,
+}
+
+struct Inner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    half_open_in_flight: u32,
+}
+
+/// Admission through a breaker. Dropping it without a verdict (e.g. when the
+/// request is rejected before reaching the upstream) frees its half-open slot.
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    settled: bool,
+}
+
+pub struct CircuitBreaker {
+    inner: Mutex<Inner>,
+    failure_threshold: u32,
+    open_duration: Duration,
+    half_open_max_requests: u32,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                half_open_in_flight: 0,
+            }),
+            failure_threshold: config.failure_threshold.max(1),
+            open_duration: config.open_duration,
+            half_open_max_requests: config.half_open_max_requests.max(1),
+        }
+    }
+
+    pub fn acquire(&self) -> Option<BreakerPermit<'_>> {
+        self.try_acquire().then_some(BreakerPermit {
+            breaker: self,
+            settled: false,
+        })
+    }
+
+    pub fn try_acquire(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.state == BreakerState::Open {
+            let elapsed = inner.opened_at.map(|t| t.elapsed()).unwrap_or_default();
+            if elapsed < self.open_duration {
+                return false;
+            }
+            inner.state = BreakerState::HalfOpen;
+            inner.opened_at = Some(Instant::now());
+            inner.half_open_in_flight = 0;
+        }
+
+        if inner.state == BreakerState::HalfOpen {
+            let probe_stalled = inner
+                .opened_at
+                .is_some_and(|t| t.elapsed() >= self.open_duration);
+            if probe_stalled {
+                inner.opened_at = Some(Instant::now());
+                inner.half_open_in_flight = 0;
+            }
+            if inner.half_open_in_flight >= self.half_open_max_requests {
+                return false;
+            }
+            inner.half_open_in_flight += 1;
+        }
+
+        true
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = BreakerState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        inner.half_open_in_flight = 0;
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+
+        let should_open = match inner.state {
+            BreakerState::HalfOpen => true,
+            BreakerState::Closed => inner.consecutive_failures >= self.failure_threshold,
+            BreakerState::Open => false,
+        };
+
+        if should_open {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Some(Instant::now());
+            inner.half_open_in_flight = 0;
+        }
+    }
+
+    /// Gives back a half-open slot without recording an outcome.
+    pub fn release(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::HalfOpen {
+            inner.half_open_in_flight = inner.half_open_in_flight.saturating_sub(1);
+        }
+    }
+
+    pub fn snapshot(&self) -> BreakerSnapshot {
+        let inner = self.inner.lock().unwrap();
+        BreakerSnapshot {
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            open_for_secs: inner
+                .opened_at
+                .filter(|_| inner.state == BreakerState::Open)
+                .map(|t| t.elapsed().as_secs()),
+        }
+    }
+}
+
+impl BreakerPermit<'_> {
+    pub fn record(mut self, failed: bool) {
+        self.settled = true;
+        if failed {
+            self.breaker.record_failure();
+        } else {
+            self.breaker.record_success();
+        }
+    }
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.breaker.release();
+        }
+    }
+}
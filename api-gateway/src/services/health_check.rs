@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use futures::future::join_all;
+
+use crate::services::upstream::{Instance, Upstream};
+use crate::state::AppState;
+
+pub fn spawn(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(state.config.health_check.interval);
+        loop {
+            interval.tick().await;
+            probe_all(&state).await;
+        }
+    });
+}
+
+async fn probe_all(state: &AppState) {
+    let probes = state.upstreams.all().into_iter().flat_map(|upstream| {
+        upstream
+            .instances
+            .iter()
+            .map(move |instance| probe(state, upstream, instance.clone()))
+    });
+
+    join_all(probes).await;
+}
+
+async fn probe(state: &AppState, upstream: &Upstream, instance: Arc<Instance>) {
+    let url = format!("{}{}", instance.url, state.config.health_check.path);
+
+    let healthy = match state
+        .client
+        .get(&url)
+        .timeout(state.config.health_check.timeout)
+        .send()
+        .await
+    {
+        Ok(res) => res.status().is_success(),
+        Err(e) => {
+            tracing::debug!("Health check for {} failed: {}", instance.url, e);
+            false
+        }
+    };
+
+    upstream.record_probe(&instance, healthy);
+}
@@ -0,0 +1,218 @@
+use std::{net::IpAddr, sync::RwLock};
+
+use axum::http::HeaderMap;
+use deadpool_redis::{redis, Connection, Pool};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::IpFilterConfig, services::pattern::match_pattern, state::AppState};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpList {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Denied,
+    NotAllowed,
+    PrivateOnly,
+}
+
+#[derive(Default)]
+struct Lists {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+}
+
+pub struct IpFilter {
+    config: IpFilterConfig,
+    runtime: RwLock<Lists>,
+}
+
+#[derive(Serialize)]
+pub struct IpFilterSnapshot {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub runtime_allow: Vec<String>,
+    pub runtime_deny: Vec<String>,
+    pub trusted_proxies: Vec<String>,
+    pub private_paths: Vec<String>,
+}
+
+impl IpList {
+    fn redis_key(self) -> &'static str {
+        match self {
+            IpList::Allow => "gateway:ip_filter:allow",
+            IpList::Deny => "gateway:ip_filter:deny",
+        }
+    }
+}
+
+impl IpFilter {
+    pub fn new(config: &IpFilterConfig) -> Self {
+        Self {
+            config: config.clone(),
+            runtime: RwLock::new(Lists::default()),
+        }
+    }
+
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !contains(&self.config.trusted_proxies, peer) {
+            return peer;
+        }
+
+        // Walk right to left: the first hop we don't operate is the client.
+        let forwarded: Vec<IpAddr> = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect();
+
+        let mut client = peer;
+        for ip in forwarded.into_iter().rev() {
+            client = ip;
+            if !contains(&self.config.trusted_proxies, ip) {
+                break;
+            }
+        }
+        client
+    }
+
+    pub fn check(&self, ip: IpAddr, path: &str) -> Result<(), Rejection> {
+        let runtime = self.runtime.read().unwrap();
+
+        if contains(&self.config.deny, ip) || contains(&runtime.deny, ip) {
+            return Err(Rejection::Denied);
+        }
+
+        let has_allow_list = !self.config.allow.is_empty() || !runtime.allow.is_empty();
+        if has_allow_list && !contains(&self.config.allow, ip) && !contains(&runtime.allow, ip) {
+            return Err(Rejection::NotAllowed);
+        }
+
+        let is_private_path = self
+            .config
+            .private_paths
+            .iter()
+            .any(|pattern| match_pattern(pattern, path).is_some());
+        if is_private_path && !contains(&self.config.private_networks, ip) {
+            return Err(Rejection::PrivateOnly);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_private(&self, ip: IpAddr) -> bool {
+        contains(&self.config.private_networks, ip)
+    }
+
+    pub fn add(&self, list: IpList, net: IpNet) {
+        let mut runtime = self.runtime.write().unwrap();
+        let entries = runtime.list_mut(list);
+        if !entries.contains(&net) {
+            entries.push(net);
+        }
+    }
+
+    pub fn remove(&self, list: IpList, net: IpNet) {
+        self.runtime
+            .write()
+            .unwrap()
+            .list_mut(list)
+            .retain(|entry| *entry != net);
+    }
+
+    pub fn replace_runtime(&self, allow: Vec<IpNet>, deny: Vec<IpNet>) {
+        *self.runtime.write().unwrap() = Lists { allow, deny };
+    }
+
+    pub fn snapshot(&self) -> IpFilterSnapshot {
+        let runtime = self.runtime.read().unwrap();
+        let strings = |nets: &[IpNet]| nets.iter().map(ToString::to_string).collect();
+
+        IpFilterSnapshot {
+            allow: strings(&self.config.allow),
+            deny: strings(&self.config.deny),
+            runtime_allow: strings(&runtime.allow),
+            runtime_deny: strings(&runtime.deny),
+            trusted_proxies: strings(&self.config.trusted_proxies),
+            private_paths: self.config.private_paths.clone(),
+        }
+    }
+}
+
+impl Lists {
+    fn list_mut(&mut self, list: IpList) -> &mut Vec<IpNet> {
+        match list {
+            IpList::Allow => &mut self.allow,
+            IpList::Deny => &mut self.deny,
+        }
+    }
+}
+
+pub async fn persist(pool: &Pool, list: IpList, net: IpNet, add: bool) -> Result<(), String> {
+    let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+
+    redis::cmd(if add { "SADD" } else { "SREM" })
+        .arg(list.redis_key())
+        .arg(net.to_string())
+        .query_async::<_, ()>(&mut conn)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub fn spawn_sync(state: AppState) {
+    let Some(pool) = state.redis.clone() else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(state.config.ip_filter.sync_interval);
+        loop {
+            interval.tick().await;
+            match load(&pool).await {
+                Ok((allow, deny)) => state.ip_filter.replace_runtime(allow, deny),
+                Err(e) => tracing::warn!("Failed to sync IP filter from Redis: {}", e),
+            }
+        }
+    });
+}
+
+async fn load(pool: &Pool) -> Result<(Vec<IpNet>, Vec<IpNet>), String> {
+    let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+
+    let allow = members(&mut conn, IpList::Allow).await?;
+    let deny = members(&mut conn, IpList::Deny).await?;
+    Ok((allow, deny))
+}
+
+async fn members(conn: &mut Connection, list: IpList) -> Result<Vec<IpNet>, String> {
+    let members: Vec<String> = redis::cmd("SMEMBERS")
+        .arg(list.redis_key())
+        .query_async(conn)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(members
+        .iter()
+        .filter_map(|cidr| cidr.parse().ok())
+        .collect())
+}
+
+fn contains(nets: &[IpNet], ip: IpAddr) -> bool {
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    };
+    nets.iter().any(|net| net.contains(&ip))
+}
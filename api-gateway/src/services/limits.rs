@@ -0,0 +1,89 @@
+use crate::{config::LimitsConfig, services::pattern::match_pattern};
+
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimit(pub usize);
+
+pub struct RouteLimits<'a> {
+    pub max_body_bytes: usize,
+    pub content_types: &'a [String],
+}
+
+pub fn for_path<'a>(config: &'a LimitsConfig, path: &str) -> RouteLimits<'a> {
+    match config
+        .rules
+        .iter()
+        .find(|rule| match_pattern(&rule.pattern, path).is_some())
+    {
+        Some(rule) => RouteLimits {
+            max_body_bytes: rule.max_body_bytes,
+            content_types: if rule.content_types.is_empty() {
+                &config.allowed_content_types
+            } else {
+                &rule.content_types
+            },
+        },
+        None => RouteLimits {
+            max_body_bytes: config.max_body_bytes,
+            content_types: &config.allowed_content_types,
+        },
+    }
+}
+
+pub fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+pub fn content_type_allowed(allowed: &[String], content_type: Option<&str>) -> bool {
+    let Some(media_type) = content_type.map(media_type) else {
+        return false;
+    };
+
+    allowed.iter().any(|allowed| {
+        allowed == "*/*"
+            || *allowed == media_type
+            || allowed
+                .strip_suffix("/*")
+                .is_some_and(|prefix| media_type.split('/').next() == Some(prefix))
+    })
+}
+
+pub fn is_json(media_type: &str) -> bool {
+    media_type == "application/json" || media_type.ends_with("+json")
+}
+
+pub fn json_depth_exceeds(body: &[u8], max_depth: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for &byte in body {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => {
+                depth += 1;
+                if depth > max_depth {
+                    return true;
+                }
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    false
+}
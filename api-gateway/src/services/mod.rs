@@ -0,0 +1,15 @@
+pub mod aggregation;
+pub mod cache;
+pub mod canary;
+pub mod circuit_breaker;
+pub mod health_check;
+pub mod ip_filter;
+pub mod limits;
+pub mod openapi;
+pub mod pattern;
+pub mod proxy;
+pub mod realtime;
+pub mod security;
+pub mod tls;
+pub mod upstream;
+pub mod versioning;
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use axum::http::HeaderMap;
+use tokio::sync::Mutex;
+use utoipa::openapi::{path::Paths, OpenApi};
+
+use crate::{
+    services::{aggregation::fetch_section, upstream::Upstream},
+    state::AppState,
+};
+
+const PRIVATE_PATHS: [&str; 2] = ["/health", "/openapi.json"];
+
+/// Merged spec, rebuilt at most once per TTL. Incomplete specs (an upstream
+/// was unreachable) are not cached.
+pub struct SpecCache {
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, OpenApi)>>,
+}
+
+impl SpecCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub async fn get(&self, state: &AppState, gateway_spec: impl FnOnce() -> OpenApi) -> OpenApi {
+        // Held while rebuilding so concurrent requests wait instead of refetching.
+        let mut cached = self.cached.lock().await;
+        if let Some((built_at, spec)) = cached.as_ref() {
+            if built_at.elapsed() < self.ttl {
+                return spec.clone();
+            }
+        }
+
+        let (spec, complete) = aggregated_spec(state, gateway_spec()).await;
+        if complete {
+            *cached = Some((Instant::now(), spec.clone()));
+        }
+        spec
+    }
+}
+
+pub async fn aggregated_spec(state: &AppState, mut spec: OpenApi) -> (OpenApi, bool) {
+    let version = &state.config.versioning.default_version;
+    spec.paths = rewrite_paths(spec.paths, version);
+
+    let upstreams = &state.upstreams;
+    let (auth, messaging, social) = tokio::join!(
+        fetch_spec(state, &upstreams.auth),
+        fetch_spec(state, &upstreams.messaging),
+        fetch_spec(state, &upstreams.social),
+    );
+
+    let mut complete = true;
+    for upstream_spec in [auth, messaging, social] {
+        match upstream_spec {
+            Some(upstream_spec) => spec.merge(upstream_spec),
+            None => complete = false,
+        }
+    }
+
+    (spec, complete)
+}
+
+async fn fetch_spec(state: &AppState, upstream: &Upstream) -> Option<OpenApi> {
+    let timeout = state.config.bff.section_timeout;
+    let value =
+        match fetch_section(state, upstream, "/openapi.json", &HeaderMap::new(), timeout).await {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to fetch OpenAPI spec from {}: {}",
+                    upstream.config.name,
+                    e.error
+                );
+                return None;
+            }
+        };
+
+    let mut spec: OpenApi = match serde_json::from_value(value) {
+        Ok(spec) => spec,
+        Err(e) => {
+            tracing::warn!("Invalid OpenAPI spec from {}: {}", upstream.config.name, e);
+            return None;
+        }
+    };
+
+    spec.paths = rewrite_paths(spec.paths, &state.config.versioning.default_version);
+    Some(spec)
+}
+
+fn rewrite_paths(paths: Paths, version: &str) -> Paths {
+    let mut rewritten = Paths::new();
+    for (path, item) in paths.paths {
+        if PRIVATE_PATHS.contains(&path.as_str()) || path.starts_with("/internal/") {
+            continue;
+        }
+        rewritten.paths.insert(public_path(&path, version), item);
+    }
+    rewritten
+}
+
+pub fn public_path(path: &str, version: &str) -> String {
+    format!("/{}{}", version, path)
+}
@@ -0,0 +1,34 @@
+pub fn match_pattern(pattern: &str, path: &str) -> Option<Vec<(String, String)>> {
+    let pattern_segments: Vec<&str> = pattern.trim_matches('/').split('/').collect();
+    let path_segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let mut params = Vec::new();
+    for (index, expected) in pattern_segments.iter().enumerate() {
+        let param = expected
+            .strip_prefix('{')
+            .and_then(|name| name.strip_suffix('}'));
+
+        if let Some(name) = param.and_then(|name| name.strip_prefix('*')) {
+            let rest = path_segments.get(index..).unwrap_or_default().join("/");
+            if index + 1 != pattern_segments.len() || rest.is_empty() {
+                return None;
+            }
+            params.push((name.to_string(), rest));
+            return Some(params);
+        }
+
+        let actual = path_segments.get(index)?;
+        match param {
+            Some(name) if !actual.is_empty() => params.push((name.to_string(), actual.to_string())),
+            Some(_) => return None,
+            None if expected == actual => {}
+            None => return None,
+        }
+    }
+
+    if pattern_segments.len() != path_segments.len() {
+        return None;
+    }
+
+    Some(params)
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::Request,
+    http::{header, HeaderMap, HeaderName, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::StreamExt;
+use rand::Rng;
+
+use crate::{
+    error::GatewayError,
+    services::{
+        limits::BodyLimit,
+        realtime,
+        upstream::{InstanceGuard, Upstream},
+    },
+    state::AppState,
+};
+
+const HOP_BY_HOP_HEADERS: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+enum Outcome {
+    Response(reqwest::Response),
+    Timeout,
+    Unreachable,
+}
+
+pub async fn forward(state: &AppState, upstream: &Upstream, req: Request<Body>) -> Response {
+    let Some(permit) = upstream.breaker.acquire() else {
+        tracing::warn!("Circuit open for upstream {}", upstream.config.name);
+        return GatewayError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Upstream {} is unavailable", upstream.config.name),
+        )
+        .into_response();
+    };
+
+    if realtime::is_realtime_request(req.headers()) {
+        return realtime::forward(state, upstream, req, permit).await;
+    }
+
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_else(|| "/".to_string());
+
+    let method = req.method().clone();
+    let headers = strip_hop_by_hop(req.headers(), true);
+    let body_limit = req
+        .extensions()
+        .get::<BodyLimit>()
+        .map_or(state.config.limits.max_body_bytes, |limit| limit.0);
+    let body = match axum::body::to_bytes(req.into_body(), body_limit).await {
+        Ok(body) => body,
+        Err(_) => {
+            return GatewayError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Request body exceeds {} bytes", body_limit),
+            )
+            .into_response()
+        }
+    };
+
+    let max_retries = if is_idempotent(&method) {
+        state.config.retry.max_retries
+    } else {
+        0
+    };
+
+    let mut attempt = 0;
+    loop {
+        let Some(guard) = upstream.select() else {
+            permit.record(true);
+            return GatewayError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("No healthy instance for upstream {}", upstream.config.name),
+            )
+            .into_response();
+        };
+
+        let url = format!("{}{}", guard.instance.url, path_and_query);
+        let outcome = send_once(state, upstream, &method, &url, &headers, body.clone()).await;
+
+        let retryable = match &outcome {
+            Outcome::Response(res) => is_upstream_failure(res.status()),
+            Outcome::Timeout | Outcome::Unreachable => true,
+        };
+
+        if retryable {
+            upstream.record_failure(&guard.instance);
+        } else {
+            upstream.record_success(&guard.instance);
+        }
+
+        if retryable && attempt < max_retries {
+            drop(guard);
+            let delay = backoff_delay(state, attempt);
+            tracing::debug!(
+                "Retrying {} {} on upstream {} in {:?} (attempt {})",
+                method,
+                url,
+                upstream.config.name,
+                delay,
+                attempt + 1
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            continue;
+        }
+
+        return match outcome {
+            Outcome::Response(res) => {
+                permit.record(is_upstream_failure(res.status()));
+                into_axum_response(res, guard)
+            }
+            Outcome::Timeout => {
+                permit.record(true);
+                GatewayError::new(
+                    StatusCode::GATEWAY_TIMEOUT,
+                    format!("Upstream {} timed out", upstream.config.name),
+                )
+                .into_response()
+            }
+            Outcome::Unreachable => {
+                permit.record(true);
+                GatewayError::new(
+                    StatusCode::BAD_GATEWAY,
+                    format!("Upstream {} is unreachable", upstream.config.name),
+                )
+                .into_response()
+            }
+        };
+    }
+}
+
+async fn send_once(
+    state: &AppState,
+    upstream: &Upstream,
+    method: &Method,
+    url: &str,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Outcome {
+    let result = state
+        .client
+        .request(method.clone(), url)
+        .headers(headers.clone())
+        .body(body)
+        .timeout(upstream.config.timeout)
+        .send()
+        .await;
+
+    match result {
+        Ok(res) => Outcome::Response(res),
+        Err(e) if e.is_timeout() => Outcome::Timeout,
+        Err(e) => {
+            tracing::error!("Upstream {} request failed: {}", upstream.config.name, e);
+            Outcome::Unreachable
+        }
+    }
+}
+
+/// Streams the upstream body through rather than buffering it, so its size
+/// doesn't matter; the instance counts as busy until the body is sent.
+fn into_axum_response(res: reqwest::Response, guard: InstanceGuard) -> Response {
+    let status = res.status();
+    let headers = strip_hop_by_hop(res.headers(), false);
+
+    let body = res.bytes_stream().map(move |chunk| {
+        let _busy = &guard;
+        chunk
+    });
+    (status, headers, Body::from_stream(body)).into_response()
+}
+
+pub fn strip_hop_by_hop(headers: &HeaderMap, strip_host: bool) -> HeaderMap {
+    let mut filtered = headers.clone();
+    for name in HOP_BY_HOP_HEADERS {
+        filtered.remove(HeaderName::from_static(name));
+    }
+    if strip_host {
+        filtered.remove(header::HOST);
+    }
+    filtered
+}
+
+/// Statuses that mean the upstream itself is unhealthy, as opposed to an
+/// application error it returned on purpose.
+pub fn is_upstream_failure(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE
+    )
+}
+
+fn backoff_delay(state: &AppState, attempt: u32) -> Duration {
+    let retry = &state.config.retry;
+    let exponential = retry
+        .base_delay
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(retry.max_delay);
+    let max_ms = exponential.as_millis() as u64;
+
+    Duration::from_millis(rand::thread_rng().gen_range(0..=max_ms))
+}
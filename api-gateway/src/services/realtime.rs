@@ -0,0 +1,462 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{
+        ws::{self, WebSocket, WebSocketUpgrade},
+        FromRequestParts, Request,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use shared::Claims;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{self, client::IntoClientRequest, protocol::frame::coding::CloseCode},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{
+    config::RealtimeConfig,
+    error::GatewayError,
+    middleware::auth::{authenticate, strip_query_token},
+    services::{
+        circuit_breaker::BreakerPermit,
+        proxy::{is_upstream_failure, strip_hop_by_hop},
+        upstream::{InstanceGuard, Upstream},
+    },
+    state::AppState,
+};
+
+type UpstreamSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const WEBSOCKET_HANDSHAKE_HEADERS: [&str; 4] = [
+    "sec-websocket-key",
+    "sec-websocket-version",
+    "sec-websocket-extensions",
+    "sec-websocket-accept",
+];
+
+pub struct ConnectionLimiter {
+    max_connections: usize,
+    max_connections_per_user: usize,
+    inner: Mutex<LimiterState>,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    total: usize,
+    per_user: HashMap<String, usize>,
+}
+
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    user_id: String,
+}
+
+pub enum LimitExceeded {
+    Global,
+    PerUser,
+}
+
+#[derive(Serialize)]
+pub struct ConnectionStats {
+    pub active_connections: usize,
+    pub connected_users: usize,
+    pub max_connections: usize,
+    pub max_connections_per_user: usize,
+}
+
+impl ConnectionLimiter {
+    pub fn new(config: &RealtimeConfig) -> Self {
+        Self {
+            max_connections: config.max_connections,
+            max_connections_per_user: config.max_connections_per_user,
+            inner: Mutex::new(LimiterState::default()),
+        }
+    }
+
+    pub fn try_acquire(self: &Arc<Self>, user_id: &str) -> Result<ConnectionPermit, LimitExceeded> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.total >= self.max_connections {
+            return Err(LimitExceeded::Global);
+        }
+
+        let user_connections = inner.per_user.entry(user_id.to_string()).or_insert(0);
+        if *user_connections >= self.max_connections_per_user {
+            return Err(LimitExceeded::PerUser);
+        }
+
+        *user_connections += 1;
+        inner.total += 1;
+
+        Ok(ConnectionPermit {
+            limiter: self.clone(),
+            user_id: user_id.to_string(),
+        })
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        let inner = self.inner.lock().unwrap();
+        ConnectionStats {
+            active_connections: inner.total,
+            connected_users: inner.per_user.len(),
+            max_connections: self.max_connections,
+            max_connections_per_user: self.max_connections_per_user,
+        }
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut inner = self.limiter.inner.lock().unwrap();
+        inner.total = inner.total.saturating_sub(1);
+        if let Some(count) = inner.per_user.get_mut(&self.user_id) {
+            *count -= 1;
+            if *count == 0 {
+                inner.per_user.remove(&self.user_id);
+            }
+        }
+    }
+}
+
+pub fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+}
+
+pub fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"))
+}
+
+pub fn is_realtime_request(headers: &HeaderMap) -> bool {
+    is_websocket_upgrade(headers) || is_event_stream(headers)
+}
+
+pub async fn forward(
+    state: &AppState,
+    upstream: &Upstream,
+    mut req: Request<Body>,
+    breaker: BreakerPermit<'_>,
+) -> Response {
+    let claims = match req.extensions().get::<Claims>().cloned() {
+        Some(claims) => claims,
+        None => match authenticate(&mut req, &state.config.jwt_secret) {
+            Ok(claims) => claims,
+            Err(status) => return GatewayError::new(status, "Unauthorized").into_response(),
+        },
+    };
+
+    let permit = match state.realtime.try_acquire(&claims.sub) {
+        Ok(permit) => permit,
+        Err(LimitExceeded::Global) => {
+            return GatewayError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Too many realtime connections",
+            )
+            .into_response()
+        }
+        Err(LimitExceeded::PerUser) => {
+            return GatewayError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many realtime connections for this user",
+            )
+            .into_response()
+        }
+    };
+
+    let Some(guard) = upstream.select() else {
+        breaker.record(true);
+        return GatewayError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("No healthy instance for upstream {}", upstream.config.name),
+        )
+        .into_response();
+    };
+
+    if is_websocket_upgrade(req.headers()) {
+        tunnel_websocket(state, upstream, req, permit, guard, breaker).await
+    } else {
+        stream_events(state, upstream, req, permit, guard, breaker).await
+    }
+}
+
+async fn tunnel_websocket(
+    state: &AppState,
+    upstream: &Upstream,
+    req: Request<Body>,
+    permit: ConnectionPermit,
+    guard: InstanceGuard,
+    breaker: BreakerPermit<'_>,
+) -> Response {
+    let (mut parts, _body) = req.into_parts();
+
+    let ws = match WebSocketUpgrade::from_request_parts(&mut parts, &()).await {
+        Ok(ws) => ws,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let path_and_query = strip_query_token(
+        parts
+            .uri
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or("/"),
+    );
+    let url = format!(
+        "{}{}",
+        websocket_base_url(&guard.instance.url),
+        path_and_query
+    );
+
+    let mut upstream_req = match url.as_str().into_client_request() {
+        Ok(upstream_req) => upstream_req,
+        Err(_) => {
+            return GatewayError::new(StatusCode::BAD_GATEWAY, "Invalid upstream URL")
+                .into_response()
+        }
+    };
+
+    for (name, value) in strip_hop_by_hop(&parts.headers, true).iter() {
+        if !WEBSOCKET_HANDSHAKE_HEADERS.contains(&name.as_str()) {
+            upstream_req
+                .headers_mut()
+                .insert(name.clone(), value.clone());
+        }
+    }
+
+    let connect = tokio_tungstenite::connect_async(upstream_req);
+    let (upstream_socket, handshake) = match tokio::time::timeout(upstream.config.timeout, connect)
+        .await
+    {
+        Ok(Ok(connected)) => connected,
+        Ok(Err(tungstenite::Error::Http(res))) => {
+            record_handshake_result(upstream, &guard, breaker, is_upstream_failure(res.status()));
+            return GatewayError::new(
+                res.status(),
+                format!(
+                    "Upstream {} rejected the WebSocket handshake",
+                    upstream.config.name
+                ),
+            )
+            .into_response();
+        }
+        Ok(Err(e)) => {
+            tracing::error!(
+                "WebSocket handshake with upstream {} failed: {}",
+                upstream.config.name,
+                e
+            );
+            record_handshake_result(upstream, &guard, breaker, true);
+            return GatewayError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Upstream {} is unreachable", upstream.config.name),
+            )
+            .into_response();
+        }
+        Err(_) => {
+            record_handshake_result(upstream, &guard, breaker, true);
+            return GatewayError::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("Upstream {} timed out", upstream.config.name),
+            )
+            .into_response();
+        }
+    };
+    record_handshake_result(upstream, &guard, breaker, false);
+
+    let protocol = handshake
+        .headers()
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let ws = match protocol {
+        Some(protocol) => ws.protocols([protocol]),
+        None => ws,
+    };
+
+    let idle_timeout = state.config.realtime.idle_timeout;
+    ws.on_upgrade(move |client| async move {
+        pump(client, upstream_socket, idle_timeout).await;
+        drop(guard);
+        drop(permit);
+    })
+}
+
+async fn stream_events(
+    state: &AppState,
+    upstream: &Upstream,
+    req: Request<Body>,
+    permit: ConnectionPermit,
+    guard: InstanceGuard,
+    breaker: BreakerPermit<'_>,
+) -> Response {
+    let path_and_query = strip_query_token(
+        req.uri()
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or("/"),
+    );
+    let url = format!("{}{}", guard.instance.url, path_and_query);
+
+    let send = state
+        .client
+        .request(req.method().clone(), &url)
+        .headers(strip_hop_by_hop(req.headers(), true))
+        .send();
+
+    let res = match tokio::time::timeout(upstream.config.timeout, send).await {
+        Ok(Ok(res)) => res,
+        Ok(Err(e)) => {
+            tracing::error!("Upstream {} request failed: {}", upstream.config.name, e);
+            record_handshake_result(upstream, &guard, breaker, true);
+            return GatewayError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Upstream {} is unreachable", upstream.config.name),
+            )
+            .into_response();
+        }
+        Err(_) => {
+            record_handshake_result(upstream, &guard, breaker, true);
+            return GatewayError::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("Upstream {} timed out", upstream.config.name),
+            )
+            .into_response();
+        }
+    };
+    record_handshake_result(upstream, &guard, breaker, is_upstream_failure(res.status()));
+
+    let status = res.status();
+    let headers = strip_hop_by_hop(res.headers(), false);
+    let idle_timeout = state.config.realtime.idle_timeout;
+
+    let events = futures::stream::unfold(
+        (Box::pin(res.bytes_stream()), permit, guard),
+        move |(mut stream, permit, guard)| async move {
+            match tokio::time::timeout(idle_timeout, stream.next()).await {
+                Ok(Some(chunk)) => Some((chunk, (stream, permit, guard))),
+                Ok(None) => None,
+                Err(_) => {
+                    tracing::debug!("Closing idle event stream from {}", guard.instance.url);
+                    None
+                }
+            }
+        },
+    );
+
+    (status, headers, Body::from_stream(events)).into_response()
+}
+
+async fn pump(client: WebSocket, upstream: UpstreamSocket, idle_timeout: Duration) {
+    enum Frame {
+        Client(Option<Result<ws::Message, axum::Error>>),
+        Upstream(Option<Result<tungstenite::Message, tungstenite::Error>>),
+    }
+
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+
+    loop {
+        let next = tokio::time::timeout(idle_timeout, async {
+            tokio::select! {
+                msg = client_rx.next() => Frame::Client(msg),
+                msg = upstream_rx.next() => Frame::Upstream(msg),
+            }
+        })
+        .await;
+
+        match next {
+            Ok(Frame::Client(Some(Ok(msg)))) => {
+                let closing = matches!(msg, ws::Message::Close(_));
+                if upstream_tx.send(to_upstream_message(msg)).await.is_err() || closing {
+                    break;
+                }
+            }
+            Ok(Frame::Upstream(Some(Ok(msg)))) => {
+                let Some(msg) = to_client_message(msg) else {
+                    continue;
+                };
+                let closing = matches!(msg, ws::Message::Close(_));
+                if client_tx.send(msg).await.is_err() || closing {
+                    break;
+                }
+            }
+            Ok(_) => break,
+            Err(_) => {
+                tracing::debug!("Closing idle WebSocket tunnel");
+                break;
+            }
+        }
+    }
+
+    let _ = client_tx.close().await;
+    let _ = upstream_tx.close().await;
+}
+
+fn to_upstream_message(msg: ws::Message) -> tungstenite::Message {
+    match msg {
+        ws::Message::Text(text) => tungstenite::Message::text(text.as_str()),
+        ws::Message::Binary(data) => tungstenite::Message::Binary(data),
+        ws::Message::Ping(data) => tungstenite::Message::Ping(data),
+        ws::Message::Pong(data) => tungstenite::Message::Pong(data),
+        ws::Message::Close(frame) => {
+            tungstenite::Message::Close(frame.map(|frame| tungstenite::protocol::CloseFrame {
+                code: CloseCode::from(frame.code),
+                reason: frame.reason.as_str().into(),
+            }))
+        }
+    }
+}
+
+fn to_client_message(msg: tungstenite::Message) -> Option<ws::Message> {
+    match msg {
+        tungstenite::Message::Text(text) => Some(ws::Message::Text(text.as_str().into())),
+        tungstenite::Message::Binary(data) => Some(ws::Message::Binary(data)),
+        tungstenite::Message::Ping(data) => Some(ws::Message::Ping(data)),
+        tungstenite::Message::Pong(data) => Some(ws::Message::Pong(data)),
+        tungstenite::Message::Close(frame) => {
+            Some(ws::Message::Close(frame.map(|frame| ws::CloseFrame {
+                code: frame.code.into(),
+                reason: frame.reason.as_str().into(),
+            })))
+        }
+        tungstenite::Message::Frame(_) => None,
+    }
+}
+
+fn record_handshake_result(
+    upstream: &Upstream,
+    guard: &InstanceGuard,
+    breaker: BreakerPermit<'_>,
+    failed: bool,
+) {
+    if failed {
+        upstream.record_failure(&guard.instance);
+    } else {
+        upstream.record_success(&guard.instance);
+    }
+    breaker.record(failed);
+}
+
+fn websocket_base_url(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        url.to_string()
+    }
+}
@@ -0,0 +1,62 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use subtle::ConstantTimeEq;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::{CorsConfig, SecurityHeadersConfig};
+
+pub fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let allow_origin = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(config.allowed_methods.clone())
+        .allow_headers(config.allowed_headers.clone())
+        .expose_headers(config.exposed_headers.clone())
+        .max_age(config.max_age)
+        .allow_credentials(config.allow_credentials)
+}
+
+pub fn apply_security_headers(headers: &mut HeaderMap, config: &SecurityHeadersConfig) {
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    if !config.hsts_max_age.is_zero() {
+        let hsts = format!(
+            "max-age={}; includeSubDomains",
+            config.hsts_max_age.as_secs()
+        );
+        if let Ok(value) = HeaderValue::from_str(&hsts) {
+            headers.insert(header::STRICT_TRANSPORT_SECURITY, value);
+        }
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&config.referrer_policy) {
+        headers.insert(header::REFERRER_POLICY, value);
+    }
+
+    let is_html = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"));
+    if is_html && !headers.contains_key(header::CONTENT_SECURITY_POLICY) {
+        if let Ok(value) = HeaderValue::from_str(&config.docs_csp) {
+            headers.insert(header::CONTENT_SECURITY_POLICY, value);
+        }
+    }
+}
+
+/// Compares secrets in constant time so response timing leaks nothing about the token.
+pub fn token_matches(provided: &str, expected: &str) -> bool {
+    provided.as_bytes().ct_eq(expected.as_bytes()).into()
+}
@@ -0,0 +1,115 @@
+use std::{io, path::Path, sync::Arc, time::SystemTime};
+
+use axum::{
+    extract::Request,
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::{
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    ServerConfig,
+};
+
+use crate::{config::TlsConfig, error::GatewayError};
+
+pub async fn load(config: &TlsConfig) -> io::Result<RustlsConfig> {
+    Ok(RustlsConfig::from_config(Arc::new(
+        server_config(config).await?,
+    )))
+}
+
+pub fn spawn_reloader(rustls: RustlsConfig, config: TlsConfig) {
+    tokio::spawn(async move {
+        let mut last_modified = modified(&config);
+        let mut interval = tokio::time::interval(config.reload_interval);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let current = modified(&config);
+            if current == last_modified {
+                continue;
+            }
+
+            match server_config(&config).await {
+                Ok(server_config) => {
+                    rustls.reload_from_config(Arc::new(server_config));
+                    last_modified = current;
+                    tracing::info!("Reloaded TLS certificate from {:?}", config.cert_path);
+                }
+                Err(e) => {
+                    // Keep serving the previous certificate until the files are valid again.
+                    tracing::error!("Failed to reload TLS certificate: {}", e);
+                }
+            }
+        }
+    });
+}
+
+pub async fn redirect_to_https(https_port: u16, req: Request) -> Response {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|v| v.to_str().ok());
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+
+    match https_url(host, path_and_query, https_port) {
+        Some(url) => Redirect::permanent(&url).into_response(),
+        None => GatewayError::new(StatusCode::BAD_REQUEST, "Missing Host header").into_response(),
+    }
+}
+
+pub fn https_url(host: Option<&str>, path_and_query: &str, https_port: u16) -> Option<String> {
+    let host = host.filter(|host| !host.is_empty())?;
+    let hostname = match host.rsplit_once(':') {
+        Some((name, port))
+            if port.chars().all(|c| c.is_ascii_digit())
+                && (!name.contains(':') || name.ends_with(']')) =>
+        {
+            name
+        }
+        _ => host,
+    };
+
+    let url = if https_port == 443 {
+        format!("https://{}{}", hostname, path_and_query)
+    } else {
+        format!("https://{}:{}{}", hostname, https_port, path_and_query)
+    };
+    Some(url)
+}
+
+/// Certificate, key and ALPN together, so a reload swaps in one complete config
+/// and no handshake is served without h2.
+async fn server_config(config: &TlsConfig) -> io::Result<ServerConfig> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+
+    let cert = tokio::fs::read(&config.cert_path).await?;
+    let key = tokio::fs::read(&config.key_path).await?;
+    let certs = CertificateDer::pem_slice_iter(&cert)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| invalid(e.to_string()))?;
+    let key = PrivateKeyDer::from_pem_slice(&key).map_err(|e| invalid(e.to_string()))?;
+
+    let mut server_config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid(e.to_string()))?;
+    server_config.alpn_protocols = if config.http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    Ok(server_config)
+}
+
+fn modified(config: &TlsConfig) -> (Option<SystemTime>, Option<SystemTime>) {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    (modified(&config.cert_path), modified(&config.key_path))
+}
@@ -0,0 +1,320 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::config::{Config, LoadBalancingStrategy, UpstreamConfig};
+use crate::services::{
+    canary::Track,
+    circuit_breaker::{BreakerSnapshot, CircuitBreaker},
+};
+
+pub struct Instance {
+    pub url: String,
+    healthy: AtomicBool,
+    draining: AtomicBool,
+    active_connections: AtomicUsize,
+    consecutive_failures: AtomicU32,
+    failed_probes: AtomicU32,
+    ejected_until: Mutex<Option<Instant>>,
+}
+
+pub struct InstanceGuard {
+    pub instance: Arc<Instance>,
+}
+
+pub struct Upstream {
+    pub config: UpstreamConfig,
+    pub breaker: CircuitBreaker,
+    pub instances: Vec<Arc<Instance>>,
+    next: AtomicUsize,
+    probe_failure_threshold: u32,
+    ejection_threshold: u32,
+    ejection_duration: Duration,
+}
+
+#[derive(Serialize)]
+pub struct InstanceStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub ejected: bool,
+    pub draining: bool,
+    pub active_connections: usize,
+    pub consecutive_failures: u32,
+}
+
+#[derive(Serialize)]
+pub struct UpstreamStatus {
+    pub name: String,
+    pub strategy: LoadBalancingStrategy,
+    pub timeout_ms: u128,
+    pub circuit_breaker: BreakerSnapshot,
+    pub instances: Vec<InstanceStatus>,
+}
+
+impl Instance {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            healthy: AtomicBool::new(true),
+            draining: AtomicBool::new(false),
+            active_connections: AtomicUsize::new(0),
+            consecutive_failures: AtomicU32::new(0),
+            failed_probes: AtomicU32::new(0),
+            ejected_until: Mutex::new(None),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed) && !self.is_draining() && !self.is_ejected()
+    }
+
+    pub fn is_ejected(&self) -> bool {
+        let mut ejected_until = self.ejected_until.lock().unwrap();
+        match *ejected_until {
+            Some(until) if Instant::now() < until => true,
+            Some(_) => {
+                *ejected_until = None;
+                self.consecutive_failures.store(0, Ordering::Relaxed);
+                tracing::info!("Upstream instance {} reinstated", self.url);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    pub fn set_draining(&self, draining: bool) {
+        if self.draining.swap(draining, Ordering::Relaxed) != draining {
+            tracing::info!(
+                "Upstream instance {} {}",
+                self.url,
+                if draining {
+                    "draining"
+                } else {
+                    "back in rotation"
+                }
+            );
+        }
+    }
+
+    pub fn active_connections(&self) -> usize {
+        self.active_connections.load(Ordering::Relaxed)
+    }
+
+    /// Active probe state only: a passive ejection runs its full `ejection_duration`
+    /// even if `/health` keeps answering, since real traffic is what failed.
+    pub fn set_healthy(&self, healthy: bool) {
+        let was_healthy = self.healthy.swap(healthy, Ordering::Relaxed);
+        if healthy {
+            if !was_healthy {
+                tracing::info!("Upstream instance {} passed its health check", self.url);
+            }
+        } else if was_healthy {
+            tracing::warn!("Upstream instance {} failed its health check", self.url);
+        }
+    }
+
+    pub fn status(&self) -> InstanceStatus {
+        InstanceStatus {
+            url: self.url.clone(),
+            healthy: self.healthy.load(Ordering::Relaxed),
+            ejected: self.is_ejected(),
+            draining: self.is_draining(),
+            active_connections: self.active_connections(),
+            consecutive_failures: self.consecutive_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl InstanceGuard {
+    fn new(instance: Arc<Instance>) -> Self {
+        instance.active_connections.fetch_add(1, Ordering::Relaxed);
+        Self { instance }
+    }
+}
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        self.instance
+            .active_connections
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Upstream {
+    pub fn new(config: &UpstreamConfig, app_config: &Config) -> Self {
+        Self {
+            config: config.clone(),
+            breaker: CircuitBreaker::new(&app_config.circuit_breaker),
+            instances: config
+                .urls
+                .iter()
+                .map(|url| Arc::new(Instance::new(url.clone())))
+                .collect(),
+            next: AtomicUsize::new(0),
+            probe_failure_threshold: app_config.health_check.failure_threshold.max(1),
+            ejection_threshold: app_config.health_check.ejection_threshold.max(1),
+            ejection_duration: app_config.health_check.ejection_duration,
+        }
+    }
+
+    pub fn select(&self) -> Option<InstanceGuard> {
+        let available: Vec<&Arc<Instance>> = self
+            .instances
+            .iter()
+            .filter(|instance| instance.is_available())
+            .collect();
+
+        if available.is_empty() {
+            return None;
+        }
+
+        let selected = match self.config.strategy {
+            LoadBalancingStrategy::RoundRobin => {
+                let index = self.next.fetch_add(1, Ordering::Relaxed) % available.len();
+                available[index]
+            }
+            LoadBalancingStrategy::LeastConnections => {
+                let offset = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..available.len())
+                    .map(|i| available[(offset + i) % available.len()])
+                    .min_by_key(|instance| instance.active_connections())?
+            }
+        };
+
+        Some(InstanceGuard::new(selected.clone()))
+    }
+
+    pub fn record_success(&self, instance: &Instance) {
+        instance.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self, instance: &Instance) {
+        let failures = instance
+            .consecutive_failures
+            .fetch_add(1, Ordering::Relaxed)
+            + 1;
+        if failures >= self.ejection_threshold {
+            let mut ejected_until = instance.ejected_until.lock().unwrap();
+            if ejected_until.is_none() {
+                tracing::warn!(
+                    "Ejecting upstream instance {} after {} consecutive errors",
+                    instance.url,
+                    failures
+                );
+                *ejected_until = Some(Instant::now() + self.ejection_duration);
+            }
+        }
+    }
+
+    /// Applies an active health probe; a single failed probe is not enough to
+    /// take an instance out of rotation.
+    pub fn record_probe(&self, instance: &Instance, healthy: bool) {
+        if healthy {
+            instance.failed_probes.store(0, Ordering::Relaxed);
+            instance.set_healthy(true);
+            return;
+        }
+
+        let failed = instance.failed_probes.fetch_add(1, Ordering::Relaxed) + 1;
+        if failed >= self.probe_failure_threshold {
+            instance.set_healthy(false);
+        }
+    }
+
+    pub fn status(&self) -> UpstreamStatus {
+        UpstreamStatus {
+            name: self.config.name.clone(),
+            strategy: self.config.strategy,
+            timeout_ms: self.config.timeout.as_millis(),
+            circuit_breaker: self.breaker.snapshot(),
+            instances: self.instances.iter().map(|i| i.status()).collect(),
+        }
+    }
+}
+
+pub struct Upstreams {
+    pub auth: Upstream,
+    pub social: Upstream,
+    pub messaging: Upstream,
+    versioned: Vec<Upstream>,
+    canary: Vec<Upstream>,
+}
+
+impl Upstreams {
+    pub fn new(config: &Config) -> Self {
+        let versioned = config
+            .versioning
+            .versions
+            .iter()
+            .flat_map(|version| {
+                version.upstreams.iter().map(|upstream| {
+                    let mut upstream_config = upstream.clone();
+                    upstream_config.name = format!("{}@{}", upstream.name, version.name);
+                    Upstream::new(&upstream_config, config)
+                })
+            })
+            .collect();
+
+        Self {
+            auth: Upstream::new(&config.auth_service, config),
+            social: Upstream::new(&config.social_service, config),
+            messaging: Upstream::new(&config.messaging_service, config),
+            versioned,
+            canary: config
+                .canary
+                .upstreams
+                .iter()
+                .map(|upstream| Upstream::new(upstream, config))
+                .collect(),
+        }
+    }
+
+    pub fn for_version<'a>(&'a self, default: &'a Upstream, version: &str) -> &'a Upstream {
+        let name = format!("{}@{}", default.config.name, version);
+        self.versioned
+            .iter()
+            .find(|upstream| upstream.config.name == name)
+            .unwrap_or(default)
+    }
+
+    pub fn for_track<'a>(&'a self, upstream: &'a Upstream, track: Track) -> &'a Upstream {
+        if track == Track::Stable {
+            return upstream;
+        }
+
+        // Canaries are declared per service, so `messaging@v2` uses `messaging-canary`.
+        let service = upstream
+            .config
+            .name
+            .split_once('@')
+            .map_or(upstream.config.name.as_str(), |(service, _)| service);
+        let name = format!("{}-canary", service);
+        self.canary
+            .iter()
+            .find(|canary| canary.config.name == name)
+            .unwrap_or(upstream)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Upstream> {
+        self.all()
+            .into_iter()
+            .find(|upstream| upstream.config.name == name)
+    }
+
+    pub fn all(&self) -> Vec<&Upstream> {
+        let mut all = vec![&self.auth, &self.social, &self.messaging];
+        all.extend(self.versioned.iter());
+        all.extend(self.canary.iter());
+        all
+    }
+}
@@ -0,0 +1,101 @@
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use chrono::{DateTime, Utc};
+
+use crate::{
+    config::{ApiVersionConfig, VersioningConfig},
+    error::GatewayError,
+};
+
+pub const ACCEPT_VERSION: HeaderName = HeaderName::from_static("accept-version");
+pub const API_VERSION: HeaderName = HeaderName::from_static("api-version");
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiVersion(pub String);
+
+pub struct ResolvedVersion<'a> {
+    pub version: &'a ApiVersionConfig,
+    pub path: Option<String>,
+}
+
+pub fn resolve<'a>(
+    config: &'a VersioningConfig,
+    path: &str,
+    headers: &HeaderMap,
+) -> Result<ResolvedVersion<'a>, GatewayError> {
+    if let Some((prefix, rest)) = split_version_prefix(path) {
+        let version = config.find(prefix).ok_or_else(|| {
+            GatewayError::new(
+                StatusCode::NOT_FOUND,
+                format!("Unsupported API version: {}", prefix),
+            )
+        })?;
+        return Ok(ResolvedVersion {
+            version,
+            path: Some(rest.to_string()),
+        });
+    }
+
+    let version = match headers.get(&ACCEPT_VERSION) {
+        Some(value) => {
+            let requested = value.to_str().map(normalize).unwrap_or_default();
+            config.find(&requested).ok_or_else(|| {
+                GatewayError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Unsupported API version: {}", requested),
+                )
+            })?
+        }
+        None => config.default_config(),
+    };
+
+    Ok(ResolvedVersion {
+        version,
+        path: None,
+    })
+}
+
+pub fn is_sunset(version: &ApiVersionConfig, now: DateTime<Utc>) -> bool {
+    version.sunset_at.is_some_and(|sunset| sunset <= now)
+}
+
+pub fn apply_headers(headers: &mut HeaderMap, version: &ApiVersionConfig) {
+    if let Ok(value) = HeaderValue::from_str(&version.name) {
+        headers.insert(API_VERSION, value);
+    }
+    headers.append(header::VARY, HeaderValue::from_static("accept-version"));
+
+    if let Some(deprecated_at) = version.deprecated_at {
+        if let Ok(value) = HeaderValue::from_str(&format!("@{}", deprecated_at.timestamp())) {
+            headers.insert(DEPRECATION, value);
+        }
+    }
+    if let Some(sunset_at) = version.sunset_at {
+        let date = sunset_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            headers.insert(SUNSET, value);
+        }
+    }
+}
+
+fn split_version_prefix(path: &str) -> Option<(&str, &str)> {
+    let trimmed = path.strip_prefix('/')?;
+    let (segment, rest) = match trimmed.find('/') {
+        Some(index) => (&trimmed[..index], &trimmed[index..]),
+        None => (trimmed, "/"),
+    };
+
+    let digits = segment.strip_prefix('v')?;
+    let is_version = !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit());
+    is_version.then_some((segment, rest))
+}
+
+fn normalize(value: &str) -> String {
+    let value = value.trim().to_ascii_lowercase();
+    if value.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("v{}", value)
+    } else {
+        value
+    }
+}
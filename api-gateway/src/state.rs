@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use deadpool_redis::{Config as RedisConfig, Pool, Runtime};
+use reqwest::Client;
+
+use crate::{
+    config::Config,
+    services::{
+        cache::ResponseCache, ip_filter::IpFilter, openapi::SpecCache, realtime::ConnectionLimiter,
+        upstream::Upstreams,
+    },
+};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub client: Client,
+    pub upstreams: Arc<Upstreams>,
+    pub realtime: Arc<ConnectionLimiter>,
+    pub cache: Arc<ResponseCache>,
+    pub ip_filter: Arc<IpFilter>,
+    pub openapi: Arc<SpecCache>,
+    pub redis: Option<Arc<Pool>>,
+}
+
+impl AppState {
+    pub fn new(config: Config) -> Self {
+        let upstreams = Upstreams::new(&config);
+        let realtime = ConnectionLimiter::new(&config.realtime);
+        let cache = ResponseCache::new(&config.cache);
+        let ip_filter = IpFilter::new(&config.ip_filter);
+        let openapi = SpecCache::new(config.openapi_cache_ttl);
+        let redis = config.redis_url.as_ref().map(|url| {
+            RedisConfig::from_url(url)
+                .create_pool(Some(Runtime::Tokio1))
+                .expect("Failed to create Redis pool")
+        });
+
+        Self {
+            config: Arc::new(config),
+            client: Client::new(),
+            upstreams: Arc::new(upstreams),
+            realtime: Arc::new(realtime),
+            cache: Arc::new(cache),
+            ip_filter: Arc::new(ip_filter),
+            openapi: Arc::new(openapi),
+            redis: redis.map(Arc::new),
+        }
+    }
+}
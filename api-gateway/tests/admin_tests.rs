@@ -0,0 +1,144 @@
+use api_gateway::config::{AdminConfig, Config};
+use api_gateway::routes::admin::{self, AdminState};
+use api_gateway::state::AppState;
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use axum_server::Handle;
+use tower::ServiceExt;
+
+const ADMIN_TOKEN: &str = "admin-secret";
+
+fn state() -> AppState {
+    let mut config = Config::for_tests();
+    config.admin = Some(AdminConfig {
+        addr: "127.0.0.1:9090".parse().unwrap(),
+        token: ADMIN_TOKEN.to_string(),
+    });
+    config.messaging_service.urls = vec![
+        "http://messaging-1:8082".to_string(),
+        "http://messaging-2:8082".to_string(),
+    ];
+    AppState::new(config)
+}
+
+fn app(state: AppState) -> Router {
+    admin::router(AdminState {
+        app: state,
+        server: Handle::new(),
+    })
+}
+
+fn request(method: Method, uri: &str, body: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", ADMIN_TOKEN))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_rejects_missing_or_wrong_token() {
+    let app = app(state());
+
+    let missing = Request::builder()
+        .uri("/admin/stats")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(missing).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let wrong = Request::builder()
+        .uri("/admin/stats")
+        .header(header::AUTHORIZATION, "Bearer nope")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(wrong).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_drained_instance_leaves_rotation() {
+    let state = state();
+    let app = app(state.clone());
+
+    let response = app
+        .clone()
+        .oneshot(request(
+            Method::POST,
+            "/admin/upstreams/messaging/drain",
+            r#"{"url":"http://messaging-1:8082"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for _ in 0..4 {
+        let guard = state.upstreams.messaging.select().unwrap();
+        assert_eq!(guard.instance.url, "http://messaging-2:8082");
+    }
+
+    let response = app
+        .oneshot(request(
+            Method::POST,
+            "/admin/upstreams/messaging/undrain",
+            r#"{"url":"http://messaging-1:8082"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let selected: Vec<String> = (0..2)
+        .map(|_| {
+            state
+                .upstreams
+                .messaging
+                .select()
+                .unwrap()
+                .instance
+                .url
+                .clone()
+        })
+        .collect();
+    assert!(selected.contains(&"http://messaging-1:8082".to_string()));
+}
+
+#[tokio::test]
+async fn test_drain_unknown_instance_is_not_found() {
+    let app = app(state());
+
+    let response = app
+        .oneshot(request(
+            Method::POST,
+            "/admin/upstreams/messaging/drain",
+            r#"{"url":"http://messaging-9:8082"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_route_table_lists_proxied_paths() {
+    let app = app(state());
+
+    let response = app
+        .oneshot(request(Method::GET, "/admin/routes", ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let routes = body["routes"].as_array().unwrap();
+    assert!(routes
+        .iter()
+        .any(|route| route["path"] == "/messages" && route["target"] == "messaging"));
+    assert_eq!(body["versions"][0]["name"], "v1");
+}
@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use api_gateway::config::Config;
+use api_gateway::routes::bff;
+use api_gateway::services::{canary::Track, versioning::ApiVersion};
+use api_gateway::state::AppState;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::get,
+    Extension, Json, Router,
+};
+use serde_json::{json, Value};
+use shared::Claims;
+use tokio::net::TcpListener;
+
+const USER_ID: &str = "user-1";
+
+async fn serve(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+async fn closed_port() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+fn auth_service() -> Router {
+    Router::new().route(
+        "/auth/me",
+        get(|| async { Json(json!({ "id": USER_ID, "email": "user@example.com" })) }),
+    )
+}
+
+fn messaging_service() -> Router {
+    Router::new().route(
+        "/users/{user_id}/conversations",
+        get(|| async { Json(json!([{ "name": "general" }])) }),
+    )
+}
+
+fn slow_social_service() -> Router {
+    Router::new().route(
+        "/users/{user_id}/posts",
+        get(|| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Json(json!([]))
+        }),
+    )
+}
+
+fn failing_social_service() -> Router {
+    Router::new().route(
+        "/users/{user_id}/posts",
+        get(|| async {
+            (
+                StatusCode::FORBIDDEN,
+                Json(json!({ "error": "Posts are private" })),
+            )
+        }),
+    )
+}
+
+fn config(auth: String, messaging: String, social: String) -> Config {
+    let mut config = Config::for_tests();
+    config.auth_service.urls = vec![auth];
+    config.messaging_service.urls = vec![messaging];
+    config.social_service.urls = vec![social];
+    config.bff.section_timeout = Duration::from_millis(300);
+    config.bff.max_section_bytes = 64 * 1024;
+    config
+}
+
+async fn home(auth: String, messaging: String, social: String) -> Value {
+    home_on(config(auth, messaging, social), Track::Stable).await
+}
+
+async fn home_on(config: Config, track: Track) -> Value {
+    let version = config.versioning.default_version.clone();
+
+    let claims = Claims {
+        sub: USER_ID.to_string(),
+        email: "user@example.com".to_string(),
+        exp: i64::MAX,
+    };
+
+    let Json(body) = bff::home(
+        State(AppState::new(config)),
+        Extension(claims),
+        Extension(ApiVersion(version)),
+        Extension(track),
+        HeaderMap::new(),
+    )
+    .await;
+    body
+}
+
+#[tokio::test]
+async fn test_aggregates_all_sections() {
+    let social = Router::new().route(
+        "/users/{user_id}/posts",
+        get(|| async { Json(json!([{ "content": "hello" }])) }),
+    );
+
+    let body = home(
+        serve(auth_service()).await,
+        serve(messaging_service()).await,
+        serve(social).await,
+    )
+    .await;
+
+    assert_eq!(body["user"]["id"], USER_ID);
+    assert_eq!(body["conversations"][0]["name"], "general");
+    assert_eq!(body["posts"][0]["content"], "hello");
+    assert_eq!(body["errors"], json!({}));
+}
+
+#[tokio::test]
+async fn test_timed_out_section_is_null() {
+    let body = home(
+        serve(auth_service()).await,
+        serve(messaging_service()).await,
+        serve(slow_social_service()).await,
+    )
+    .await;
+
+    assert_eq!(body["user"]["id"], USER_ID);
+    assert_eq!(body["conversations"][0]["name"], "general");
+    assert_eq!(body["posts"], Value::Null);
+    assert_eq!(body["errors"]["posts"]["status"], 504);
+}
+
+#[tokio::test]
+async fn test_upstream_error_is_reported() {
+    let body = home(
+        serve(auth_service()).await,
+        closed_port().await,
+        serve(failing_social_service()).await,
+    )
+    .await;
+
+    assert_eq!(body["user"]["id"], USER_ID);
+    assert_eq!(body["conversations"], Value::Null);
+    assert_eq!(body["errors"]["conversations"]["status"], 502);
+    assert_eq!(body["posts"], Value::Null);
+    assert_eq!(body["errors"]["posts"]["status"], 403);
+    assert_eq!(body["errors"]["posts"]["error"], "Posts are private");
+    assert!(body["errors"].get("user").is_none());
+}
+
+#[tokio::test]
+async fn test_oversized_section_fails() {
+    let social = Router::new().route(
+        "/users/{user_id}/posts",
+        get(|| async { Json(json!([{ "content": "x".repeat(128 * 1024) }])) }),
+    );
+
+    let body = home(
+        serve(auth_service()).await,
+        serve(messaging_service()).await,
+        serve(social).await,
+    )
+    .await;
+
+    assert_eq!(body["user"]["id"], USER_ID);
+    assert_eq!(body["posts"], Value::Null);
+    assert_eq!(body["errors"]["posts"]["status"], 502);
+}
+
+#[tokio::test]
+async fn test_canary_track_uses_canary_upstreams() {
+    let social = |content: &'static str| {
+        Router::new().route(
+            "/users/{user_id}/posts",
+            get(move || async move { Json(json!([{ "content": content }])) }),
+        )
+    };
+    let mut config = config(
+        serve(auth_service()).await,
+        serve(messaging_service()).await,
+        serve(social("stable")).await,
+    );
+    let mut canary = config.social_service.clone();
+    canary.name = "social-canary".to_string();
+    canary.urls = vec![serve(social("canary")).await];
+    config.canary.upstreams = vec![canary];
+
+    let stable = home_on(config.clone(), Track::Stable).await;
+    assert_eq!(stable["posts"][0]["content"], "stable");
+
+    let body = home_on(config, Track::Canary).await;
+    assert_eq!(body["posts"][0]["content"], "canary");
+    // Services without a canary stay on their stable upstream.
+    assert_eq!(body["user"]["id"], USER_ID);
+}
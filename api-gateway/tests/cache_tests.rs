@@ -0,0 +1,197 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use api_gateway::config::{CacheConfig, CacheRule, Config};
+use api_gateway::middleware::cache::cache_middleware;
+use api_gateway::services::cache::{cache_key, CachedResponse, ResponseCache};
+use api_gateway::state::AppState;
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Request, StatusCode},
+    routing::get,
+    Router,
+};
+use http_body_util::BodyExt;
+use shared::cache_purge::CachePurge;
+use tower::ServiceExt;
+
+fn cache() -> ResponseCache {
+    ResponseCache::new(&CacheConfig {
+        max_entries: 100,
+        max_entry_bytes: 1024,
+        rules: vec![CacheRule {
+            pattern: "/users/{user_id}/posts".to_string(),
+            ttl: Duration::from_secs(30),
+            tags: vec!["user-posts:{user_id}".to_string()],
+            vary: vec![],
+            shared: false,
+        }],
+    })
+}
+
+fn cached(body: &'static str) -> CachedResponse {
+    let now = Instant::now();
+    CachedResponse {
+        status: StatusCode::OK,
+        headers: HeaderMap::new(),
+        body: Bytes::from_static(body.as_bytes()),
+        etag: "\"abc\"".to_string(),
+        stored_at: now,
+        expires_at: now + Duration::from_secs(30),
+    }
+}
+
+fn headers(authorization: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::AUTHORIZATION,
+        HeaderValue::from_str(authorization).unwrap(),
+    );
+    headers
+}
+
+#[test]
+fn test_match_route_renders_tags() {
+    let cache = cache();
+
+    let route = cache.match_route("/users/42/posts").unwrap();
+    assert_eq!(route.tags, vec!["user-posts:42".to_string()]);
+
+    assert!(cache.match_route("/users/42/conversations").is_none());
+    assert!(cache.match_route("/posts/42").is_none());
+}
+
+#[test]
+fn test_vary_authorization_separates_variants() {
+    let cache = cache();
+    let vary = [HeaderName::from_static("authorization")];
+
+    cache.store(
+        "/users/42/posts".to_string(),
+        &vary,
+        &headers("Bearer alice"),
+        vec![],
+        cached("alice"),
+    );
+
+    assert!(cache
+        .lookup("/users/42/posts", &headers("Bearer alice"))
+        .is_some());
+    assert!(cache
+        .lookup("/users/42/posts", &headers("Bearer bob"))
+        .is_none());
+}
+
+#[test]
+fn test_if_none_match_returns_not_modified() {
+    let cache = cache();
+    cache.store(
+        "/users/42/posts".to_string(),
+        &[],
+        &HeaderMap::new(),
+        vec![],
+        cached("posts"),
+    );
+
+    let mut request_headers = HeaderMap::new();
+    request_headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"abc\""));
+
+    let response = cache.lookup("/users/42/posts", &request_headers).unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[test]
+fn test_purge_by_tag() {
+    let cache = cache();
+    cache.store(
+        "/users/42/posts".to_string(),
+        &[],
+        &HeaderMap::new(),
+        vec!["user-posts:42".to_string()],
+        cached("posts"),
+    );
+    cache.store(
+        "/users/7/posts".to_string(),
+        &[],
+        &HeaderMap::new(),
+        vec!["user-posts:7".to_string()],
+        cached("posts"),
+    );
+
+    assert_eq!(cache.purge_tags(&["user-posts:42".to_string()]), 1);
+    assert!(cache.lookup("/users/42/posts", &HeaderMap::new()).is_none());
+    assert!(cache.lookup("/users/7/posts", &HeaderMap::new()).is_some());
+
+    // The message social-service publishes on the purge channel.
+    let payload =
+        serde_json::to_string(&CachePurge::tags(vec!["user-posts:7".to_string()])).unwrap();
+    let purge: CachePurge = serde_json::from_str(&payload).unwrap();
+    assert_eq!(cache.apply(&purge), 1);
+    assert!(cache.lookup("/users/7/posts", &HeaderMap::new()).is_none());
+}
+
+#[test]
+fn test_cache_key_is_scoped_to_user_unless_shared() {
+    let mut rule = cache().match_route("/users/42/posts").unwrap().rule.clone();
+    let path = "/users/42/posts?limit=10";
+
+    let alice = cache_key(&rule, "v1", path, Some("alice")).unwrap();
+    let bob = cache_key(&rule, "v1", path, Some("bob")).unwrap();
+    assert_ne!(alice, bob);
+    assert!(cache_key(&rule, "v1", path, None).is_none());
+
+    rule.shared = true;
+    assert_eq!(
+        cache_key(&rule, "v1", path, Some("alice")),
+        cache_key(&rule, "v1", path, Some("bob"))
+    );
+    assert!(cache_key(&rule, "v1", path, None).is_some());
+}
+
+#[tokio::test]
+async fn test_oversized_response_is_passed_through_uncached() {
+    let mut config = Config::for_tests();
+    config.cache.max_entry_bytes = 1024;
+    config.cache.rules = vec![CacheRule {
+        pattern: "/posts/{post_id}".to_string(),
+        ttl: Duration::from_secs(30),
+        tags: vec![],
+        vary: vec![],
+        shared: true,
+    }];
+    let state = AppState::new(config);
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let upstream_calls = calls.clone();
+    let app = Router::new()
+        .route(
+            "/posts/{post_id}",
+            get(move || {
+                upstream_calls.fetch_add(1, Ordering::SeqCst);
+                // A stream has no exact size hint, so the limit is hit while reading.
+                let chunks = (0..4).map(|_| Ok::<_, std::io::Error>(Bytes::from(vec![b'a'; 512])));
+                async move { Body::from_stream(futures::stream::iter(chunks)) }
+            }),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            state,
+            cache_middleware,
+        ));
+
+    for _ in 0..2 {
+        let response = app
+            .clone()
+            .oneshot(Request::get("/posts/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len(), 2048);
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,124 @@
+use api_gateway::config::{ApiVersionConfig, CanaryConfig, CanaryRule, Config};
+use api_gateway::services::canary::{self, Track};
+use api_gateway::services::upstream::Upstreams;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+
+fn config(weight: u32) -> CanaryConfig {
+    CanaryConfig {
+        header: HeaderName::from_static("x-canary"),
+        cookie: "canary".to_string(),
+        rules: vec![CanaryRule {
+            pattern: "/messages/{*path}".to_string(),
+            weight,
+        }],
+        upstreams: Vec::new(),
+    }
+}
+
+#[test]
+fn test_weight_splits_users_stickily() {
+    let config = config(20);
+    let headers = HeaderMap::new();
+
+    let canary_users = (0..1000)
+        .map(|i| format!("user-{}", i))
+        .filter(|user| {
+            let track = canary::assign(&config, "/messages/1", &headers, Some(user), false);
+            assert_eq!(
+                track,
+                canary::assign(&config, "/messages/2", &headers, Some(user), false)
+            );
+            track == Track::Canary
+        })
+        .count();
+
+    assert!((150..250).contains(&canary_users), "{}", canary_users);
+}
+
+#[test]
+fn test_header_override_wins_over_weight() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-canary", HeaderValue::from_static("1"));
+    assert_eq!(
+        canary::assign(&config(0), "/messages/1", &headers, Some("user-1"), true),
+        Track::Canary
+    );
+
+    headers.insert("x-canary", HeaderValue::from_static("0"));
+    assert_eq!(
+        canary::assign(&config(100), "/messages/1", &headers, Some("user-1"), true),
+        Track::Stable
+    );
+}
+
+#[test]
+fn test_cookie_override() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::COOKIE,
+        HeaderValue::from_static("theme=dark; canary=true"),
+    );
+
+    assert_eq!(
+        canary::assign(&config(0), "/messages/1", &headers, None, false),
+        Track::Canary
+    );
+}
+
+#[test]
+fn test_unmatched_route_stays_stable() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-canary", HeaderValue::from_static("1"));
+
+    assert_eq!(
+        canary::assign(&config(100), "/posts", &headers, Some("user-1"), true),
+        Track::Stable
+    );
+}
+
+#[test]
+fn test_header_ignored_from_untrusted_clients() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-canary", HeaderValue::from_static("1"));
+
+    assert_eq!(
+        canary::assign(&config(0), "/messages/1", &headers, Some("user-1"), false),
+        Track::Stable
+    );
+}
+
+#[test]
+fn test_versioned_upstream_uses_service_canary() {
+    let mut config = Config::for_tests();
+    let mut v2_messaging = config.messaging_service.clone();
+    v2_messaging.urls = vec!["http://messaging-v2:8082".to_string()];
+    config.versioning.versions.push(ApiVersionConfig {
+        name: "v2".to_string(),
+        deprecated_at: None,
+        sunset_at: None,
+        upstreams: vec![v2_messaging],
+    });
+    let mut canary = config.messaging_service.clone();
+    canary.name = "messaging-canary".to_string();
+    canary.urls = vec!["http://messaging-canary:8082".to_string()];
+    config.canary.upstreams = vec![canary];
+    let upstreams = Upstreams::new(&config);
+
+    let v2 = upstreams.for_version(&upstreams.messaging, "v2");
+    assert_eq!(v2.config.name, "messaging@v2");
+    assert_eq!(
+        upstreams.for_track(v2, Track::Canary).config.name,
+        "messaging-canary"
+    );
+    assert_eq!(
+        upstreams.for_track(v2, Track::Stable).config.name,
+        "messaging@v2"
+    );
+    assert_eq!(
+        upstreams
+            .for_track(&upstreams.messaging, Track::Canary)
+            .config
+            .name,
+        "messaging-canary"
+    );
+}
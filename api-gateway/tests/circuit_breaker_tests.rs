@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use api_gateway::config::CircuitBreakerConfig;
+use api_gateway::services::circuit_breaker::{BreakerState, CircuitBreaker};
+use api_gateway::services::proxy::is_upstream_failure;
+use axum::http::StatusCode;
+
+fn breaker(open_duration: Duration) -> CircuitBreaker {
+    CircuitBreaker::new(&CircuitBreakerConfig {
+        failure_threshold: 3,
+        open_duration,
+        half_open_max_requests: 1,
+    })
+}
+
+#[test]
+fn test_opens_after_consecutive_failures() {
+    let breaker = breaker(Duration::from_secs(60));
+
+    for _ in 0..2 {
+        assert!(breaker.try_acquire());
+        breaker.record_failure();
+    }
+    assert_eq!(breaker.snapshot().state, BreakerState::Closed);
+
+    assert!(breaker.try_acquire());
+    breaker.record_failure();
+
+    assert_eq!(breaker.snapshot().state, BreakerState::Open);
+    assert!(!breaker.try_acquire());
+}
+
+#[test]
+fn test_success_resets_failure_count() {
+    let breaker = breaker(Duration::from_secs(60));
+
+    breaker.record_failure();
+    breaker.record_failure();
+    breaker.record_success();
+    breaker.record_failure();
+
+    let snapshot = breaker.snapshot();
+    assert_eq!(snapshot.state, BreakerState::Closed);
+    assert_eq!(snapshot.consecutive_failures, 1);
+}
+
+#[test]
+fn test_half_open_allows_single_probe() {
+    let breaker = breaker(Duration::ZERO);
+
+    for _ in 0..3 {
+        breaker.record_failure();
+    }
+
+    assert!(breaker.try_acquire());
+    assert_eq!(breaker.snapshot().state, BreakerState::HalfOpen);
+
+    breaker.record_success();
+    assert_eq!(breaker.snapshot().state, BreakerState::Closed);
+}
+
+#[test]
+fn test_half_open_failure_reopens() {
+    let breaker = breaker(Duration::from_millis(20));
+
+    for _ in 0..3 {
+        breaker.record_failure();
+    }
+    assert!(!breaker.try_acquire());
+
+    std::thread::sleep(Duration::from_millis(30));
+    assert!(breaker.try_acquire());
+    assert!(!breaker.try_acquire());
+
+    breaker.record_failure();
+    assert_eq!(breaker.snapshot().state, BreakerState::Open);
+    assert!(!breaker.try_acquire());
+}
+
+#[test]
+fn test_unsettled_permit_frees_half_open_slot() {
+    let breaker = breaker(Duration::from_millis(20));
+
+    for _ in 0..3 {
+        breaker.record_failure();
+    }
+    std::thread::sleep(Duration::from_millis(30));
+
+    let permit = breaker.acquire().expect("half-open trial");
+    assert!(breaker.acquire().is_none());
+    drop(permit);
+
+    assert_eq!(breaker.snapshot().state, BreakerState::HalfOpen);
+    breaker.acquire().expect("slot released").record(false);
+    assert_eq!(breaker.snapshot().state, BreakerState::Closed);
+}
+
+#[test]
+fn test_only_gateway_errors_count_as_upstream_failures() {
+    assert!(!is_upstream_failure(StatusCode::INTERNAL_SERVER_ERROR));
+    assert!(!is_upstream_failure(StatusCode::NOT_FOUND));
+    assert!(is_upstream_failure(StatusCode::BAD_GATEWAY));
+    assert!(is_upstream_failure(StatusCode::SERVICE_UNAVAILABLE));
+    assert!(is_upstream_failure(StatusCode::GATEWAY_TIMEOUT));
+}
@@ -0,0 +1,93 @@
+use std::{net::IpAddr, time::Duration};
+
+use api_gateway::config::{parse_cidr, IpFilterConfig};
+use api_gateway::services::ip_filter::{IpFilter, IpList, Rejection};
+use axum::http::{HeaderMap, HeaderValue};
+
+fn config() -> IpFilterConfig {
+    IpFilterConfig {
+        allow: vec![],
+        deny: vec![parse_cidr("203.0.113.0/24").unwrap()],
+        trusted_proxies: vec![parse_cidr("10.0.0.0/8").unwrap()],
+        private_networks: vec![
+            parse_cidr("10.0.0.0/8").unwrap(),
+            parse_cidr("127.0.0.1").unwrap(),
+        ],
+        private_paths: vec!["/internal/{*path}".to_string()],
+        sync_interval: Duration::from_secs(10),
+    }
+}
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+#[test]
+fn test_client_ip_only_trusts_forwarded_for_from_proxies() {
+    let filter = IpFilter::new(&config());
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "x-forwarded-for",
+        HeaderValue::from_static("1.1.1.1, 198.51.100.7, 10.0.0.3"),
+    );
+
+    assert_eq!(
+        filter.client_ip(ip("10.0.0.2"), &headers),
+        ip("198.51.100.7")
+    );
+    assert_eq!(
+        filter.client_ip(ip("198.51.100.9"), &headers),
+        ip("198.51.100.9")
+    );
+}
+
+#[test]
+fn test_deny_list_and_runtime_rules() {
+    let filter = IpFilter::new(&config());
+
+    assert_eq!(
+        filter.check(ip("203.0.113.5"), "/posts"),
+        Err(Rejection::Denied)
+    );
+    assert_eq!(filter.check(ip("198.51.100.7"), "/posts"), Ok(()));
+
+    let abuser = parse_cidr("198.51.100.0/28").unwrap();
+    filter.add(IpList::Deny, abuser);
+    assert_eq!(
+        filter.check(ip("198.51.100.7"), "/posts"),
+        Err(Rejection::Denied)
+    );
+
+    filter.remove(IpList::Deny, abuser);
+    assert_eq!(filter.check(ip("198.51.100.7"), "/posts"), Ok(()));
+}
+
+#[test]
+fn test_allow_list_blocks_everyone_else() {
+    let filter = IpFilter::new(&config());
+    filter.add(IpList::Allow, parse_cidr("192.0.2.0/24").unwrap());
+
+    assert_eq!(filter.check(ip("192.0.2.10"), "/posts"), Ok(()));
+    assert_eq!(
+        filter.check(ip("198.51.100.7"), "/posts"),
+        Err(Rejection::NotAllowed)
+    );
+}
+
+#[test]
+fn test_private_paths_require_private_network() {
+    let filter = IpFilter::new(&config());
+
+    assert_eq!(
+        filter.check(ip("10.1.2.3"), "/internal/cache/purge"),
+        Ok(())
+    );
+    assert_eq!(
+        filter.check(ip("::ffff:127.0.0.1"), "/internal/ip-rules"),
+        Ok(())
+    );
+    assert_eq!(
+        filter.check(ip("198.51.100.7"), "/internal/cache/purge"),
+        Err(Rejection::PrivateOnly)
+    );
+}
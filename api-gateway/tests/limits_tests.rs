@@ -0,0 +1,145 @@
+use api_gateway::config::{Config, LimitRule, LimitsConfig};
+use api_gateway::middleware::limits::limits_middleware;
+use api_gateway::services::{limits, pattern::match_pattern};
+use api_gateway::state::AppState;
+use axum::{
+    body::{Body, Bytes},
+    http::{header, Method, Request, StatusCode},
+    routing::post,
+    Router,
+};
+use tower::ServiceExt;
+
+fn config() -> LimitsConfig {
+    LimitsConfig {
+        max_body_bytes: 1024,
+        allowed_content_types: vec!["application/json".to_string()],
+        max_json_depth: 3,
+        rules: vec![LimitRule {
+            pattern: "/users/{user_id}/avatar".to_string(),
+            max_body_bytes: 5 * 1024 * 1024,
+            content_types: vec!["image/*".to_string()],
+        }],
+        ..Config::for_tests().limits
+    }
+}
+
+fn app() -> Router {
+    let mut config = Config::for_tests();
+    config.limits = self::config();
+    let state = AppState::new(config);
+
+    Router::new()
+        .route(
+            "/posts",
+            post(|body: Bytes| async move { body.len().to_string() }),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            state,
+            limits_middleware,
+        ))
+}
+
+async fn status(req: Request<Body>) -> StatusCode {
+    app().oneshot(req).await.unwrap().status()
+}
+
+// No Content-Length and no Transfer-Encoding, as with HTTP/2.
+fn streamed(content_type: &str, body: String) -> Request<Body> {
+    let chunks = body
+        .into_bytes()
+        .chunks(256)
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+        .collect::<Vec<_>>();
+
+    Request::builder()
+        .method(Method::POST)
+        .uri("/posts")
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from_stream(futures::stream::iter(chunks)))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_unknown_length_body_is_checked() {
+    let small = r#"{"content":"hello"}"#.to_string();
+    assert_eq!(
+        status(streamed("application/json", small.clone())).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(streamed("text/plain", small)).await,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+
+    let large = format!(r#"{{"content":"{}"}}"#, "x".repeat(2048));
+    assert_eq!(
+        status(streamed("application/json", large)).await,
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+
+    let nested = r#"{"a":{"b":{"c":{"d":1}}}}"#.to_string();
+    assert_eq!(
+        status(streamed("application/json", nested)).await,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn test_empty_body_skips_content_type_check() {
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("/posts")
+        .body(Body::empty())
+        .unwrap();
+
+    assert_eq!(status(req).await, StatusCode::OK);
+}
+
+#[test]
+fn test_route_rules_override_defaults() {
+    let config = config();
+
+    let avatar = limits::for_path(&config, "/users/42/avatar");
+    assert_eq!(avatar.max_body_bytes, 5 * 1024 * 1024);
+    assert_eq!(avatar.content_types, ["image/*".to_string()]);
+
+    let posts = limits::for_path(&config, "/posts");
+    assert_eq!(posts.max_body_bytes, 1024);
+    assert_eq!(posts.content_types, ["application/json".to_string()]);
+}
+
+#[test]
+fn test_content_type_allowlist() {
+    let json = ["application/json".to_string()];
+    let images = ["image/*".to_string()];
+
+    assert!(limits::content_type_allowed(
+        &json,
+        Some("application/json; charset=utf-8")
+    ));
+    assert!(!limits::content_type_allowed(&json, Some("text/plain")));
+    assert!(!limits::content_type_allowed(&json, None));
+    assert!(limits::content_type_allowed(&images, Some("image/png")));
+    assert!(!limits::content_type_allowed(
+        &images,
+        Some("application/pdf")
+    ));
+}
+
+#[test]
+fn test_json_depth_ignores_brackets_in_strings() {
+    assert!(!limits::json_depth_exceeds(br#"{"a":{"b":[1]}}"#, 3));
+    assert!(limits::json_depth_exceeds(br#"{"a":{"b":[[1]]}}"#, 3));
+    assert!(!limits::json_depth_exceeds(br#"{"a":"[[[[\"{{{{"}"#, 3));
+}
+
+#[test]
+fn test_pattern_wildcard_matches_remaining_segments() {
+    assert_eq!(
+        match_pattern("/posts/{*rest}", "/posts/abc/comments"),
+        Some(vec![("rest".to_string(), "abc/comments".to_string())])
+    );
+    assert!(match_pattern("/posts/{*rest}", "/posts").is_none());
+    assert!(match_pattern("/posts/{post_id}", "/posts/abc/comments").is_none());
+}
@@ -0,0 +1,90 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+use api_gateway::config::Config;
+use api_gateway::routes::docs::GatewayApiDoc;
+use api_gateway::state::AppState;
+use axum::{routing::get, Json, Router};
+use serde_json::json;
+use tokio::net::TcpListener;
+use utoipa::OpenApi;
+
+async fn spec_server(path: &'static str, fetches: Arc<AtomicUsize>) -> String {
+    let router = Router::new().route(
+        "/openapi.json",
+        get(move || async move {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            Json(json!({
+                "openapi": "3.1.0",
+                "info": { "title": path, "version": "0.1.0" },
+                "paths": {
+                    path: { "get": { "responses": {} } },
+                    "/internal/cache/stats": { "get": { "responses": {} } }
+                }
+            }))
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+async fn state(social_url: Option<String>, fetches: Arc<AtomicUsize>) -> AppState {
+    let mut config = Config::for_tests();
+    config.auth_service.urls = vec![spec_server("/auth/me", fetches.clone()).await];
+    config.messaging_service.urls = vec![spec_server("/conversations", fetches.clone()).await];
+    config.social_service.urls = vec![match social_url {
+        Some(url) => url,
+        None => spec_server("/posts", fetches).await,
+    }];
+    config.openapi_cache_ttl = Duration::from_secs(60);
+    AppState::new(config)
+}
+
+#[tokio::test]
+async fn test_merged_spec_is_cached() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let state = state(None, fetches.clone()).await;
+
+    let spec = state.openapi.get(&state, GatewayApiDoc::openapi).await;
+    let version = &state.config.versioning.default_version;
+    for path in ["/bff/home", "/auth/me", "/conversations", "/posts"] {
+        assert!(spec
+            .paths
+            .paths
+            .contains_key(&format!("/{}{}", version, path)));
+    }
+    assert!(!spec
+        .paths
+        .paths
+        .keys()
+        .any(|path| path.contains("/internal/")));
+    assert_eq!(fetches.load(Ordering::SeqCst), 3);
+
+    state.openapi.get(&state, GatewayApiDoc::openapi).await;
+    assert_eq!(fetches.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_incomplete_spec_is_not_cached() {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let social_url = format!("http://{}", closed.local_addr().unwrap());
+    drop(closed);
+    let state = state(Some(social_url), fetches.clone()).await;
+
+    let spec = state.openapi.get(&state, GatewayApiDoc::openapi).await;
+    assert!(spec
+        .paths
+        .paths
+        .keys()
+        .any(|path| path.ends_with("/auth/me")));
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+    state.openapi.get(&state, GatewayApiDoc::openapi).await;
+    assert_eq!(fetches.load(Ordering::SeqCst), 4);
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use api_gateway::config::RealtimeConfig;
+use api_gateway::middleware::auth::strip_query_token;
+use api_gateway::services::realtime::{ConnectionLimiter, LimitExceeded};
+
+fn limiter(max_connections: usize, max_connections_per_user: usize) -> Arc<ConnectionLimiter> {
+    Arc::new(ConnectionLimiter::new(&RealtimeConfig {
+        idle_timeout: Duration::from_secs(120),
+        max_connections,
+        max_connections_per_user,
+    }))
+}
+
+#[test]
+fn test_per_user_limit() {
+    let limiter = limiter(10, 2);
+
+    let _first = limiter.try_acquire("alice").ok().unwrap();
+    let _second = limiter.try_acquire("alice").ok().unwrap();
+    assert!(matches!(
+        limiter.try_acquire("alice"),
+        Err(LimitExceeded::PerUser)
+    ));
+    assert!(limiter.try_acquire("bob").is_ok());
+}
+
+#[test]
+fn test_global_limit() {
+    let limiter = limiter(2, 5);
+
+    let _alice = limiter.try_acquire("alice").ok().unwrap();
+    let _bob = limiter.try_acquire("bob").ok().unwrap();
+    assert!(matches!(
+        limiter.try_acquire("carol"),
+        Err(LimitExceeded::Global)
+    ));
+}
+
+#[test]
+fn test_dropped_permit_frees_slot() {
+    let limiter = limiter(10, 1);
+
+    let permit = limiter.try_acquire("alice").ok().unwrap();
+    let stats = limiter.stats();
+    assert_eq!(stats.active_connections, 1);
+    assert_eq!(stats.connected_users, 1);
+
+    drop(permit);
+    let stats = limiter.stats();
+    assert_eq!(stats.active_connections, 0);
+    assert_eq!(stats.connected_users, 0);
+    assert!(limiter.try_acquire("alice").is_ok());
+}
+
+#[test]
+fn test_rejected_acquire_does_not_leak() {
+    let limiter = limiter(1, 1);
+
+    let _alice = limiter.try_acquire("alice").ok().unwrap();
+    assert!(limiter.try_acquire("bob").is_err());
+    assert_eq!(limiter.stats().connected_users, 1);
+}
+
+#[test]
+fn test_query_token_is_not_forwarded() {
+    assert_eq!(
+        strip_query_token("/api/messaging/ws?access_token=abc"),
+        "/api/messaging/ws"
+    );
+    assert_eq!(
+        strip_query_token("/api/messaging/events?token=abc&since=42"),
+        "/api/messaging/events?since=42"
+    );
+    assert_eq!(
+        strip_query_token("/api/messaging/events?since=42&access_token=abc&limit=5"),
+        "/api/messaging/events?since=42&limit=5"
+    );
+    assert_eq!(
+        strip_query_token("/api/messaging/ws?tokens=1"),
+        "/api/messaging/ws?tokens=1"
+    );
+    assert_eq!(strip_query_token("/api/messaging/ws"), "/api/messaging/ws");
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use api_gateway::config::{CorsConfig, SecurityHeadersConfig};
+use api_gateway::services::security;
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode},
+    routing::get,
+    Router,
+};
+use tower::ServiceExt;
+
+fn cors_app() -> Router {
+    let config = CorsConfig {
+        allowed_origins: vec!["https://app.staki.dev".to_string()],
+        allowed_methods: vec![Method::GET, Method::POST],
+        allowed_headers: vec![header::AUTHORIZATION, header::CONTENT_TYPE],
+        exposed_headers: vec![HeaderName::from_static("x-request-id")],
+        max_age: Duration::from_secs(600),
+        allow_credentials: true,
+    };
+
+    Router::new()
+        .route("/posts", get(|| async { "ok" }))
+        .layer(security::cors_layer(&config))
+}
+
+fn preflight(origin: &str) -> Request<Body> {
+    Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/posts")
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn security_config() -> SecurityHeadersConfig {
+    SecurityHeadersConfig {
+        hsts_max_age: Duration::from_secs(3600),
+        referrer_policy: "no-referrer".to_string(),
+        docs_csp: "default-src 'none'".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_cors_allows_configured_origin() {
+    let response = cors_app()
+        .oneshot(preflight("https://app.staki.dev"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(
+        headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://app.staki.dev"
+    );
+    assert_eq!(
+        headers
+            .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            .unwrap(),
+        "true"
+    );
+    assert_eq!(headers.get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+}
+
+#[tokio::test]
+async fn test_cors_rejects_unknown_origin() {
+    let response = cors_app()
+        .oneshot(preflight("https://evil.example"))
+        .await
+        .unwrap();
+
+    assert!(response
+        .headers()
+        .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        .is_none());
+}
+
+#[test]
+fn test_security_headers_on_api_responses() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    security::apply_security_headers(&mut headers, &security_config());
+
+    assert_eq!(
+        headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+        "nosniff"
+    );
+    assert_eq!(
+        headers.get(header::STRICT_TRANSPORT_SECURITY).unwrap(),
+        "max-age=3600; includeSubDomains"
+    );
+    assert_eq!(headers.get(header::REFERRER_POLICY).unwrap(), "no-referrer");
+    assert!(headers.get(header::CONTENT_SECURITY_POLICY).is_none());
+}
+
+#[test]
+fn test_csp_only_on_html_pages() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+
+    security::apply_security_headers(&mut headers, &security_config());
+
+    assert_eq!(
+        headers.get(header::CONTENT_SECURITY_POLICY).unwrap(),
+        "default-src 'none'"
+    );
+}
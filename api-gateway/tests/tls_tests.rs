@@ -0,0 +1,39 @@
+use std::{path::PathBuf, time::Duration};
+
+use api_gateway::config::TlsConfig;
+use api_gateway::services::tls;
+
+#[test]
+fn test_https_url_replaces_http_port() {
+    assert_eq!(
+        tls::https_url(Some("api.staki.dev:80"), "/v1/posts?limit=10", 443).as_deref(),
+        Some("https://api.staki.dev/v1/posts?limit=10")
+    );
+    assert_eq!(
+        tls::https_url(Some("localhost:8080"), "/health", 8443).as_deref(),
+        Some("https://localhost:8443/health")
+    );
+    assert_eq!(
+        tls::https_url(Some("[::1]:8080"), "/", 443).as_deref(),
+        Some("https://[::1]/")
+    );
+}
+
+#[test]
+fn test_https_url_requires_host() {
+    assert!(tls::https_url(None, "/", 443).is_none());
+    assert!(tls::https_url(Some(""), "/", 443).is_none());
+}
+
+#[tokio::test]
+async fn test_load_fails_for_missing_certificate() {
+    let config = TlsConfig {
+        cert_path: PathBuf::from("/nonexistent/cert.pem"),
+        key_path: PathBuf::from("/nonexistent/key.pem"),
+        reload_interval: Duration::from_secs(30),
+        redirect_port: None,
+        http2: true,
+    };
+
+    assert!(tls::load(&config).await.is_err());
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use api_gateway::config::{Config, LoadBalancingStrategy};
+use api_gateway::services::{proxy, upstream::Upstream};
+use api_gateway::state::AppState;
+use axum::{body::Body, extract::Request, routing::get, Router};
+use tokio::net::TcpListener;
+
+fn upstream(strategy: LoadBalancingStrategy) -> Upstream {
+    let mut config = Config::for_tests();
+    config.health_check.failure_threshold = 2;
+    config.health_check.ejection_threshold = 2;
+    config.health_check.ejection_duration = Duration::from_millis(50);
+
+    let mut upstream_config = config.messaging_service.clone();
+    upstream_config.urls = vec![
+        "http://messaging-1:8082".to_string(),
+        "http://messaging-2:8082".to_string(),
+        "http://messaging-3:8082".to_string(),
+    ];
+    upstream_config.strategy = strategy;
+    Upstream::new(&upstream_config, &config)
+}
+
+fn selected_url(upstream: &Upstream) -> String {
+    upstream.select().unwrap().instance.url.clone()
+}
+
+#[test]
+fn test_round_robin_cycles_through_instances() {
+    let upstream = upstream(LoadBalancingStrategy::RoundRobin);
+
+    let urls: Vec<String> = (0..6).map(|_| selected_url(&upstream)).collect();
+    assert_eq!(urls[0..3], urls[3..6]);
+    assert_eq!(
+        urls[0..3]
+            .iter()
+            .collect::<std::collections::HashSet<_>>()
+            .len(),
+        3
+    );
+}
+
+#[test]
+fn test_least_connections_prefers_idle_instance() {
+    let upstream = upstream(LoadBalancingStrategy::LeastConnections);
+
+    let first = upstream.select().unwrap();
+    let second = upstream.select().unwrap();
+    let third = upstream.select().unwrap();
+    assert_ne!(first.instance.url, second.instance.url);
+    assert_ne!(third.instance.url, first.instance.url);
+    assert_ne!(third.instance.url, second.instance.url);
+
+    let freed = second.instance.url.clone();
+    drop(second);
+    assert_eq!(selected_url(&upstream), freed);
+}
+
+#[test]
+fn test_ejects_after_consecutive_errors_and_reinstates() {
+    let upstream = upstream(LoadBalancingStrategy::RoundRobin);
+    let instance = upstream.instances[0].clone();
+
+    upstream.record_failure(&instance);
+    upstream.record_success(&instance);
+    upstream.record_failure(&instance);
+    assert!(instance.is_available());
+
+    upstream.record_failure(&instance);
+    assert!(instance.is_ejected());
+    for _ in 0..6 {
+        assert_ne!(selected_url(&upstream), instance.url);
+    }
+
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(instance.is_available());
+}
+
+#[test]
+fn test_single_failed_probe_keeps_instance_in_rotation() {
+    let upstream = upstream(LoadBalancingStrategy::RoundRobin);
+    let instance = upstream.instances[1].clone();
+
+    upstream.record_probe(&instance, false);
+    assert!(instance.is_available());
+
+    upstream.record_probe(&instance, false);
+    assert!(!instance.is_available());
+
+    upstream.record_probe(&instance, true);
+    assert!(instance.is_available());
+}
+
+#[test]
+fn test_healthy_probe_does_not_end_ejection() {
+    let upstream = upstream(LoadBalancingStrategy::RoundRobin);
+    let instance = upstream.instances[2].clone();
+
+    upstream.record_failure(&instance);
+    upstream.record_failure(&instance);
+    assert!(instance.is_ejected());
+
+    upstream.record_probe(&instance, true);
+    assert!(instance.is_ejected());
+    assert!(!instance.is_available());
+    for _ in 0..6 {
+        assert_ne!(selected_url(&upstream), instance.url);
+    }
+
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(instance.is_available());
+}
+
+#[test]
+fn test_no_instance_when_all_unavailable() {
+    let upstream = upstream(LoadBalancingStrategy::RoundRobin);
+    for instance in &upstream.instances {
+        instance.set_draining(true);
+    }
+    assert!(upstream.select().is_none());
+}
+
+#[tokio::test]
+async fn test_streams_responses_larger_than_the_body_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let large = Router::new().route("/large", get(|| async { vec![b'x'; 4 * 1024 * 1024] }));
+    tokio::spawn(async move { axum::serve(listener, large).await.unwrap() });
+
+    let mut config = Config::for_tests();
+    config.limits.max_body_bytes = 1024;
+    config.messaging_service.urls = vec![url];
+    let state = AppState::new(config);
+    let upstream = &state.upstreams.messaging;
+
+    let req = Request::get("/large").body(Body::empty()).unwrap();
+    let res = proxy::forward(&state, upstream, req).await;
+    assert!(res.status().is_success());
+    // The instance stays busy until its body has been sent.
+    assert_eq!(upstream.instances[0].active_connections(), 1);
+
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.len(), 4 * 1024 * 1024);
+    assert_eq!(upstream.instances[0].active_connections(), 0);
+}
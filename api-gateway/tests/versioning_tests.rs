@@ -0,0 +1,85 @@
+use api_gateway::config::{ApiVersionConfig, VersioningConfig};
+use api_gateway::services::versioning::{self, ACCEPT_VERSION, API_VERSION};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use chrono::{Duration, TimeZone, Utc};
+
+fn version(name: &str) -> ApiVersionConfig {
+    ApiVersionConfig {
+        name: name.to_string(),
+        deprecated_at: None,
+        sunset_at: None,
+        upstreams: vec![],
+    }
+}
+
+fn config() -> VersioningConfig {
+    VersioningConfig {
+        default_version: "v1".to_string(),
+        versions: vec![version("v1"), version("v2")],
+    }
+}
+
+#[test]
+fn test_path_prefix_selects_version_and_strips_prefix() {
+    let config = config();
+
+    let resolved = versioning::resolve(&config, "/v2/posts/abc", &HeaderMap::new()).unwrap();
+
+    assert_eq!(resolved.version.name, "v2");
+    assert_eq!(resolved.path.as_deref(), Some("/posts/abc"));
+}
+
+#[test]
+fn test_accept_version_header_and_default() {
+    let config = config();
+    let mut headers = HeaderMap::new();
+
+    let resolved = versioning::resolve(&config, "/posts", &headers).unwrap();
+    assert_eq!(resolved.version.name, "v1");
+    assert!(resolved.path.is_none());
+
+    headers.insert(ACCEPT_VERSION, HeaderValue::from_static("2"));
+    let resolved = versioning::resolve(&config, "/posts", &headers).unwrap();
+    assert_eq!(resolved.version.name, "v2");
+}
+
+#[test]
+fn test_unknown_versions_are_rejected() {
+    let config = config();
+
+    let err = versioning::resolve(&config, "/v9/posts", &HeaderMap::new())
+        .err()
+        .unwrap();
+    assert_eq!(err.status, StatusCode::NOT_FOUND);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT_VERSION, HeaderValue::from_static("v9"));
+    let err = versioning::resolve(&config, "/posts", &headers)
+        .err()
+        .unwrap();
+    assert_eq!(err.status, StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_deprecation_and_sunset_headers() {
+    let mut retiring = version("v1");
+    retiring.deprecated_at = Some(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+    retiring.sunset_at = Some(Utc.with_ymd_and_hms(2026, 7, 1, 0, 0, 0).unwrap());
+
+    let mut headers = HeaderMap::new();
+    versioning::apply_headers(&mut headers, &retiring);
+
+    assert_eq!(headers.get(API_VERSION).unwrap(), "v1");
+    assert_eq!(headers.get("deprecation").unwrap(), "@1767225600");
+    assert_eq!(
+        headers.get("sunset").unwrap(),
+        "Wed, 01 Jul 2026 00:00:00 GMT"
+    );
+
+    let sunset = retiring.sunset_at.unwrap();
+    assert!(!versioning::is_sunset(
+        &retiring,
+        sunset - Duration::days(1)
+    ));
+    assert!(versioning::is_sunset(&retiring, sunset));
+}
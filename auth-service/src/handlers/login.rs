@@ -2,9 +2,21 @@ use std::sync::Arc;
 
 use axum::{http::StatusCode, Extension, Json};
 
+use shared::ErrorBody;
+
 use crate::models::{AuthResponse, LoginRequest};
 use crate::services::AuthService;
 
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Authenticated", body = AuthResponse),
+        (status = 401, description = "Invalid credentials", body = ErrorBody),
+    )
+)]
 pub async fn login(
     Extension(auth_service): Extension<Arc<AuthService>>,
     Json(req): Json<LoginRequest>,
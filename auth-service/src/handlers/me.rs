@@ -1,11 +1,22 @@
 use axum::{http::StatusCode, Extension, Json};
 use bson::doc;
 use mongodb::Database;
-use shared::Claims;
+use shared::{Claims, ErrorBody};
 use std::sync::Arc;
 
 use crate::models::{User, UserResponse};
 
+#[utoipa::path(
+    get,
+    path = "/auth/me",
+    tag = "auth",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Current user", body = UserResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+    )
+)]
 pub async fn get_me(
     Extension(claims): Extension<Claims>,
     Extension(db): Extension<Arc<Database>>,
@@ -1,9 +1,20 @@
 use axum::{http::StatusCode, Extension, Json};
+use shared::ErrorBody;
 use std::sync::Arc;
 
 use crate::models::{AuthResponse, RegisterRequest};
 use crate::services::AuthService;
 
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, description = "Account created", body = AuthResponse),
+        (status = 400, description = "Email already used or invalid request", body = ErrorBody),
+    )
+)]
 pub async fn register(
     Extension(auth_service): Extension<Arc<AuthService>>,
     Json(req): Json<RegisterRequest>,
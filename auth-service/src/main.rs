@@ -3,14 +3,14 @@ use axum::{
     Extension, Router,
 };
 use mongodb::{options::ClientOptions, Client};
+use shared::RequestIdLayer;
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
-use tracing_subscriber;
 
 mod config;
 mod handlers;
 mod middleware;
 mod models;
+mod openapi;
 mod services;
 
 use config::Config;
@@ -41,9 +41,11 @@ async fn main() {
         .layer(axum::middleware::from_fn(middleware::auth_middleware));
 
     let app = Router::new()
+        .route("/health", get(shared::health_check))
+        .route("/openapi.json", get(openapi::openapi_json))
         .merge(public_routes)
         .merge(protected_routes)
-        .layer(CorsLayer::permissive());
+        .layer(RequestIdLayer::new());
 
     let addr = format!("0.0.0.0:{}", config.port);
     let listener = tokio::net::TcpListener::bind(&addr)
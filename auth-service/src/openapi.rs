@@ -0,0 +1,26 @@
+use axum::Json;
+use shared::{openapi::BearerSecurity, ErrorBody};
+use utoipa::OpenApi;
+
+use crate::handlers;
+use crate::models::{AuthResponse, LoginRequest, RegisterRequest, UserResponse};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Staki Auth Service"),
+    paths(handlers::login::login, handlers::register::register, handlers::me::get_me),
+    components(schemas(
+        RegisterRequest,
+        LoginRequest,
+        AuthResponse,
+        UserResponse,
+        ErrorBody
+    )),
+    modifiers(&BearerSecurity),
+    tags((name = "auth", description = "Registration and authentication"))
+)]
+pub struct ApiDoc;
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
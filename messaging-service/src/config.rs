@@ -5,6 +5,17 @@ pub struct Config {
     pub port: u16,
     pub mongo_uri: String,
     pub redis_uri: String,
+    pub idempotency_ttl_secs: u64,
+    pub ws_ping_interval_secs: u64,
+    pub ws_idle_timeout_secs: u64,
+    pub realtime_publish_timeout_ms: u64,
+    pub presence_ttl_secs: u64,
+    pub typing_ttl_secs: u64,
+    pub message_cache_ttl_secs: u64,
+    pub message_cache_timeout_ms: u64,
+    pub message_edit_window_secs: u64,
+    /// Shared secret of the `/internal` routes; they are disabled when unset.
+    pub internal_token: Option<String>,
 }
 
 impl Config {
@@ -18,6 +29,45 @@ impl Config {
                 .expect("PORT must be a number"),
             mongo_uri: env::var("MONGODB_URI").expect("MONGODB_URI must be set"),
             redis_uri: env::var("REDIS_URL").expect("REDIS_URL must be set"),
+            idempotency_ttl_secs: env::var("IDEMPOTENCY_TTL_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .expect("IDEMPOTENCY_TTL_SECS must be a number"),
+            ws_ping_interval_secs: env::var("WS_PING_INTERVAL_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("WS_PING_INTERVAL_SECS must be a number"),
+            ws_idle_timeout_secs: env::var("WS_IDLE_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .expect("WS_IDLE_TIMEOUT_SECS must be a number"),
+            realtime_publish_timeout_ms: env::var("REALTIME_PUBLISH_TIMEOUT_MS")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse()
+                .expect("REALTIME_PUBLISH_TIMEOUT_MS must be a number"),
+            presence_ttl_secs: env::var("PRESENCE_TTL_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .expect("PRESENCE_TTL_SECS must be a number"),
+            typing_ttl_secs: env::var("TYPING_TTL_SECS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .expect("TYPING_TTL_SECS must be a number"),
+            message_cache_ttl_secs: env::var("MESSAGE_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .expect("MESSAGE_CACHE_TTL_SECS must be a number"),
+            message_cache_timeout_ms: env::var("MESSAGE_CACHE_TIMEOUT_MS")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse()
+                .expect("MESSAGE_CACHE_TIMEOUT_MS must be a number"),
+            message_edit_window_secs: env::var("MESSAGE_EDIT_WINDOW_SECS")
+                .unwrap_or_else(|_| "900".to_string())
+                .parse()
+                .expect("MESSAGE_EDIT_WINDOW_SECS must be a number"),
+            internal_token: env::var("INTERNAL_API_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty()),
         }
     }
 }
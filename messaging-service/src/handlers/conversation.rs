@@ -1,25 +1,47 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Json,
 };
 use chrono::Utc;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use crate::{models::conversation::{Conversation, CreateConversationRequest}, services::conversation::ConversationService};
-use shared::jwt::AuthenticatedUser;
+use crate::{
+    handlers::messaging::PaginationQuery,
+    models::{
+        conversation::{is_valid_user_id, Conversation, CreateConversationRequest, InboxEntry},
+        event::RealtimeEvent,
+    },
+    services::{conversation::ConversationService, realtime::RealtimeHub},
+};
+use shared::{jwt::AuthenticatedUser, ErrorBody};
 
 #[derive(Clone)]
 pub struct ConvAppState {
     pub conversation_service: Arc<ConversationService>,
+    pub realtime: Arc<RealtimeHub>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/conversations",
+    tag = "conversations",
+    request_body = CreateConversationRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Conversation created or existing one returned", body = Conversation),
+        (status = 400, description = "Invalid participant id", body = ErrorBody),
+    )
+)]
 pub async fn create_conversation(
     State(state): State<ConvAppState>,
     AuthenticatedUser(user): AuthenticatedUser,
     Json(req): Json<CreateConversationRequest>,
 ) -> Result<(StatusCode, Json<Conversation>), (StatusCode, String)> {
     let mut participants = req.participants;
+    if !participants.iter().all(|p| is_valid_user_id(p)) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid participant id".to_string()));
+    }
     if !participants.contains(&user.sub) {
         participants.push(user.sub.clone());
     }
@@ -29,6 +51,11 @@ pub async fn create_conversation(
         participants,
         created_at: Utc::now(),
         updated_at: Utc::now(),
+        read_markers: HashMap::new(),
+        delivered_markers: HashMap::new(),
+        last_message: None,
+        last_activity_at: Some(Utc::now()),
+        unread_counts: HashMap::new(),
     };
 
     let created = state.conversation_service.create(conv).await.map_err(|_| {
@@ -41,6 +68,17 @@ pub async fn create_conversation(
     Ok((StatusCode::CREATED, Json(created)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/conversations",
+    tag = "conversations",
+    params(("user_id" = String, Path, description = "User id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Conversations of the user", body = [Conversation]),
+        (status = 403, description = "Access denied", body = ErrorBody),
+    )
+)]
 pub async fn get_conversations_by_user(
     State(state): State<ConvAppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -64,6 +102,58 @@ pub async fn get_conversations_by_user(
     Ok(Json(conversations))
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/inbox",
+    tag = "conversations",
+    params(("user_id" = String, Path, description = "User id"), PaginationQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Conversations, most recent activity first", body = [InboxEntry]),
+        (status = 403, description = "Access denied", body = ErrorBody),
+    )
+)]
+pub async fn get_inbox(
+    State(state): State<ConvAppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(user_id): Path<String>,
+    Query(pagination): Query<PaginationQuery>,
+) -> Result<Json<Vec<InboxEntry>>, (StatusCode, String)> {
+    if user.sub != user_id {
+        return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
+    }
+
+    let conversations = state
+        .conversation_service
+        .inbox(&user_id, pagination.skip, pagination.limit)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch inbox".to_string(),
+            )
+        })?;
+
+    let entries = conversations
+        .iter()
+        .filter_map(|conv| conv.inbox_entry(&user_id))
+        .collect();
+
+    Ok(Json(entries))
+}
+
+#[utoipa::path(
+    get,
+    path = "/conversations/{conversation_id}",
+    tag = "conversations",
+    params(("conversation_id" = String, Path, description = "Conversation id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Conversation", body = Conversation),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    )
+)]
 pub async fn get_conversation(
     State(state): State<ConvAppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -92,6 +182,18 @@ pub async fn get_conversation(
     Ok(Json(conversation))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/conversations/{conversation_id}",
+    tag = "conversations",
+    params(("conversation_id" = String, Path, description = "Conversation id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Conversation deleted", body = String),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    )
+)]
 pub async fn delete_conversation(
     State(state): State<ConvAppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -128,9 +230,28 @@ pub async fn delete_conversation(
             )
         })?;
 
+    state
+        .realtime
+        .publish(RealtimeEvent::ConversationDeleted { conversation_id })
+        .await;
+
     Ok((StatusCode::OK, "Conversation deleted".to_string()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/conversations/{conversation_id}/members",
+    tag = "conversations",
+    params(("conversation_id" = String, Path, description = "Conversation id")),
+    request_body(content = Object, description = "`{\"user_id\": \"...\"}`"),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Participant added", body = String),
+        (status = 400, description = "Missing or invalid user_id", body = ErrorBody),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    )
+)]
 pub async fn add_participant(
     State(state): State<ConvAppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -144,6 +265,9 @@ pub async fn add_participant(
     let user_id = participant["user_id"]
         .as_str()
         .ok_or((StatusCode::BAD_REQUEST, "Missing user_id".to_string()))?;
+    if !is_valid_user_id(user_id) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid user_id".to_string()));
+    }
 
     let conversation = state
         .conversation_service
@@ -175,6 +299,22 @@ pub async fn add_participant(
     Ok((StatusCode::OK, "Participant added".to_string()))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/conversations/{conversation_id}/members/{user_id}",
+    tag = "conversations",
+    params(
+        ("conversation_id" = String, Path, description = "Conversation id"),
+        ("user_id" = String, Path, description = "Participant to remove"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Participant removed", body = String),
+        (status = 400, description = "Invalid conversation or user id", body = ErrorBody),
+        (status = 403, description = "Access denied", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    )
+)]
 pub async fn remove_participant(
     State(state): State<ConvAppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -183,6 +323,9 @@ pub async fn remove_participant(
     let conv_id = mongodb::bson::oid::ObjectId::parse_str(&conversation_id).map_err(|_| {
         (StatusCode::BAD_REQUEST, "Invalid conversation id".to_string())
     })?;
+    if !is_valid_user_id(&user_id) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid user id".to_string()));
+    }
 
     let conversation = state
         .conversation_service
@@ -211,5 +354,13 @@ pub async fn remove_participant(
             )
         })?;
 
+    state
+        .realtime
+        .publish(RealtimeEvent::MemberRemoved {
+            conversation_id,
+            user_id,
+        })
+        .await;
+
     Ok((StatusCode::OK, "Participant removed".to_string()))
 }
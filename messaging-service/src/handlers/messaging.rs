@@ -1,22 +1,36 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, HeaderValue, StatusCode},
     Json,
 };
-use chrono::Utc;
+use chrono::{DateTime, SubsecRound, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::Deserialize;
-use std::sync::Arc;
-
-use deadpool_redis::redis::AsyncCommands;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use subtle::ConstantTimeEq;
+use utoipa::IntoParams;
 
 use crate::{
-    models::message::Message, services::conversation::ConversationService,
-    services::messaging::MessageService,
+    models::{
+        conversation::{Conversation, MarkReceiptRequest, MessagePreview, ReceiptMarker},
+        event::RealtimeEvent,
+        message::{
+            is_valid_emoji, AddReactionRequest, EditMessageRequest, HistoryBound, Message,
+            MessageCursor, MessageRevision, Reaction, ReactionSummary, MAX_REACTIONS_PER_USER,
+        },
+    },
+    services::cache::{CacheLookup, CacheStats, MessageCache},
+    services::conversation::ConversationService,
+    services::messaging::{MessageService, ReactionOutcome},
+    services::presence::PresenceService,
+    services::realtime::RealtimeHub,
 };
-use shared::jwt::AuthenticatedUser;
+use shared::{jwt::AuthenticatedUser, ErrorBody};
+
+/// Retries of the unread recount when messages keep arriving meanwhile.
+const UNREAD_REFRESH_ATTEMPTS: usize = 3;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
 pub struct PaginationQuery {
     #[serde(default)]
     pub skip: i64,
@@ -28,19 +42,60 @@ fn default_limit() -> i64 {
     50
 }
 
+#[derive(Deserialize, IntoParams)]
+pub struct CursorQuery {
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// History paging: `before`/`after` take an opaque cursor, a message id or an
+/// RFC 3339 timestamp. `skip` is kept for older clients and excludes both.
+#[derive(Deserialize, IntoParams)]
+pub struct HistoryQuery {
+    #[serde(default)]
+    pub skip: i64,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub message_service: Arc<MessageService>,
     pub conversation_service: Arc<ConversationService>,
-    pub redis_pool: Arc<deadpool_redis::Pool>,
+    pub cache: Arc<MessageCache>,
+    /// How long after sending a message its sender may still edit it.
+    pub edit_window: Duration,
+    pub realtime: Arc<RealtimeHub>,
+    pub presence: Arc<PresenceService>,
+    pub internal_token: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/conversations/{conversation_id}/messages",
+    tag = "messages",
+    params(("conversation_id" = String, Path, description = "Conversation id"), HistoryQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Messages, newest first", body = [Message], headers(
+            ("X-Next-Cursor" = String, description = "Pass as `before` to fetch older messages"),
+            ("X-Prev-Cursor" = String, description = "Pass as `after` to fetch newer messages"),
+        )),
+        (status = 400, description = "Invalid conversation id or cursor", body = ErrorBody),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    )
+)]
 pub async fn get_messages(
     State(state): State<AppState>,
     AuthenticatedUser(user): AuthenticatedUser,
     Path(conversation_id): Path<String>,
-    Query(pagination): Query<PaginationQuery>,
-) -> Result<Json<Vec<Message>>, (StatusCode, String)> {
+    Query(pagination): Query<HistoryQuery>,
+) -> Result<(HeaderMap, Json<Vec<Message>>), (StatusCode, String)> {
     let conv_id = ObjectId::parse_str(&conversation_id).map_err(|_| {
         (
             StatusCode::BAD_REQUEST,
@@ -64,58 +119,190 @@ pub async fn get_messages(
         return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
     }
 
-    let mut con = state.redis_pool.get().await.map_err(|_| {
+    let before = match &pagination.before {
+        Some(raw) => Some(resolve_bound(&state, conv_id, raw).await?),
+        None => None,
+    };
+    let after = match &pagination.after {
+        Some(raw) => Some(resolve_bound(&state, conv_id, raw).await?),
+        None => None,
+    };
+    if pagination.skip != 0 && (before.is_some() || after.is_some()) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "skip cannot be combined with before/after".to_string(),
+        ));
+    }
+
+    let limit = pagination.limit.clamp(1, 100);
+    let page = page_key(pagination.skip, limit, before.as_ref(), after.as_ref());
+    let cache_id = conv_id.to_hex();
+    let version = match state.cache.get(&cache_id, &page).await {
+        CacheLookup::Hit(mut messages) => {
+            for message in &mut messages {
+                message.apply_reactions(Some(&user.sub));
+            }
+            return Ok((page_cursors(&messages, limit), Json(messages)));
+        }
+        CacheLookup::Miss(version) => Some(version),
+        CacheLookup::Unavailable => None,
+    };
+
+    let messages = if before.is_some() || after.is_some() {
+        state
+            .message_service
+            .get_messages_page(conv_id, before.as_ref(), after.as_ref(), limit)
+            .await
+    } else {
+        state
+            .message_service
+            .get_messages_by_conversation(conv_id, pagination.skip, limit)
+            .await
+    };
+    let mut messages = messages.map_err(|_| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            "Internal error (Redis)".to_string(),
+            "Internal error".to_string(),
         )
     })?;
 
-    let cache_key = format!("messages:{}", conversation_id);
-    if let Ok(Some(cached)) = con.get::<String, Option<String>>(cache_key.clone()).await {
-        if let Ok(messages) = serde_json::from_str::<Vec<Message>>(&cached) {
-            return Ok(Json(messages));
-        }
+    for message in &mut messages {
+        conversation.apply_receipts(message);
     }
 
-    let messages = state
-        .message_service
-        .get_messages_by_conversation(conv_id, pagination.skip, pagination.limit)
-        .await
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Internal error".to_string(),
-            )
-        })?;
+    if let Some(version) = version {
+        state.cache.put(&cache_id, version, &page, &messages).await;
+    }
 
-    let serialized = serde_json::to_string(&messages).unwrap();
-    let _: () = con.set_ex(cache_key, serialized, 60).await.map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Redis set error".to_string(),
-        )
-    })?;
+    for message in &mut messages {
+        message.apply_reactions(Some(&user.sub));
+    }
+
+    Ok((page_cursors(&messages, pagination.limit), Json(messages)))
+}
+
+/// Hit rate of the conversation history cache; not routed by the API Gateway.
+pub async fn cache_stats(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<CacheStats>, (StatusCode, String)> {
+    verify_internal_token(&state, &headers)?;
+    Ok(Json(state.cache.stats()))
+}
+
+fn verify_internal_token(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, String)> {
+    let expected = state.internal_token.as_deref().ok_or((
+        StatusCode::FORBIDDEN,
+        "Internal API is disabled".to_string(),
+    ))?;
+
+    let provided = headers
+        .get("x-internal-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+
+    // Constant time, like the gateway, so timing leaks nothing about the token.
+    if !bool::from(provided.as_bytes().ct_eq(expected.as_bytes())) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Invalid internal token".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+async fn resolve_bound(
+    state: &AppState,
+    conv_id: ObjectId,
+    raw: &str,
+) -> Result<HistoryBound, (StatusCode, String)> {
+    if let Some(cursor) = MessageCursor::decode(raw) {
+        return Ok(HistoryBound::Cursor(cursor));
+    }
+    if ObjectId::parse_str(raw).is_ok() {
+        let message = find_in_conversation(state, &conv_id.to_hex(), raw).await?;
+        return MessageCursor::new(&message)
+            .map(HistoryBound::Cursor)
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Internal error".to_string()));
+    }
+    DateTime::parse_from_rfc3339(raw)
+        .map(|time| HistoryBound::Time(time.with_timezone(&Utc)))
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))
+}
 
-    Ok(Json(messages))
+pub fn page_key(
+    skip: i64,
+    limit: i64,
+    before: Option<&HistoryBound>,
+    after: Option<&HistoryBound>,
+) -> String {
+    let bound = |bound: Option<&HistoryBound>| match bound {
+        Some(HistoryBound::Cursor(cursor)) => cursor.encode(),
+        Some(HistoryBound::Time(time)) => time.to_rfc3339(),
+        None => String::new(),
+    };
+    format!("{}:{}:{}:{}", skip, limit, bound(before), bound(after))
 }
 
+// `next` pages towards older messages and is only set when the page came back full.
+pub fn page_cursors(messages: &[Message], limit: i64) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let newest = messages.first().and_then(MessageCursor::new);
+    let oldest = messages.last().and_then(MessageCursor::new);
+    if let Some(oldest) = oldest.filter(|_| messages.len() as i64 >= limit.clamp(1, 100)) {
+        if let Ok(value) = HeaderValue::from_str(&oldest.encode()) {
+            headers.insert("x-next-cursor", value);
+        }
+    }
+    if let Some(newest) = newest {
+        if let Ok(value) = HeaderValue::from_str(&newest.encode()) {
+            headers.insert("x-prev-cursor", value);
+        }
+    }
+    headers
+}
+
+#[utoipa::path(
+    post,
+    path = "/messages",
+    tag = "messages",
+    request_body = Message,
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response when a retry reuses the same key")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Message sent", body = Object),
+        (status = 400, description = "Empty or too long content", body = ErrorBody),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+        (status = 409, description = "A request with this Idempotency-Key is in progress", body = ErrorBody),
+        (status = 422, description = "Idempotency-Key reused with a different body", body = ErrorBody),
+    )
+)]
 pub async fn send_message(
     State(state): State<AppState>,
     AuthenticatedUser(user): AuthenticatedUser,
     Json(mut msg): Json<Message>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), (StatusCode, String)> {
-    if msg.content.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Message content cannot be empty".to_string()));
-    }
-
-    if msg.content.len() > 10000 {
-        return Err((StatusCode::BAD_REQUEST, "Message content too long (max 10000 characters)".to_string()));
-    }
+    validate_content(&msg.content)?;
 
+    msg.id = None;
     msg.sender_id = user.sub.clone();
-    msg.sent_at = Utc::now();
+    // Stored to the millisecond; the response and cursors match what is stored.
+    msg.sent_at = Utc::now().trunc_subsecs(3);
     msg.read = false;
+    msg.read_by.clear();
+    msg.delivered_to.clear();
+    msg.edited_at = None;
+    msg.user_reactions.clear();
+    msg.reactions.clear();
+    msg.quoted = None;
+    msg.thread_id = None;
+    msg.reply_count = 0;
+    msg.last_reply_at = None;
 
     let conv_id = msg.conversation_id;
     let conversation = state
@@ -134,6 +321,25 @@ pub async fn send_message(
         return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
     }
 
+    if let Some(reply_to) = msg.reply_to {
+        let target = state
+            .message_service
+            .find_by_id(reply_to)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal error".to_string(),
+                )
+            })?;
+        if !target.is_some_and(|target| msg.set_reply_target(&target)) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Can only reply to a message in the same conversation".to_string(),
+            ));
+        }
+    }
+
     let inserted_id = state
         .message_service
         .send_message(msg.clone())
@@ -145,19 +351,37 @@ pub async fn send_message(
             )
         })?;
 
-    let mut con = state.redis_pool.get().await.map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Internal error (Redis)".to_string(),
-        )
-    })?;
-    let cache_key = format!("messages:{}", conv_id);
-    let _: () = con.del(cache_key).await.map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Redis delete error".to_string(),
-        )
-    })?;
+    msg.id = ObjectId::parse_str(&inserted_id).ok();
+    state
+        .realtime
+        .publish(RealtimeEvent::MessageCreated {
+            message: msg.clone(),
+        })
+        .await;
+
+    if let Some(preview) = MessagePreview::new(&msg) {
+        let recipients: Vec<String> = conversation
+            .participants
+            .iter()
+            .filter(|p| **p != user.sub)
+            .cloned()
+            .collect();
+        if let Err(e) = state
+            .conversation_service
+            .record_message(conv_id, &preview, &recipients)
+            .await
+        {
+            tracing::warn!("failed to update inbox for conversation {}: {}", conv_id, e);
+        }
+    }
+
+    if let Some(root_id) = msg.thread_id {
+        if let Err(e) = state.message_service.record_reply(root_id, msg.sent_at).await {
+            tracing::warn!("failed to count reply in thread {}: {}", root_id, e);
+        }
+    }
+
+    state.cache.invalidate(&conv_id.to_hex()).await;
 
     Ok((
         StatusCode::CREATED,
@@ -168,6 +392,30 @@ pub async fn send_message(
     ))
 }
 
+fn validate_content(content: &str) -> Result<(), (StatusCode, String)> {
+    if content.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Message content cannot be empty".to_string()));
+    }
+
+    if content.len() > 10000 {
+        return Err((StatusCode::BAD_REQUEST, "Message content too long (max 10000 characters)".to_string()));
+    }
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/messages/{message_id}",
+    tag = "messages",
+    params(("message_id" = String, Path, description = "Message id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Message", body = Message),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Message not found", body = ErrorBody),
+    )
+)]
 pub async fn get_message(
     State(state): State<AppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -205,19 +453,41 @@ pub async fn get_message(
         return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
     }
 
+    let mut message = message;
+    conversation.apply_receipts(&mut message);
+    message.apply_reactions(Some(&user.sub));
+
     Ok(Json(message))
 }
 
-pub async fn mark_as_read(
+#[utoipa::path(
+    patch,
+    path = "/messages/{message_id}",
+    tag = "messages",
+    params(("message_id" = String, Path, description = "Message id")),
+    request_body = EditMessageRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Edited message", body = Message),
+        (status = 400, description = "Empty or too long content", body = ErrorBody),
+        (status = 403, description = "Not the sender, or the edit window has passed", body = ErrorBody),
+        (status = 404, description = "Message not found", body = ErrorBody),
+        (status = 409, description = "Message was edited concurrently", body = ErrorBody),
+    )
+)]
+pub async fn edit_message(
     State(state): State<AppState>,
     AuthenticatedUser(user): AuthenticatedUser,
     Path(message_id): Path<String>,
-) -> Result<(StatusCode, String), (StatusCode, String)> {
+    Json(req): Json<EditMessageRequest>,
+) -> Result<Json<Message>, (StatusCode, String)> {
+    validate_content(&req.content)?;
+
     let msg_id = ObjectId::parse_str(&message_id).map_err(|_| {
         (StatusCode::BAD_REQUEST, "Invalid message id".to_string())
     })?;
 
-    let message = state
+    let mut message = state
         .message_service
         .find_by_id(msg_id)
         .await
@@ -229,6 +499,15 @@ pub async fn mark_as_read(
         })?
         .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))?;
 
+    if message.sender_id != user.sub {
+        return Err((StatusCode::FORBIDDEN, "Can only edit your own messages".to_string()));
+    }
+
+    let age = (Utc::now() - message.sent_at).to_std().unwrap_or_default();
+    if age > state.edit_window {
+        return Err((StatusCode::FORBIDDEN, "Edit window has expired".to_string()));
+    }
+
     let conversation = state
         .conversation_service
         .find_by_id(message.conversation_id)
@@ -245,9 +524,271 @@ pub async fn mark_as_read(
         return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
     }
 
+    if message.content == req.content {
+        conversation.apply_receipts(&mut message);
+        message.apply_reactions(Some(&user.sub));
+        return Ok(Json(message));
+    }
+
+    let edited_at = state
+        .message_service
+        .edit_message(&message, &req.content)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((
+            StatusCode::CONFLICT,
+            "Message was edited concurrently".to_string(),
+        ))?;
+
+    message.content = req.content;
+    message.edited_at = Some(edited_at);
+    conversation.apply_receipts(&mut message);
+
+    if conversation.last_message.as_ref().map(|last| last.message_id) == Some(msg_id) {
+        let preview = MessagePreview::new(&message);
+        if let Err(e) = state
+            .conversation_service
+            .set_last_message(message.conversation_id, preview.as_ref())
+            .await
+        {
+            tracing::warn!("failed to update inbox after editing {}: {}", msg_id, e);
+        }
+    }
+
+    state.cache.invalidate(&message.conversation_id.to_hex()).await;
+    let mut edited = message.clone();
+    edited.apply_reactions(None);
     state
+        .realtime
+        .publish(RealtimeEvent::MessageEdited { message: edited })
+        .await;
+
+    message.apply_reactions(Some(&user.sub));
+    Ok(Json(message))
+}
+
+#[utoipa::path(
+    get,
+    path = "/messages/{message_id}/revisions",
+    tag = "messages",
+    params(("message_id" = String, Path, description = "Message id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Previous contents, oldest first", body = [MessageRevision]),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Message not found", body = ErrorBody),
+    )
+)]
+pub async fn get_revisions(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(message_id): Path<String>,
+) -> Result<Json<Vec<MessageRevision>>, (StatusCode, String)> {
+    let msg_id = ObjectId::parse_str(&message_id).map_err(|_| {
+        (StatusCode::BAD_REQUEST, "Invalid message id".to_string())
+    })?;
+
+    let message = state
+        .message_service
+        .find_by_id(msg_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))?;
+
+    let conversation = state
+        .conversation_service
+        .find_by_id(message.conversation_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+
+    if !conversation.participants.contains(&user.sub) {
+        return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
+    }
+
+    let revisions = state
+        .message_service
+        .revisions(msg_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?;
+
+    Ok(Json(revisions))
+}
+
+#[utoipa::path(
+    get,
+    path = "/messages/{message_id}/thread",
+    tag = "messages",
+    params(("message_id" = String, Path, description = "Root message id"), CursorQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Replies in the thread, newest first", body = [Message], headers(
+            ("X-Next-Cursor" = String, description = "Pass as `before` to fetch older replies"),
+            ("X-Prev-Cursor" = String, description = "Pass as `after` to fetch newer replies"),
+        )),
+        (status = 400, description = "Invalid message id or cursor", body = ErrorBody),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Message not found", body = ErrorBody),
+    )
+)]
+pub async fn get_thread(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(message_id): Path<String>,
+    Query(pagination): Query<CursorQuery>,
+) -> Result<(HeaderMap, Json<Vec<Message>>), (StatusCode, String)> {
+    let (msg_id, root) = find_for_participant(&state, &user.sub, &message_id).await?;
+    let root_id = root.thread_id.unwrap_or(msg_id);
+    let conv_id = root.conversation_id;
+
+    let before = match &pagination.before {
+        Some(raw) => Some(resolve_bound(&state, conv_id, raw).await?),
+        None => None,
+    };
+    let after = match &pagination.after {
+        Some(raw) => Some(resolve_bound(&state, conv_id, raw).await?),
+        None => None,
+    };
+
+    let limit = pagination.limit.clamp(1, 100);
+    let mut replies = state
+        .message_service
+        .get_thread_page(root_id, before.as_ref(), after.as_ref(), limit)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?;
+
+    let conversation = state
+        .conversation_service
+        .find_by_id(conv_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+
+    for reply in &mut replies {
+        conversation.apply_receipts(reply);
+        reply.apply_reactions(Some(&user.sub));
+    }
+
+    Ok((page_cursors(&replies, limit), Json(replies)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/messages/{message_id}/reactions",
+    tag = "messages",
+    params(("message_id" = String, Path, description = "Message id")),
+    request_body = AddReactionRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Reactions on the message", body = [ReactionSummary]),
+        (status = 400, description = "Not an emoji or too many reactions", body = ErrorBody),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Message not found", body = ErrorBody),
+    )
+)]
+pub async fn add_reaction(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(message_id): Path<String>,
+    Json(req): Json<AddReactionRequest>,
+) -> Result<Json<Vec<ReactionSummary>>, (StatusCode, String)> {
+    if !is_valid_emoji(&req.emoji) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid emoji".to_string()));
+    }
+
+    let (msg_id, message) = find_for_participant(&state, &user.sub, &message_id).await?;
+    let reaction = Reaction {
+        emoji: req.emoji,
+        user_id: user.sub.clone(),
+        reacted_at: Utc::now(),
+    };
+    let outcome = state
+        .message_service
+        .add_reaction(msg_id, &reaction)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?;
+
+    if let ReactionOutcome::LimitReached = outcome {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Too many reactions (max {} per message)", MAX_REACTIONS_PER_USER),
+        ));
+    }
+    if let ReactionOutcome::Added = outcome {
+        state.cache.invalidate(&message.conversation_id.to_hex()).await;
+        state
+            .realtime
+            .publish(RealtimeEvent::ReactionAdded {
+                conversation_id: message.conversation_id.to_hex(),
+                message_id: message_id.clone(),
+                user_id: user.sub.clone(),
+                emoji: reaction.emoji,
+            })
+            .await;
+    }
+
+    reactions_for(&state, &user.sub, msg_id).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/messages/{message_id}/reactions/{emoji}",
+    tag = "messages",
+    params(
+        ("message_id" = String, Path, description = "Message id"),
+        ("emoji" = String, Path, description = "Emoji to remove, percent-encoded"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Reactions on the message", body = [ReactionSummary]),
+        (status = 403, description = "Not a participant", body = ErrorBody),
+        (status = 404, description = "Message not found", body = ErrorBody),
+    )
+)]
+pub async fn remove_reaction(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path((message_id, emoji)): Path<(String, String)>,
+) -> Result<Json<Vec<ReactionSummary>>, (StatusCode, String)> {
+    let (msg_id, message) = find_for_participant(&state, &user.sub, &message_id).await?;
+    let removed = state
         .message_service
-        .update_read_status(msg_id, true)
+        .remove_reaction(msg_id, &emoji, &user.sub)
         .await
         .map_err(|_| {
             (
@@ -256,23 +797,386 @@ pub async fn mark_as_read(
             )
         })?;
 
-    let mut con = state.redis_pool.get().await.map_err(|_| {
+    if removed {
+        state.cache.invalidate(&message.conversation_id.to_hex()).await;
+        state
+            .realtime
+            .publish(RealtimeEvent::ReactionRemoved {
+                conversation_id: message.conversation_id.to_hex(),
+                message_id: message_id.clone(),
+                user_id: user.sub.clone(),
+                emoji,
+            })
+            .await;
+    }
+
+    reactions_for(&state, &user.sub, msg_id).await
+}
+
+async fn find_for_participant(
+    state: &AppState,
+    user_id: &str,
+    message_id: &str,
+) -> Result<(ObjectId, Message), (StatusCode, String)> {
+    let msg_id = ObjectId::parse_str(message_id).map_err(|_| {
+        (StatusCode::BAD_REQUEST, "Invalid message id".to_string())
+    })?;
+
+    let message = state
+        .message_service
+        .find_by_id(msg_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))?;
+
+    let conversation = state
+        .conversation_service
+        .find_by_id(message.conversation_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+
+    if !conversation.participants.iter().any(|p| p == user_id) {
+        return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
+    }
+
+    Ok((msg_id, message))
+}
+
+async fn reactions_for(
+    state: &AppState,
+    user_id: &str,
+    msg_id: ObjectId,
+) -> Result<Json<Vec<ReactionSummary>>, (StatusCode, String)> {
+    let mut message = state
+        .message_service
+        .find_by_id(msg_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))?;
+    message.apply_reactions(Some(user_id));
+
+    Ok(Json(message.reactions))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/messages/{message_id}/read",
+    tag = "messages",
+    params(("message_id" = String, Path, description = "Message id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Message and everything before it marked as read", body = String),
+        (status = 403, description = "Not a participant, or own message", body = ErrorBody),
+        (status = 404, description = "Message not found", body = ErrorBody),
+    )
+)]
+pub async fn mark_as_read(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(message_id): Path<String>,
+) -> Result<(StatusCode, String), (StatusCode, String)> {
+    let msg_id = ObjectId::parse_str(&message_id).map_err(|_| {
+        (StatusCode::BAD_REQUEST, "Invalid message id".to_string())
+    })?;
+
+    let message = state
+        .message_service
+        .find_by_id(msg_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))?;
+
+    mark_up_to(&state, &user.sub, message, Receipt::Read).await?;
+
+    Ok((StatusCode::OK, "Message marked as read".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/conversations/{conversation_id}/read",
+    tag = "messages",
+    request_body = MarkReceiptRequest,
+    params(("conversation_id" = String, Path, description = "Conversation id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Caller's read marker, which never moves backwards", body = ReceiptMarker),
+        (status = 403, description = "Not a participant, or own message", body = ErrorBody),
+        (status = 404, description = "Message not found in this conversation", body = ErrorBody),
+    )
+)]
+pub async fn mark_conversation_read(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<MarkReceiptRequest>,
+) -> Result<Json<ReceiptMarker>, (StatusCode, String)> {
+    let message = find_in_conversation(&state, &conversation_id, &req.message_id).await?;
+    let marker = mark_up_to(&state, &user.sub, message, Receipt::Read).await?;
+    Ok(Json(marker))
+}
+
+#[utoipa::path(
+    post,
+    path = "/conversations/{conversation_id}/delivered",
+    tag = "messages",
+    request_body = MarkReceiptRequest,
+    params(("conversation_id" = String, Path, description = "Conversation id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Caller's delivery marker, which never moves backwards", body = ReceiptMarker),
+        (status = 403, description = "Not a participant, or own message", body = ErrorBody),
+        (status = 404, description = "Message not found in this conversation", body = ErrorBody),
+    )
+)]
+pub async fn mark_conversation_delivered(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(conversation_id): Path<String>,
+    Json(req): Json<MarkReceiptRequest>,
+) -> Result<Json<ReceiptMarker>, (StatusCode, String)> {
+    let message = find_in_conversation(&state, &conversation_id, &req.message_id).await?;
+    let marker = mark_up_to(&state, &user.sub, message, Receipt::Delivered).await?;
+    Ok(Json(marker))
+}
+
+async fn find_in_conversation(
+    state: &AppState,
+    conversation_id: &str,
+    message_id: &str,
+) -> Result<Message, (StatusCode, String)> {
+    let conv_id = ObjectId::parse_str(conversation_id).map_err(|_| {
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Internal error (Redis)".to_string(),
+            StatusCode::BAD_REQUEST,
+            "Invalid conversation id".to_string(),
         )
     })?;
-    let cache_key = format!("messages:{}", message.conversation_id);
-    let _: () = con.del(cache_key).await.map_err(|_| {
+    let msg_id = ObjectId::parse_str(message_id).map_err(|_| {
+        (StatusCode::BAD_REQUEST, "Invalid message id".to_string())
+    })?;
+
+    state
+        .message_service
+        .find_by_id(msg_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .filter(|message| message.conversation_id == conv_id)
+        .ok_or((StatusCode::NOT_FOUND, "Message not found".to_string()))
+}
+
+#[derive(Clone, Copy)]
+enum Receipt {
+    Read,
+    Delivered,
+}
+
+// Moves the caller's marker forward to `message`; reading a message also delivers it.
+async fn mark_up_to(
+    state: &AppState,
+    user_id: &str,
+    message: Message,
+    receipt: Receipt,
+) -> Result<ReceiptMarker, (StatusCode, String)> {
+    let conversation = state
+        .conversation_service
+        .find_by_id(message.conversation_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "Conversation not found".to_string()))?;
+
+    if !conversation.participants.iter().any(|p| p == user_id) {
+        return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
+    }
+
+    if message.sender_id == user_id {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Cannot mark your own message".to_string(),
+        ));
+    }
+
+    let marker = ReceiptMarker::new(&message).ok_or((
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Internal error".to_string(),
+    ))?;
+
+    let fields: &[(&str, &HashMap<String, ReceiptMarker>)] = match receipt {
+        Receipt::Read => &[
+            ("read_markers", &conversation.read_markers),
+            ("delivered_markers", &conversation.delivered_markers),
+        ],
+        Receipt::Delivered => &[("delivered_markers", &conversation.delivered_markers)],
+    };
+
+    let mut changed = false;
+    let mut advanced = false;
+    for (i, (field, markers)) in fields.iter().enumerate() {
+        if let Some(current) = markers.get(user_id) {
+            if current.covers(marker.sent_at, marker.message_id) {
+                continue;
+            }
+        }
+
+        // Another request may have moved the marker further since it was read.
+        let moved = state
+            .conversation_service
+            .set_marker(message.conversation_id, field, user_id, &marker)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal error".to_string(),
+                )
+            })?;
+        changed |= moved;
+        advanced |= moved && i == 0;
+    }
+
+    if advanced && matches!(receipt, Receipt::Read) {
+        refresh_unread(state, message.conversation_id, &conversation, user_id, &marker).await?;
+    }
+
+    let current = match receipt {
+        Receipt::Read => conversation.read_markers.get(user_id),
+        Receipt::Delivered => conversation.delivered_markers.get(user_id),
+    };
+    let marker = match current {
+        Some(current) if current.covers(marker.sent_at, marker.message_id) => current.clone(),
+        _ => marker,
+    };
+
+    if !changed {
+        return Ok(marker);
+    }
+
+    let conversation_id = message.conversation_id.to_hex();
+    let event = match receipt {
+        _ if !advanced => None,
+        Receipt::Read => Some(RealtimeEvent::MessageRead {
+            conversation_id: conversation_id.clone(),
+            message_id: marker.message_id.to_hex(),
+            reader_id: user_id.to_string(),
+            read_at: marker.marked_at,
+        }),
+        Receipt::Delivered => Some(RealtimeEvent::MessageDelivered {
+            conversation_id: conversation_id.clone(),
+            message_id: marker.message_id.to_hex(),
+            user_id: user_id.to_string(),
+            delivered_at: marker.marked_at,
+        }),
+    };
+    if let Some(event) = event {
+        state.realtime.publish(event).await;
+    }
+
+    state.cache.invalidate(&conversation_id).await;
+
+    Ok(marker)
+}
+
+// Recomputes the caller's unread count once their read marker has moved.
+async fn refresh_unread(
+    state: &AppState,
+    conv_id: ObjectId,
+    conversation: &Conversation,
+    user_id: &str,
+    marker: &ReceiptMarker,
+) -> Result<(), (StatusCode, String)> {
+    let internal = |_| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            "Redis delete error".to_string(),
+            "Internal error".to_string(),
         )
-    })?;
+    };
 
-    Ok((StatusCode::OK, "Message marked as read".to_string()))
+    let mut last_message = conversation.last_message.clone();
+    for _ in 0..UNREAD_REFRESH_ATTEMPTS {
+        let caught_up = last_message
+            .as_ref()
+            .is_none_or(|last| marker.covers(last.sent_at, last.message_id));
+        let unread = if caught_up {
+            0
+        } else {
+            state
+                .message_service
+                .count_unread(conv_id, user_id, Some(marker))
+                .await
+                .map_err(internal)?
+        };
+
+        let applied = state
+            .conversation_service
+            .set_unread(
+                conv_id,
+                user_id,
+                unread,
+                last_message.as_ref().map(|last| last.message_id),
+            )
+            .await
+            .map_err(internal)?;
+        if applied {
+            return Ok(());
+        }
+
+        // A message arrived meanwhile; count again against the new last message.
+        last_message = state
+            .conversation_service
+            .find_by_id(conv_id)
+            .await
+            .map_err(internal)?
+            .and_then(|conversation| conversation.last_message);
+    }
+
+    tracing::warn!(
+        "Gave up refreshing unread count of {} in conversation {}",
+        user_id,
+        conv_id
+    );
+    Ok(())
 }
 
+#[utoipa::path(
+    delete,
+    path = "/messages/{message_id}",
+    tag = "messages",
+    params(("message_id" = String, Path, description = "Message id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Message deleted", body = String),
+        (status = 403, description = "Not the sender", body = ErrorBody),
+        (status = 404, description = "Message not found", body = ErrorBody),
+    )
+)]
 pub async fn delete_message(
     State(state): State<AppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -309,19 +1213,68 @@ pub async fn delete_message(
             )
         })?;
 
-    let mut con = state.redis_pool.get().await.map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Internal error (Redis)".to_string(),
-        )
-    })?;
-    let cache_key = format!("messages:{}", message.conversation_id);
-    let _: () = con.del(cache_key).await.map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Redis delete error".to_string(),
-        )
-    })?;
+    if let Err(e) = forget_in_inbox(&state, &message).await {
+        tracing::warn!("failed to update inbox after deleting {}: {}", msg_id, e);
+    }
+
+    if let Some(root_id) = message.thread_id {
+        if let Err(e) = state.message_service.forget_reply(root_id).await {
+            tracing::warn!("failed to uncount reply in thread {}: {}", root_id, e);
+        }
+    }
+
+    state
+        .realtime
+        .publish(RealtimeEvent::MessageDeleted {
+            conversation_id: message.conversation_id.to_hex(),
+            message_id: message_id.clone(),
+        })
+        .await;
+
+    state.cache.invalidate(&message.conversation_id.to_hex()).await;
 
     Ok((StatusCode::OK, "Message deleted".to_string()))
 }
+
+// Undoes what `record_message` counted for a message that no longer exists.
+async fn forget_in_inbox(state: &AppState, message: &Message) -> anyhow::Result<()> {
+    let (Some(id), Some(conversation)) = (
+        message.id,
+        state
+            .conversation_service
+            .find_by_id(message.conversation_id)
+            .await?,
+    ) else {
+        return Ok(());
+    };
+
+    let unread_by: Vec<String> = conversation
+        .participants
+        .iter()
+        .filter(|p| **p != message.sender_id)
+        .filter(|p| {
+            conversation
+                .read_markers
+                .get(*p)
+                .is_none_or(|marker| !marker.covers(message.sent_at, id))
+        })
+        .cloned()
+        .collect();
+    state
+        .conversation_service
+        .decrement_unread(message.conversation_id, &unread_by)
+        .await?;
+
+    if conversation.last_message.as_ref().map(|last| last.message_id) == Some(id) {
+        let latest = state
+            .message_service
+            .latest(message.conversation_id)
+            .await?;
+        let preview = latest.as_ref().and_then(MessagePreview::new);
+        state
+            .conversation_service
+            .set_last_message(message.conversation_id, preview.as_ref())
+            .await?;
+    }
+    Ok(())
+}
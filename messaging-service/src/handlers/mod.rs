@@ -1,2 +1,4 @@
 pub mod conversation;
 pub mod messaging;
+pub mod presence;
+pub mod realtime;
@@ -0,0 +1,50 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+
+use crate::{handlers::messaging::AppState, models::presence::Presence};
+use shared::{jwt::AuthenticatedUser, ErrorBody};
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/presence",
+    tag = "realtime",
+    params(("user_id" = String, Path, description = "User id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Online status and last activity", body = Presence),
+        (status = 403, description = "No shared conversation with this user", body = ErrorBody),
+    )
+)]
+pub async fn get_presence(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<Presence>, (StatusCode, String)> {
+    if user_id != user.sub {
+        let shared = state
+            .conversation_service
+            .share_conversation(&user.sub, &user_id)
+            .await
+            .map_err(|_| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Internal error".to_string(),
+                )
+            })?;
+        if !shared {
+            return Err((StatusCode::FORBIDDEN, "Access denied".to_string()));
+        }
+    }
+
+    let presence = state.presence.get(&user_id).await.map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal error (Redis)".to_string(),
+        )
+    })?;
+
+    Ok(Json(presence))
+}
@@ -0,0 +1,292 @@
+use axum::{
+    extract::{
+        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::Response,
+};
+use futures::{SinkExt, StreamExt};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tokio::time::{interval, sleep_until, Instant};
+use utoipa::IntoParams;
+
+use crate::{
+    handlers::messaging::AppState,
+    models::event::{ClientFrame, RealtimeEvent},
+    services::realtime::Subscription,
+};
+use shared::{jwt::AuthenticatedUser, ErrorBody};
+
+const REPLAY_LIMIT: i64 = 500;
+/// How long a socket waits for its Redis subscriptions before replaying or
+/// confirming a `subscribe` frame.
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, IntoParams)]
+pub struct RealtimeQuery {
+    /// Last message id the client has seen; newer messages are replayed first.
+    pub since: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/messages/ws",
+    tag = "realtime",
+    params(RealtimeQuery),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 101, description = "WebSocket streaming message.created, message.deleted and message.read events"),
+        (status = 400, description = "Invalid since id", body = ErrorBody),
+    )
+)]
+pub async fn realtime(
+    State(state): State<AppState>,
+    AuthenticatedUser(user): AuthenticatedUser,
+    Query(query): Query<RealtimeQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, String)> {
+    let since = query
+        .since
+        .map(|id| ObjectId::parse_str(&id))
+        .transpose()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid since id".to_string()))?;
+
+    let conversations = state
+        .conversation_service
+        .find_by_participant(&user.sub)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal error".to_string(),
+            )
+        })?;
+
+    let mut subscription = state.realtime.connect(&user.sub);
+    for conversation in &conversations {
+        if let Some(id) = conversation.id {
+            subscription.join(id.to_hex());
+        }
+    }
+
+    Ok(ws.on_upgrade(move |socket| async move {
+        run_socket(state, user.sub, subscription, since, socket).await;
+    }))
+}
+
+async fn run_socket(
+    state: AppState,
+    user_id: String,
+    mut subscription: Subscription,
+    since: Option<ObjectId>,
+    socket: WebSocket,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    let socket_id = ObjectId::new().to_hex();
+    heartbeat(&state, &user_id, &socket_id, &subscription).await;
+
+    // Subscribed before replaying, so clients may see a message twice but never miss one.
+    if let Some(since) = since {
+        wait_subscribed(&state, &user_id).await;
+        let conversation_ids: Vec<ObjectId> = subscription
+            .conversations()
+            .iter()
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        match state
+            .message_service
+            .find_since(&conversation_ids, since, REPLAY_LIMIT)
+            .await
+        {
+            Ok(messages) => {
+                for mut message in messages {
+                    message.apply_reactions(Some(&user_id));
+                    if send_event(&mut sender, &RealtimeEvent::MessageCreated { message })
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+            Err(e) => tracing::error!("Failed to replay messages for {}: {}", user_id, e),
+        }
+    }
+
+    let mut ping = interval(state.realtime.ping_interval);
+    let mut deadline = Instant::now() + state.realtime.idle_timeout;
+
+    loop {
+        tokio::select! {
+            Some(event) = subscription.events.recv() => {
+                if event.revokes(&user_id) {
+                    subscription.forget(&event.conversation_id());
+                }
+                if send_event(&mut sender, &event).await.is_err() {
+                    break;
+                }
+            }
+            frame = receiver.next() => {
+                let Some(Ok(frame)) = frame else {
+                    break;
+                };
+                deadline = Instant::now() + state.realtime.idle_timeout;
+
+                let reply = match frame {
+                    WsMessage::Text(text) => handle_frame(&state, &user_id, &mut subscription, &text).await,
+                    WsMessage::Close(_) => break,
+                    _ => None,
+                };
+                if let Some(reply) = reply {
+                    if sender.send(WsMessage::Text(reply.to_string().into())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            _ = ping.tick() => {
+                if sender.send(WsMessage::Ping(Default::default())).await.is_err() {
+                    break;
+                }
+                heartbeat(&state, &user_id, &socket_id, &subscription).await;
+            }
+            _ = sleep_until(deadline) => {
+                tracing::debug!("Closing idle realtime socket for {}", user_id);
+                break;
+            }
+        }
+    }
+
+    let _ = sender.close().await;
+
+    match state.presence.disconnect(&user_id, &socket_id).await {
+        Ok(true) => announce_presence(&state, &user_id, &subscription).await,
+        Ok(false) => {}
+        Err(e) => tracing::warn!("Failed to clear presence for {}: {}", user_id, e),
+    }
+}
+
+async fn wait_subscribed(state: &AppState, user_id: &str) {
+    if tokio::time::timeout(SUBSCRIBE_TIMEOUT, state.realtime.flush())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Realtime subscriptions of {} are not active yet", user_id);
+    }
+}
+
+async fn heartbeat(state: &AppState, user_id: &str, socket_id: &str, subscription: &Subscription) {
+    match state.presence.heartbeat(user_id, socket_id).await {
+        Ok(true) => announce_presence(state, user_id, subscription).await,
+        Ok(false) => {}
+        Err(e) => tracing::warn!("Failed to refresh presence for {}: {}", user_id, e),
+    }
+}
+
+async fn announce_presence(state: &AppState, user_id: &str, subscription: &Subscription) {
+    let presence = match state.presence.get(user_id).await {
+        Ok(presence) => presence,
+        Err(e) => {
+            tracing::warn!("Failed to read presence for {}: {}", user_id, e);
+            return;
+        }
+    };
+
+    for conversation_id in subscription.conversations() {
+        state
+            .realtime
+            .publish(RealtimeEvent::PresenceUpdated {
+                conversation_id: conversation_id.clone(),
+                presence: presence.clone(),
+            })
+            .await;
+    }
+}
+
+async fn handle_frame(
+    state: &AppState,
+    user_id: &str,
+    subscription: &mut Subscription,
+    text: &str,
+) -> Option<serde_json::Value> {
+    let frame = match serde_json::from_str::<ClientFrame>(text) {
+        Ok(frame) => frame,
+        Err(_) => return Some(json!({ "type": "error", "message": "Invalid frame" })),
+    };
+
+    match frame {
+        ClientFrame::Ping => Some(json!({ "type": "pong" })),
+        ClientFrame::Subscribe { conversation_id } => {
+            let Ok(conv_id) = ObjectId::parse_str(&conversation_id) else {
+                return Some(json!({ "type": "error", "message": "Invalid conversation id" }));
+            };
+
+            let is_participant = state
+                .conversation_service
+                .find_by_id(conv_id)
+                .await
+                .ok()
+                .flatten()
+                .is_some_and(|conversation| conversation.participants.iter().any(|p| p == user_id));
+            if !is_participant {
+                return Some(json!({ "type": "error", "message": "Access denied" }));
+            }
+
+            subscription.join(conversation_id.clone());
+            wait_subscribed(state, user_id).await;
+            Some(json!({ "type": "subscribed", "conversation_id": conversation_id }))
+        }
+        ClientFrame::Typing { conversation_id } => {
+            if !subscription.is_joined(&conversation_id) {
+                return Some(json!({ "type": "error", "message": "Access denied" }));
+            }
+
+            match state.presence.start_typing(&conversation_id, user_id).await {
+                Ok(Some(expires_at)) => {
+                    state
+                        .realtime
+                        .publish(RealtimeEvent::TypingStarted {
+                            conversation_id,
+                            user_id: user_id.to_string(),
+                            expires_at,
+                        })
+                        .await
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to record typing for {}: {}", user_id, e),
+            }
+            None
+        }
+        ClientFrame::StopTyping { conversation_id } => {
+            if !subscription.is_joined(&conversation_id) {
+                return Some(json!({ "type": "error", "message": "Access denied" }));
+            }
+
+            match state.presence.stop_typing(&conversation_id, user_id).await {
+                Ok(true) => {
+                    state
+                        .realtime
+                        .publish(RealtimeEvent::TypingStopped {
+                            conversation_id,
+                            user_id: user_id.to_string(),
+                        })
+                        .await
+                }
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to clear typing for {}: {}", user_id, e),
+            }
+            None
+        }
+    }
+}
+
+async fn send_event<S>(sender: &mut S, event: &RealtimeEvent) -> Result<(), S::Error>
+where
+    S: SinkExt<WsMessage> + Unpin,
+{
+    let text = serde_json::to_string(event).expect("events always serialize");
+    sender.send(WsMessage::Text(text.into())).await
+}
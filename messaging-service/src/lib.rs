@@ -0,0 +1,5 @@
+pub mod config;
+pub mod handlers;
+pub mod models;
+pub mod openapi;
+pub mod services;
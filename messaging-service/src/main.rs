@@ -4,25 +4,27 @@ use axum::{
 };
 use deadpool_redis::{Config as RedisConfig, Runtime};
 use mongodb::Client as MongoClient;
-use std::sync::Arc;
-use tokio;
-use tracing_subscriber;
+use shared::{IdempotencyLayer, RequestIdLayer};
+use std::{sync::Arc, time::Duration};
 
-mod config;
-mod handlers;
-mod models;
-mod services;
-
-use config::Config;
-use handlers::conversation::{
+use messaging_service::config::Config;
+use messaging_service::handlers::conversation::{
     add_participant, create_conversation, delete_conversation, get_conversation,
-    get_conversations_by_user, remove_participant, ConvAppState,
+    get_conversations_by_user, get_inbox, remove_participant, ConvAppState,
 };
-use handlers::messaging::{
-    delete_message, get_message, get_messages, mark_as_read, send_message, AppState as MsgAppState,
+use messaging_service::handlers::messaging::{
+    add_reaction, cache_stats, delete_message, edit_message, get_message, get_messages,
+    get_revisions, get_thread, mark_as_read, mark_conversation_delivered,
+    mark_conversation_read, remove_reaction, send_message, AppState as MsgAppState,
 };
-use services::conversation::ConversationService;
-use services::messaging::MessageService;
+use messaging_service::handlers::presence::get_presence;
+use messaging_service::handlers::realtime::realtime;
+use messaging_service::openapi;
+use messaging_service::services::cache::MessageCache;
+use messaging_service::services::conversation::ConversationService;
+use messaging_service::services::messaging::MessageService;
+use messaging_service::services::presence::PresenceService;
+use messaging_service::services::realtime::RealtimeHub;
 
 #[tokio::main]
 async fn main() {
@@ -37,23 +39,78 @@ async fn main() {
         .create_pool(Some(Runtime::Tokio1))
         .expect("Failed to create Redis pool");
 
+    let redis_pool = Arc::new(redis_pool);
+    let idempotency = IdempotencyLayer::new(redis_pool.clone())
+        .ttl(Duration::from_secs(config.idempotency_ttl_secs));
+
     let message_service = Arc::new(MessageService::new(&mongo_client));
+    if let Err(e) = message_service.ensure_indexes().await {
+        tracing::warn!("failed to create message indexes: {}", e);
+    }
     let conversation_service = Arc::new(ConversationService::new(&mongo_client));
+    if let Err(e) = conversation_service.ensure_indexes().await {
+        tracing::warn!("failed to create conversation indexes: {}", e);
+    }
+
+    let redis_subscriber =
+        redis::Client::open(config.redis_uri.as_str()).expect("Invalid Redis URL");
+    let realtime_hub = Arc::new(RealtimeHub::new(
+        redis_pool.clone(),
+        redis_subscriber,
+        Duration::from_secs(config.ws_ping_interval_secs),
+        Duration::from_secs(config.ws_idle_timeout_secs),
+        Duration::from_millis(config.realtime_publish_timeout_ms),
+    ));
+    realtime_hub.spawn_listener();
 
     let msg_state = MsgAppState {
         message_service: message_service.clone(),
         conversation_service: conversation_service.clone(),
-        redis_pool: Arc::new(redis_pool),
+        cache: Arc::new(MessageCache::new(
+            redis_pool.clone(),
+            Duration::from_secs(config.message_cache_ttl_secs),
+            Duration::from_millis(config.message_cache_timeout_ms),
+        )),
+        edit_window: Duration::from_secs(config.message_edit_window_secs),
+        realtime: realtime_hub.clone(),
+        presence: Arc::new(PresenceService::new(
+            redis_pool.clone(),
+            Duration::from_secs(config.presence_ttl_secs),
+            Duration::from_secs(config.typing_ttl_secs),
+        )),
+        internal_token: config.internal_token.clone(),
     };
     let conv_state = ConvAppState {
         conversation_service: conversation_service.clone(),
+        realtime: realtime_hub,
     };
 
     let msg_router = Router::new()
-        .route("/messages", post(send_message))
-        .route("/messages/{message_id}", get(get_message).delete(delete_message))
+        .route("/messages", post(send_message).layer(idempotency))
+        .route("/messages/ws", get(realtime))
+        .route(
+            "/messages/{message_id}",
+            get(get_message).patch(edit_message).delete(delete_message),
+        )
+        .route("/messages/{message_id}/revisions", get(get_revisions))
+        .route("/messages/{message_id}/thread", get(get_thread))
+        .route("/messages/{message_id}/reactions", post(add_reaction))
+        .route(
+            "/messages/{message_id}/reactions/{emoji}",
+            delete(remove_reaction),
+        )
         .route("/messages/{message_id}/read", patch(mark_as_read))
         .route("/conversations/{conversation_id}/messages", get(get_messages))
+        .route(
+            "/conversations/{conversation_id}/read",
+            post(mark_conversation_read),
+        )
+        .route(
+            "/conversations/{conversation_id}/delivered",
+            post(mark_conversation_delivered),
+        )
+        .route("/users/{user_id}/presence", get(get_presence))
+        .route("/internal/cache/stats", get(cache_stats))
         .with_state(msg_state);
 
     let conv_router = Router::new()
@@ -68,9 +125,15 @@ async fn main() {
             delete(remove_participant),
         )
         .route("/users/{user_id}/conversations", get(get_conversations_by_user))
+        .route("/users/{user_id}/inbox", get(get_inbox))
         .with_state(conv_state);
 
-    let app = Router::new().merge(msg_router).merge(conv_router);
+    let app = Router::new()
+        .route("/health", get(shared::health_check))
+        .route("/openapi.json", get(openapi::openapi_json))
+        .merge(msg_router)
+        .merge(conv_router)
+        .layer(RequestIdLayer::new());
 
     let addr = format!("0.0.0.0:{}", config.port);
     let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
@@ -1,17 +1,145 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use shared::openapi::ObjectIdJson;
+use utoipa::ToSchema;
+
+use crate::models::{datetime, message::Message};
+
+const PREVIEW_CHARS: usize = 100;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Conversation {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
     pub id: Option<ObjectId>,
     pub participants: Vec<String>,
+    #[serde(with = "datetime")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "datetime")]
     pub updated_at: DateTime<Utc>,
+    /// Last message each participant has read, keyed by user id.
+    #[serde(default)]
+    pub read_markers: HashMap<String, ReceiptMarker>,
+    /// Last message delivered to each participant's device, keyed by user id.
+    #[serde(default)]
+    pub delivered_markers: HashMap<String, ReceiptMarker>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_message: Option<MessagePreview>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "datetime::option"
+    )]
+    pub last_activity_at: Option<DateTime<Utc>>,
+    /// Maintained on send, read and delete so the inbox never scans `messages`.
+    #[serde(default)]
+    pub unread_counts: HashMap<String, i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MessagePreview {
+    #[schema(value_type = ObjectIdJson)]
+    pub message_id: ObjectId,
+    pub sender_id: String,
+    pub preview: String,
+    #[serde(with = "datetime")]
+    pub sent_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InboxEntry {
+    #[schema(value_type = ObjectIdJson)]
+    pub conversation_id: ObjectId,
+    pub participants: Vec<String>,
+    pub last_message: Option<MessagePreview>,
+    pub last_activity_at: DateTime<Utc>,
+    pub unread_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReceiptMarker {
+    #[schema(value_type = ObjectIdJson)]
+    pub message_id: ObjectId,
+    #[serde(with = "datetime")]
+    pub sent_at: DateTime<Utc>,
+    pub marked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MarkReceiptRequest {
+    pub message_id: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateConversationRequest {
     pub participants: Vec<String>,
 }
+
+/// Whether `user_id` can key the per-user maps: MongoDB reads a `.` in a field path
+/// as nesting and a leading `$` as an operator.
+pub fn is_valid_user_id(user_id: &str) -> bool {
+    !user_id.is_empty() && !user_id.starts_with('$') && !user_id.contains(['.', '\0'])
+}
+
+impl ReceiptMarker {
+    pub fn new(message: &Message) -> Option<Self> {
+        Some(Self {
+            message_id: message.id?,
+            sent_at: message.sent_at,
+            marked_at: Utc::now(),
+        })
+    }
+
+    /// Whether everything up to and including `message` is covered by this marker.
+    pub fn covers(&self, sent_at: DateTime<Utc>, message_id: ObjectId) -> bool {
+        (self.sent_at, self.message_id) >= (sent_at, message_id)
+    }
+}
+
+impl MessagePreview {
+    pub fn new(message: &Message) -> Option<Self> {
+        Some(Self {
+            message_id: message.id?,
+            sender_id: message.sender_id.clone(),
+            preview: message.content.chars().take(PREVIEW_CHARS).collect(),
+            sent_at: message.sent_at,
+        })
+    }
+}
+
+impl Conversation {
+    pub fn inbox_entry(&self, user_id: &str) -> Option<InboxEntry> {
+        Some(InboxEntry {
+            conversation_id: self.id?,
+            participants: self.participants.clone(),
+            last_message: self.last_message.clone(),
+            last_activity_at: self.last_activity_at.unwrap_or(self.updated_at),
+            unread_count: self.unread_counts.get(user_id).copied().unwrap_or(0).max(0),
+        })
+    }
+
+    /// Fills in `read_by` / `delivered_to` from the participants' markers.
+    pub fn apply_receipts(&self, message: &mut Message) {
+        let Some(id) = message.id else {
+            return;
+        };
+        let covered = |markers: &HashMap<String, ReceiptMarker>| -> Vec<String> {
+            let mut users: Vec<String> = markers
+                .iter()
+                .filter(|(user_id, marker)| {
+                    **user_id != message.sender_id && marker.covers(message.sent_at, id)
+                })
+                .map(|(user_id, _)| user_id.clone())
+                .collect();
+            users.sort();
+            users
+        };
+
+        message.read_by = covered(&self.read_markers);
+        message.delivered_to = covered(&self.delivered_markers);
+        message.read = !message.read_by.is_empty();
+    }
+}
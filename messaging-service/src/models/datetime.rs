@@ -0,0 +1,68 @@
+//! Serde helpers for dates MongoDB has to sort or compare.
+//!
+//! `chrono` writes RFC 3339 strings, which don't sort chronologically once their
+//! fractional digits vary. Fields using `#[serde(with = "datetime")]` are stored as
+//! BSON dates instead and stay strings in JSON. Documents written before were
+//! stored as strings, so both forms are read back.
+
+use bson::Bson;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+/// BSON date for `time`, for filters and `$set`s written by hand.
+pub fn to_bson(time: &DateTime<Utc>) -> Bson {
+    Bson::DateTime(bson::DateTime::from_millis(time.timestamp_millis()))
+}
+
+/// Like `bson::to_bson`, but with dates in their stored form; `bson::to_bson` is
+/// human-readable and would write them as strings.
+pub fn to_stored<T: Serialize>(value: &T) -> anyhow::Result<Bson> {
+    let raw = bson::to_raw_document_buf(value)?;
+    Ok(Bson::Document(raw.to_document()?))
+}
+
+pub fn serialize<S: Serializer>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        time.serialize(serializer)
+    } else {
+        bson::DateTime::from_millis(time.timestamp_millis()).serialize(serializer)
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    from_bson(Bson::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+fn from_bson(time: Bson) -> Result<DateTime<Utc>, String> {
+    match time {
+        Bson::DateTime(time) => Utc
+            .timestamp_millis_opt(time.timestamp_millis())
+            .single()
+            .ok_or_else(|| "date out of range".to_string()),
+        Bson::String(time) => time.parse().map_err(|e| format!("invalid date: {}", e)),
+        other => Err(format!("expected a date, found {}", other)),
+    }
+}
+
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => super::serialize(time, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Bson::deserialize(deserializer)? {
+            Bson::Null => Ok(None),
+            time => from_bson(time).map(Some).map_err(D::Error::custom),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::{message::Message, presence::Presence};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum RealtimeEvent {
+    #[serde(rename = "message.created")]
+    MessageCreated { message: Message },
+    #[serde(rename = "message.edited")]
+    MessageEdited { message: Message },
+    #[serde(rename = "message.deleted")]
+    MessageDeleted {
+        conversation_id: String,
+        message_id: String,
+    },
+    #[serde(rename = "message.read")]
+    MessageRead {
+        conversation_id: String,
+        message_id: String,
+        reader_id: String,
+        read_at: DateTime<Utc>,
+    },
+    #[serde(rename = "message.delivered")]
+    MessageDelivered {
+        conversation_id: String,
+        message_id: String,
+        user_id: String,
+        delivered_at: DateTime<Utc>,
+    },
+    #[serde(rename = "reaction.added")]
+    ReactionAdded {
+        conversation_id: String,
+        message_id: String,
+        user_id: String,
+        emoji: String,
+    },
+    #[serde(rename = "reaction.removed")]
+    ReactionRemoved {
+        conversation_id: String,
+        message_id: String,
+        user_id: String,
+        emoji: String,
+    },
+    #[serde(rename = "typing.started")]
+    TypingStarted {
+        conversation_id: String,
+        user_id: String,
+        expires_at: DateTime<Utc>,
+    },
+    #[serde(rename = "typing.stopped")]
+    TypingStopped {
+        conversation_id: String,
+        user_id: String,
+    },
+    #[serde(rename = "presence.updated")]
+    PresenceUpdated {
+        conversation_id: String,
+        presence: Presence,
+    },
+    /// Sent to the conversation, then the removed user's sockets stop receiving it.
+    #[serde(rename = "member.removed")]
+    MemberRemoved {
+        conversation_id: String,
+        user_id: String,
+    },
+    /// Last event of a deleted conversation; every socket leaves it.
+    #[serde(rename = "conversation.deleted")]
+    ConversationDeleted { conversation_id: String },
+    /// Events may have been lost; reconnect with `since` or refetch the history.
+    #[serde(rename = "sync.required")]
+    SyncRequired { conversation_id: String },
+}
+
+impl RealtimeEvent {
+    /// Whether `user_id` loses access to the conversation with this event.
+    pub fn revokes(&self, user_id: &str) -> bool {
+        match self {
+            RealtimeEvent::MemberRemoved {
+                user_id: removed, ..
+            } => removed == user_id,
+            RealtimeEvent::ConversationDeleted { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn conversation_id(&self) -> String {
+        match self {
+            RealtimeEvent::MessageCreated { message }
+            | RealtimeEvent::MessageEdited { message } => message.conversation_id.to_hex(),
+            RealtimeEvent::MessageDeleted {
+                conversation_id, ..
+            }
+            | RealtimeEvent::MessageRead {
+                conversation_id, ..
+            }
+            | RealtimeEvent::MessageDelivered {
+                conversation_id, ..
+            }
+            | RealtimeEvent::ReactionAdded {
+                conversation_id, ..
+            }
+            | RealtimeEvent::ReactionRemoved {
+                conversation_id, ..
+            }
+            | RealtimeEvent::TypingStarted {
+                conversation_id, ..
+            }
+            | RealtimeEvent::TypingStopped {
+                conversation_id, ..
+            }
+            | RealtimeEvent::PresenceUpdated {
+                conversation_id, ..
+            }
+            | RealtimeEvent::MemberRemoved {
+                conversation_id, ..
+            }
+            | RealtimeEvent::ConversationDeleted { conversation_id }
+            | RealtimeEvent::SyncRequired { conversation_id } => conversation_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientFrame {
+    Ping,
+    Subscribe { conversation_id: String },
+    Typing { conversation_id: String },
+    StopTyping { conversation_id: String },
+}
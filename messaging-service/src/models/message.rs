@@ -1,13 +1,197 @@
 use chrono::{DateTime, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use shared::openapi::ObjectIdJson;
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::models::{conversation::MessagePreview, datetime};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Message {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
     pub id: Option<ObjectId>,
+    #[schema(value_type = ObjectIdJson)]
     pub conversation_id: ObjectId,
     pub sender_id: String,
     pub content: String,
+    #[serde(with = "datetime")]
     pub sent_at: DateTime<Utc>,
     pub read: bool,
+    /// Participants (other than the sender) who have read this message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read_by: Vec<String>,
+    /// Participants (other than the sender) whose devices received this message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delivered_to: Vec<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "datetime::option"
+    )]
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Who reacted with what, as stored; replaced by `reactions` before responding.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(ignore)]
+    pub user_reactions: Vec<Reaction>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reactions: Vec<ReactionSummary>,
+    /// Message being answered; it must belong to the same conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
+    pub reply_to: Option<ObjectId>,
+    /// Snapshot of `reply_to` taken when the reply was sent; its preview is
+    /// emptied when `reply_to` is deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quoted: Option<MessagePreview>,
+    /// Root message of the thread this reply belongs to, until the root is deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
+    pub thread_id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub reply_count: i64,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "datetime::option"
+    )]
+    pub last_reply_at: Option<DateTime<Utc>>,
+}
+
+fn is_zero(count: &i64) -> bool {
+    *count == 0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reaction {
+    pub emoji: String,
+    pub user_id: String,
+    #[serde(with = "datetime")]
+    pub reacted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReactionSummary {
+    pub emoji: String,
+    pub count: usize,
+    pub reacted_by_me: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddReactionRequest {
+    pub emoji: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditMessageRequest {
+    pub content: String,
+}
+
+/// Content a message had before an edit replaced it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MessageRevision {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
+    pub id: Option<ObjectId>,
+    #[schema(value_type = ObjectIdJson)]
+    pub message_id: ObjectId,
+    pub content: String,
+    /// When this content was written: the send time or the previous edit.
+    #[serde(with = "datetime")]
+    pub written_at: DateTime<Utc>,
+    #[serde(with = "datetime")]
+    pub replaced_at: DateTime<Utc>,
+}
+
+impl Message {
+    /// Makes this message a reply to `target`. Returns false if `target` is in
+    /// another conversation; a reply to a reply joins the thread of its root.
+    pub fn set_reply_target(&mut self, target: &Message) -> bool {
+        let Some(target_id) = target.id else {
+            return false;
+        };
+        if target.conversation_id != self.conversation_id {
+            return false;
+        }
+        self.reply_to = Some(target_id);
+        self.quoted = MessagePreview::new(target);
+        self.thread_id = Some(target.thread_id.unwrap_or(target_id));
+        true
+    }
+
+    /// Aggregates `user_reactions` into per-emoji counts, in order of first use,
+    /// flagged for `viewer`.
+    pub fn apply_reactions(&mut self, viewer: Option<&str>) {
+        let mut summaries: Vec<ReactionSummary> = Vec::new();
+        for reaction in self.user_reactions.drain(..) {
+            let mine = viewer == Some(reaction.user_id.as_str());
+            match summaries.iter_mut().find(|s| s.emoji == reaction.emoji) {
+                Some(summary) => {
+                    summary.count += 1;
+                    summary.reacted_by_me |= mine;
+                }
+                None => summaries.push(ReactionSummary {
+                    emoji: reaction.emoji,
+                    count: 1,
+                    reacted_by_me: mine,
+                }),
+            }
+        }
+        self.reactions = summaries;
+    }
+}
+
+/// Distinct emojis one user may put on a single message.
+pub const MAX_REACTIONS_PER_USER: usize = 20;
+
+/// Whether `emoji` looks like a single emoji rather than text: non-ASCII apart
+/// from the digits, `#` and `*` that start keycap sequences.
+pub fn is_valid_emoji(emoji: &str) -> bool {
+    !emoji.is_empty()
+        && emoji.len() <= 64
+        && !emoji.is_ascii()
+        && emoji
+            .chars()
+            .all(|c| !c.is_whitespace() && (!c.is_ascii() || matches!(c, '0'..='9' | '#' | '*')))
+}
+
+/// Position of a message in its conversation's history, ordered by `(sent_at, _id)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MessageCursor {
+    pub sent_at: DateTime<Utc>,
+    pub id: ObjectId,
+}
+
+/// Edge of a history page: an exact message position or a point in time.
+#[derive(Debug, Clone, Copy)]
+pub enum HistoryBound {
+    Cursor(MessageCursor),
+    Time(DateTime<Utc>),
+}
+
+impl MessageCursor {
+    pub fn new(message: &Message) -> Option<Self> {
+        Some(Self {
+            sent_at: message.sent_at,
+            id: message.id?,
+        })
+    }
+
+    /// Opaque to clients: the nanosecond timestamp followed by the id, hex-encoded.
+    pub fn encode(&self) -> String {
+        let nanos = self.sent_at.timestamp_nanos_opt().unwrap_or_default();
+        format!("{:016x}{}", nanos as u64, self.id.to_hex())
+    }
+
+    pub fn decode(raw: &str) -> Option<Self> {
+        if raw.len() != 40 {
+            return None;
+        }
+        let nanos = u64::from_str_radix(raw.get(..16)?, 16).ok()? as i64;
+        let id = ObjectId::parse_str(raw.get(16..)?).ok()?;
+        Some(Self {
+            sent_at: DateTime::from_timestamp_nanos(nanos),
+            id,
+        })
+    }
 }
@@ -1,2 +1,5 @@
 pub mod conversation;
+pub mod datetime;
+pub mod event;
 pub mod message;
+pub mod presence;
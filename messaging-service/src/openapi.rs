@@ -0,0 +1,54 @@
+use axum::Json;
+use shared::{openapi::BearerSecurity, ErrorBody};
+use utoipa::OpenApi;
+
+use crate::handlers::{conversation, messaging, presence, realtime};
+use crate::models::{
+    conversation::{
+        Conversation, CreateConversationRequest, InboxEntry, MarkReceiptRequest, MessagePreview,
+        ReceiptMarker,
+    },
+    event::RealtimeEvent,
+    message::{AddReactionRequest, EditMessageRequest, Message, MessageRevision, ReactionSummary},
+    presence::{Presence, PresenceStatus},
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Staki Messaging Service"),
+    paths(
+        messaging::send_message,
+        messaging::get_message,
+        messaging::edit_message,
+        messaging::get_revisions,
+        messaging::get_thread,
+        messaging::add_reaction,
+        messaging::remove_reaction,
+        messaging::delete_message,
+        messaging::mark_as_read,
+        messaging::mark_conversation_read,
+        messaging::mark_conversation_delivered,
+        messaging::get_messages,
+        realtime::realtime,
+        presence::get_presence,
+        conversation::create_conversation,
+        conversation::get_conversation,
+        conversation::delete_conversation,
+        conversation::add_participant,
+        conversation::remove_participant,
+        conversation::get_conversations_by_user,
+        conversation::get_inbox,
+    ),
+    components(schemas(Message, EditMessageRequest, MessageRevision, AddReactionRequest, ReactionSummary, ReceiptMarker, MarkReceiptRequest, RealtimeEvent, Presence, PresenceStatus, Conversation, CreateConversationRequest, InboxEntry, MessagePreview, ErrorBody)),
+    modifiers(&BearerSecurity),
+    tags(
+        (name = "messages", description = "Messages within conversations"),
+        (name = "conversations", description = "Conversations and participants"),
+        (name = "realtime", description = "WebSocket delivery of message events"),
+    )
+)]
+pub struct ApiDoc;
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,175 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use deadpool_redis::{redis, Pool};
+use serde::Serialize;
+
+use crate::models::message::Message;
+
+const VERSION_TTL_SECS: u64 = 7 * 24 * 3600;
+
+// Pages live under `messages:{conv}:v{version}:{page}`. Writes bump the version
+// instead of deleting keys, so every cached page of the conversation goes stale at
+// once and old entries simply expire.
+pub struct MessageCache {
+    redis: Arc<Pool>,
+    ttl: Duration,
+    timeout: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    errors: AtomicU64,
+}
+
+pub enum CacheLookup {
+    Hit(Vec<Message>),
+    /// Not cached; store the page under this version.
+    Miss(u64),
+    /// Redis is unreachable; read from MongoDB and skip caching.
+    Unavailable,
+}
+
+#[derive(Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub errors: u64,
+    pub hit_rate: f64,
+}
+
+impl MessageCache {
+    pub fn new(redis: Arc<Pool>, ttl: Duration, timeout: Duration) -> Self {
+        Self {
+            redis,
+            ttl,
+            timeout,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn get(&self, conversation_id: &str, page: &str) -> CacheLookup {
+        let lookup = tokio::time::timeout(self.timeout, async {
+            let mut conn = self.redis.get().await?;
+            let version: Option<u64> = redis::cmd("GET")
+                .arg(version_key(conversation_id))
+                .query_async(&mut conn)
+                .await?;
+            let version = version.unwrap_or(0);
+            let cached: Option<String> = redis::cmd("GET")
+                .arg(page_key(conversation_id, version, page))
+                .query_async(&mut conn)
+                .await?;
+            anyhow::Ok((version, cached))
+        })
+        .await;
+
+        match lookup {
+            Ok(Ok((version, cached))) => {
+                match cached.and_then(|raw| serde_json::from_str(&raw).ok()) {
+                    Some(messages) => {
+                        self.hits.fetch_add(1, Ordering::Relaxed);
+                        CacheLookup::Hit(messages)
+                    }
+                    None => {
+                        self.misses.fetch_add(1, Ordering::Relaxed);
+                        CacheLookup::Miss(version)
+                    }
+                }
+            }
+            Ok(Err(e)) => {
+                self.unavailable("read", &e);
+                CacheLookup::Unavailable
+            }
+            Err(_) => {
+                self.unavailable("read", "timed out");
+                CacheLookup::Unavailable
+            }
+        }
+    }
+
+    pub async fn put(&self, conversation_id: &str, version: u64, page: &str, messages: &[Message]) {
+        let Ok(serialized) = serde_json::to_string(messages) else {
+            return;
+        };
+        let result = tokio::time::timeout(self.timeout, async {
+            let mut conn = self.redis.get().await?;
+            redis::cmd("SET")
+                .arg(page_key(conversation_id, version, page))
+                .arg(serialized)
+                .arg("EX")
+                .arg(self.ttl.as_secs())
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            anyhow::Ok(())
+        })
+        .await;
+
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => self.unavailable("write", &e),
+            Err(_) => self.unavailable("write", "timed out"),
+        }
+    }
+
+    /// Makes every cached page of the conversation stale.
+    pub async fn invalidate(&self, conversation_id: &str) {
+        let result = tokio::time::timeout(self.timeout, async {
+            let mut conn = self.redis.get().await?;
+            let key = version_key(conversation_id);
+            redis::pipe()
+                .cmd("INCR")
+                .arg(&key)
+                .ignore()
+                .cmd("EXPIRE")
+                .arg(&key)
+                .arg(VERSION_TTL_SECS)
+                .ignore()
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            anyhow::Ok(())
+        })
+        .await;
+
+        // Pages cached before the outage may be served until they expire.
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => self.unavailable("invalidate", &e),
+            Err(_) => self.unavailable("invalidate", "timed out"),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        CacheStats {
+            hits,
+            misses,
+            errors: self.errors.load(Ordering::Relaxed),
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+        }
+    }
+
+    fn unavailable(&self, op: &str, error: impl std::fmt::Display) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("message cache {} failed: {}", op, error);
+    }
+}
+
+fn version_key(conversation_id: &str) -> String {
+    format!("messages:{}:version", conversation_id)
+}
+
+fn page_key(conversation_id: &str, version: u64, page: &str) -> String {
+    format!("messages:{}:v{}:{}", conversation_id, version, page)
+}
@@ -1,10 +1,11 @@
-use crate::models::conversation::Conversation;
+use crate::models::conversation::{is_valid_user_id, Conversation, MessagePreview, ReceiptMarker};
+use crate::models::datetime;
 use anyhow::{Ok, Result};
 use bson::oid::ObjectId;
-use bson::Bson;
+use bson::{Bson, Document};
 use chrono::Utc;
 use futures::stream::TryStreamExt;
-use mongodb::{bson::doc, Client, Collection};
+use mongodb::{bson::doc, Client, Collection, IndexModel};
 
 pub struct ConversationService {
     pub collection: Collection<Conversation>,
@@ -17,6 +18,69 @@ impl ConversationService {
         Self { collection }
     }
 
+    pub async fn ensure_indexes(&self) -> Result<()> {
+        let inbox = IndexModel::builder()
+            .keys(doc! { "participants": 1, "last_activity_at": -1 })
+            .build();
+        self.collection.create_index(inbox).await?;
+
+        // Conversations created before `last_activity_at` existed would sort last in the
+        // inbox, and ones that stored it as a string wouldn't sort by time.
+        let legacy = doc! { "last_activity_at": { "$not": { "$type": "date" } } };
+        let backfill = vec![doc! {
+            "$set": {
+                "last_activity_at": {
+                    "$toDate": {
+                        "$ifNull": ["$last_activity_at", "$last_message.sent_at", "$updated_at"]
+                    }
+                }
+            }
+        }];
+        self.collection.update_many(legacy, backfill).await?;
+
+        // So were the `sent_at` of previews and markers, which are compared on update.
+        let legacy = doc! { "last_message.sent_at": { "$type": "string" } };
+        let backfill = vec![doc! {
+            "$set": { "last_message.sent_at": { "$toDate": "$last_message.sent_at" } }
+        }];
+        self.collection.update_many(legacy, backfill).await?;
+        for field in ["read_markers", "delivered_markers"] {
+            let markers = doc! { "$objectToArray": { "$ifNull": [format!("${}", field), {}] } };
+            let legacy = doc! {
+                "$expr": {
+                    "$anyElementTrue": [{
+                        "$map": {
+                            "input": markers.clone(),
+                            "in": { "$eq": [{ "$type": "$$this.v.sent_at" }, "string"] },
+                        }
+                    }]
+                }
+            };
+            let backfill = vec![doc! {
+                "$set": {
+                    field: {
+                        "$arrayToObject": {
+                            "$map": {
+                                "input": markers,
+                                "in": {
+                                    "k": "$$this.k",
+                                    "v": {
+                                        "$mergeObjects": [
+                                            "$$this.v",
+                                            { "sent_at": { "$toDate": "$$this.v.sent_at" } },
+                                        ]
+                                    },
+                                },
+                            }
+                        }
+                    }
+                }
+            }];
+            self.collection.update_many(legacy, backfill).await?;
+        }
+        Ok(())
+    }
+
     pub async fn find_by_id(&self, id: ObjectId) -> Result<Option<Conversation>> {
         let filter = doc! { "_id": id };
         let conv = self.collection.find_one(filter).await?;
@@ -56,6 +120,145 @@ impl ConversationService {
         Ok(conversations)
     }
 
+    pub async fn inbox(&self, user_id: &str, skip: i64, limit: i64) -> Result<Vec<Conversation>> {
+        let safe_limit = limit.clamp(1, 100);
+        let filter = doc! { "participants": user_id };
+        let mut cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "last_activity_at": -1, "_id": -1 })
+            .skip(skip.max(0) as u64)
+            .limit(safe_limit)
+            .await?;
+        let mut conversations = Vec::new();
+        while let Some(conv) = cursor.try_next().await? {
+            conversations.push(conv);
+        }
+        Ok(conversations)
+    }
+
+    /// Messages can be recorded out of order; an older one only bumps the unread counts.
+    pub async fn record_message(
+        &self,
+        id: ObjectId,
+        preview: &MessagePreview,
+        recipients: &[String],
+    ) -> Result<()> {
+        let unread = recipients
+            .iter()
+            .map(|user_id| Ok((user_path("unread_counts", user_id)?, Bson::Int64(1))))
+            .collect::<Result<Document>>()?;
+
+        let sent_at = datetime::to_bson(&preview.sent_at);
+        let filter = doc! {
+            "_id": id,
+            "$or": [
+                { "last_message": { "$exists": false } },
+                { "last_message.sent_at": { "$lt": sent_at.clone() } },
+                {
+                    "last_message.sent_at": sent_at.clone(),
+                    "last_message.message_id": { "$lt": preview.message_id },
+                },
+            ]
+        };
+        let mut update = doc! {
+            "$set": {
+                "last_message": datetime::to_stored(preview)?,
+                "last_activity_at": sent_at,
+            }
+        };
+        if !unread.is_empty() {
+            update.insert("$inc", unread.clone());
+        }
+        let result = self.collection.update_one(filter, update).await?;
+
+        if result.matched_count == 0 && !unread.is_empty() {
+            self.collection
+                .update_one(doc! { "_id": id }, doc! { "$inc": unread })
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_last_message(
+        &self,
+        id: ObjectId,
+        preview: Option<&MessagePreview>,
+    ) -> Result<()> {
+        let filter = doc! { "_id": id };
+        let update = match preview {
+            Some(preview) => doc! { "$set": { "last_message": datetime::to_stored(preview)? } },
+            None => doc! { "$unset": { "last_message": "" } },
+        };
+        self.collection.update_one(filter, update).await?;
+        Ok(())
+    }
+
+    /// Applies only if no message was recorded since `last_message_id` was read, so a
+    /// concurrent `record_message` increment isn't overwritten.
+    pub async fn set_unread(
+        &self,
+        id: ObjectId,
+        user_id: &str,
+        count: i64,
+        last_message_id: Option<ObjectId>,
+    ) -> Result<bool> {
+        let filter = match last_message_id {
+            Some(message_id) => doc! { "_id": id, "last_message.message_id": message_id },
+            None => doc! { "_id": id, "last_message": { "$exists": false } },
+        };
+        let update = doc! { "$set": { user_path("unread_counts", user_id)?: count } };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    pub async fn decrement_unread(&self, id: ObjectId, user_ids: &[String]) -> Result<()> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        let filter = doc! { "_id": id };
+        let unread = user_ids
+            .iter()
+            .map(|user_id| Ok((user_path("unread_counts", user_id)?, Bson::Int64(-1))))
+            .collect::<Result<Document>>()?;
+        self.collection
+            .update_one(filter, doc! { "$inc": unread })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn share_conversation(&self, user_id: &str, other_id: &str) -> Result<bool> {
+        let filter = doc! { "participants": { "$all": [user_id, other_id] } };
+        let conv = self.collection.find_one(filter).await?;
+        Ok(conv.is_some())
+    }
+
+    /// Moves the marker forward only; returns false if it already covered `marker`.
+    pub async fn set_marker(
+        &self,
+        id: ObjectId,
+        field: &str,
+        user_id: &str,
+        marker: &ReceiptMarker,
+    ) -> Result<bool> {
+        let path = user_path(field, user_id)?;
+        let sent_at = datetime::to_bson(&marker.sent_at);
+        let filter = doc! {
+            "_id": id,
+            "$or": [
+                { &path: { "$exists": false } },
+                { format!("{}.sent_at", path): { "$lt": sent_at.clone() } },
+                {
+                    format!("{}.sent_at", path): sent_at,
+                    format!("{}.message_id", path): { "$lt": marker.message_id },
+                },
+            ]
+        };
+        let update = doc! { "$set": { &path: datetime::to_stored(marker)? } };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+
     pub async fn delete(&self, id: ObjectId) -> Result<()> {
         let filter = doc! { "_id": id };
         self.collection.delete_one(filter).await?;
@@ -67,7 +270,7 @@ impl ConversationService {
         let now = Utc::now();
         let update = doc! {
             "$addToSet": { "participants": user_id },
-            "$set": { "updated_at": Bson::DateTime(bson::DateTime::from_millis(now.timestamp_millis())) }
+            "$set": { "updated_at": datetime::to_bson(&now) }
         };
         self.collection.update_one(filter, update).await?;
         Ok(())
@@ -77,10 +280,23 @@ impl ConversationService {
         let filter = doc! { "_id": id };
         let now = Utc::now();
         let update = doc! {
-            "$pull": { "participants": user_id },
-            "$set": { "updated_at": Bson::DateTime(bson::DateTime::from_millis(now.timestamp_millis())) }
+            "$pull": { "participants": &user_id },
+            "$unset": {
+                user_path("unread_counts", &user_id)?: "",
+                user_path("read_markers", &user_id)?: "",
+                user_path("delivered_markers", &user_id)?: "",
+            },
+            "$set": { "updated_at": datetime::to_bson(&now) }
         };
         self.collection.update_one(filter, update).await?;
         Ok(())
     }
 }
+
+/// Path of `user_id`'s entry in one of the per-user maps.
+fn user_path(field: &str, user_id: &str) -> Result<String> {
+    if !is_valid_user_id(user_id) {
+        anyhow::bail!("invalid user id {:?}", user_id);
+    }
+    Ok(format!("{}.{}", field, user_id))
+}
@@ -1,18 +1,82 @@
-use crate::models::message::Message;
+use crate::models::{
+    conversation::ReceiptMarker,
+    datetime,
+    message::{HistoryBound, Message, MessageRevision, Reaction, MAX_REACTIONS_PER_USER},
+};
 use anyhow::Result;
-use bson::oid::ObjectId;
+use bson::{oid::ObjectId, Bson, Document};
+use chrono::{DateTime, SubsecRound, Utc};
 use futures::stream::TryStreamExt;
-use mongodb::{bson::doc, Client, Collection};
+use mongodb::{bson::doc, options::IndexOptions, Client, Collection, IndexModel};
+
+pub enum ReactionOutcome {
+    Added,
+    /// The user had already reacted with this emoji.
+    AlreadyPresent,
+    /// The user already has `MAX_REACTIONS_PER_USER` reactions on the message.
+    LimitReached,
+}
 
 pub struct MessageService {
     pub collection: Collection<Message>,
+    pub revisions: Collection<MessageRevision>,
 }
 
 impl MessageService {
     pub fn new(client: &Client) -> Self {
         let db = client.database("messaging");
         let collection = db.collection("messages");
-        Self { collection }
+        let revisions = db.collection("message_revisions");
+        Self {
+            collection,
+            revisions,
+        }
+    }
+
+    pub async fn ensure_indexes(&self) -> Result<()> {
+        let history = IndexModel::builder()
+            .keys(doc! { "conversation_id": 1, "sent_at": -1, "_id": -1 })
+            .build();
+        self.collection.create_index(history).await?;
+        let revisions = IndexModel::builder()
+            .keys(doc! { "message_id": 1, "replaced_at": 1 })
+            .build();
+        self.revisions.create_index(revisions).await?;
+        let threads = IndexModel::builder()
+            .keys(doc! { "thread_id": 1, "sent_at": -1, "_id": -1 })
+            .options(
+                IndexOptions::builder()
+                    .partial_filter_expression(doc! { "thread_id": { "$exists": true } })
+                    .build(),
+            )
+            .build();
+        self.collection.create_index(threads).await?;
+
+        // These dates used to be stored as RFC 3339 strings, which don't sort by time
+        // and never equal the dates now written.
+        backfill_dates(&self.collection, "sent_at").await?;
+        backfill_dates(&self.collection, "edited_at").await?;
+        backfill_dates(&self.collection, "last_reply_at").await?;
+        backfill_dates(&self.revisions, "written_at").await?;
+        backfill_dates(&self.revisions, "replaced_at").await?;
+        let legacy = doc! { "user_reactions.reacted_at": { "$type": "string" } };
+        let backfill = vec![doc! {
+            "$set": {
+                "user_reactions": {
+                    "$map": {
+                        "input": "$user_reactions",
+                        "in": {
+                            "$mergeObjects": [
+                                "$$this",
+                                { "reacted_at": { "$toDate": "$$this.reacted_at" } },
+                            ]
+                        },
+                    }
+                }
+            }
+        }];
+        self.collection.update_many(legacy, backfill).await?;
+        Ok(())
     }
 
     pub async fn send_message(&self, msg: Message) -> Result<String> {
@@ -31,31 +95,215 @@ impl MessageService {
         Ok(msg)
     }
 
-    pub async fn update_read_status(&self, id: ObjectId, read: bool) -> Result<()> {
-        let filter = doc! { "_id": id };
-        let update = doc! { "$set": { "read": read } };
-        self.collection.update_one(filter, update).await?;
-        Ok(())
+    pub async fn latest(&self, conv_id: ObjectId) -> Result<Option<Message>> {
+        let filter = doc! { "conversation_id": conv_id };
+        let msg = self
+            .collection
+            .find_one(filter)
+            .sort(doc! { "sent_at": -1, "_id": -1 })
+            .await?;
+        Ok(msg)
     }
 
+    pub async fn count_unread(
+        &self,
+        conv_id: ObjectId,
+        user_id: &str,
+        marker: Option<&ReceiptMarker>,
+    ) -> Result<i64> {
+        let mut filter = doc! {
+            "conversation_id": conv_id,
+            "sender_id": { "$ne": user_id },
+        };
+        if let Some(marker) = marker {
+            let sent_at = datetime::to_bson(&marker.sent_at);
+            filter.insert(
+                "$or",
+                vec![
+                    doc! { "sent_at": { "$gt": sent_at.clone() } },
+                    doc! { "sent_at": sent_at, "_id": { "$gt": marker.message_id } },
+                ],
+            );
+        }
+        let count = self.collection.count_documents(filter).await?;
+        Ok(count as i64)
+    }
+
+    /// Deletes the message and its revisions. Replies keep their quote without the
+    /// deleted text; if the message was a thread root, its replies leave the thread.
     pub async fn delete_message(&self, id: ObjectId) -> Result<()> {
         let filter = doc! { "_id": id };
         self.collection.delete_one(filter).await?;
+        self.revisions
+            .delete_many(doc! { "message_id": id })
+            .await?;
+        self.collection
+            .update_many(
+                doc! { "reply_to": id },
+                doc! { "$set": { "quoted.preview": "" } },
+            )
+            .await?;
+        self.collection
+            .update_many(
+                doc! { "thread_id": id },
+                doc! { "$unset": { "thread_id": "" } },
+            )
+            .await?;
         Ok(())
     }
 
+    /// Replaces the content and keeps the old one as a revision. Returns the edit
+    /// time, or `None` if `message` was edited in the meantime.
+    pub async fn edit_message(
+        &self,
+        message: &Message,
+        content: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let id = message
+            .id
+            .ok_or_else(|| anyhow::anyhow!("Message has no id"))?;
+        // Stored dates keep milliseconds; the returned time must match them.
+        let now = Utc::now().trunc_subsecs(3);
+
+        // The revision goes in first: if the update then fails, the old content is
+        // still recorded rather than lost.
+        let revision = MessageRevision {
+            id: None,
+            message_id: id,
+            content: message.content.clone(),
+            written_at: message.edited_at.unwrap_or(message.sent_at),
+            replaced_at: now,
+        };
+        let revision_id = self.revisions.insert_one(revision).await?.inserted_id;
+
+        let filter = doc! {
+            "_id": id,
+            "edited_at": message.edited_at.as_ref().map_or(Bson::Null, datetime::to_bson),
+        };
+        let update = doc! {
+            "$set": {
+                "content": content,
+                "edited_at": datetime::to_bson(&now),
+            }
+        };
+        let updated = match self.collection.update_one(filter, update).await {
+            Ok(result) => result.matched_count > 0,
+            Err(e) => {
+                self.discard_revision(revision_id).await;
+                return Err(e.into());
+            }
+        };
+        if !updated {
+            self.discard_revision(revision_id).await;
+            return Ok(None);
+        }
+        Ok(Some(now))
+    }
+
+    async fn discard_revision(&self, revision_id: Bson) {
+        if let Err(e) = self.revisions.delete_one(doc! { "_id": revision_id }).await {
+            tracing::warn!("failed to discard unused message revision: {}", e);
+        }
+    }
+
+    pub async fn add_reaction(&self, id: ObjectId, reaction: &Reaction) -> Result<ReactionOutcome> {
+        let filter = doc! {
+            "_id": id,
+            "user_reactions": {
+                "$not": {
+                    "$elemMatch": { "emoji": &reaction.emoji, "user_id": &reaction.user_id }
+                }
+            },
+            // Checked in the same update so concurrent requests can't exceed the cap.
+            "$expr": {
+                "$lt": [
+                    { "$size": {
+                        "$filter": {
+                            "input": { "$ifNull": ["$user_reactions", []] },
+                            "cond": { "$eq": ["$$this.user_id", &reaction.user_id] },
+                        }
+                    } },
+                    MAX_REACTIONS_PER_USER as i32,
+                ]
+            },
+        };
+        let update = doc! { "$push": { "user_reactions": datetime::to_stored(reaction)? } };
+        let result = self.collection.update_one(filter, update).await?;
+        if result.modified_count > 0 {
+            return Ok(ReactionOutcome::Added);
+        }
+
+        let existing = doc! {
+            "_id": id,
+            "user_reactions": {
+                "$elemMatch": { "emoji": &reaction.emoji, "user_id": &reaction.user_id }
+            },
+        };
+        if self.collection.count_documents(existing).await? > 0 {
+            Ok(ReactionOutcome::AlreadyPresent)
+        } else {
+            Ok(ReactionOutcome::LimitReached)
+        }
+    }
+
+    /// Returns false if the user had not reacted with this emoji.
+    pub async fn remove_reaction(&self, id: ObjectId, emoji: &str, user_id: &str) -> Result<bool> {
+        let filter = doc! { "_id": id };
+        let update = doc! {
+            "$pull": { "user_reactions": { "emoji": emoji, "user_id": user_id } }
+        };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    pub async fn revisions(&self, message_id: ObjectId) -> Result<Vec<MessageRevision>> {
+        let mut cursor = self
+            .revisions
+            .find(doc! { "message_id": message_id })
+            .sort(doc! { "replaced_at": 1 })
+            .await?;
+        let mut revisions = Vec::new();
+        while let Some(revision) = cursor.try_next().await? {
+            revisions.push(revision);
+        }
+        Ok(revisions)
+    }
+
+    pub async fn find_since(
+        &self,
+        conv_ids: &[ObjectId],
+        since: ObjectId,
+        limit: i64,
+    ) -> Result<Vec<Message>> {
+        let filter = doc! {
+            "conversation_id": { "$in": conv_ids },
+            "_id": { "$gt": since },
+        };
+        let mut cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! {"_id": 1})
+            .limit(limit)
+            .await?;
+        let mut messages = Vec::new();
+        while let Some(msg) = cursor.try_next().await? {
+            messages.push(msg);
+        }
+        Ok(messages)
+    }
+
     pub async fn get_messages_by_conversation(
         &self,
         conv_id: ObjectId,
         skip: i64,
         limit: i64,
     ) -> Result<Vec<Message>> {
-        let safe_limit = limit.min(100).max(1);
+        let safe_limit = limit.clamp(1, 100);
         let filter = doc! {"conversation_id": conv_id};
         let mut cursor = self
             .collection
             .find(filter)
-            .sort(doc! {"sent_at": -1})
+            .sort(doc! {"sent_at": -1, "_id": -1})
             .skip(skip as u64)
             .limit(safe_limit)
             .await?;
@@ -65,4 +313,113 @@ impl MessageService {
         }
         Ok(messages)
     }
+
+    pub async fn get_messages_page(
+        &self,
+        conv_id: ObjectId,
+        before: Option<&HistoryBound>,
+        after: Option<&HistoryBound>,
+        limit: i64,
+    ) -> Result<Vec<Message>> {
+        self.page(doc! { "conversation_id": conv_id }, before, after, limit)
+            .await
+    }
+
+    pub async fn get_thread_page(
+        &self,
+        root_id: ObjectId,
+        before: Option<&HistoryBound>,
+        after: Option<&HistoryBound>,
+        limit: i64,
+    ) -> Result<Vec<Message>> {
+        self.page(doc! { "thread_id": root_id }, before, after, limit)
+            .await
+    }
+
+    pub async fn record_reply(&self, root_id: ObjectId, sent_at: DateTime<Utc>) -> Result<()> {
+        let filter = doc! { "_id": root_id };
+        let update = doc! {
+            "$inc": { "reply_count": 1 },
+            "$set": { "last_reply_at": datetime::to_bson(&sent_at) },
+        };
+        self.collection.update_one(filter, update).await?;
+        Ok(())
+    }
+
+    pub async fn forget_reply(&self, root_id: ObjectId) -> Result<()> {
+        let filter = doc! { "_id": root_id, "reply_count": { "$gt": 0 } };
+        let update = doc! { "$inc": { "reply_count": -1 } };
+        self.collection.update_one(filter, update).await?;
+        Ok(())
+    }
+
+    /// Newest-first page of `scope` strictly between `after` and `before`.
+    async fn page(
+        &self,
+        scope: Document,
+        before: Option<&HistoryBound>,
+        after: Option<&HistoryBound>,
+        limit: i64,
+    ) -> Result<Vec<Message>> {
+        let safe_limit = limit.clamp(1, 100);
+        let mut bounds = Vec::new();
+        if let Some(before) = before {
+            bounds.push(bound_filter(before, "$lt"));
+        }
+        if let Some(after) = after {
+            bounds.push(bound_filter(after, "$gt"));
+        }
+        let mut filter = scope;
+        if !bounds.is_empty() {
+            filter.insert("$and", bounds);
+        }
+
+        // Paging forward walks up from `after`, then flips back to newest first.
+        let forward = after.is_some() && before.is_none();
+        let order = if forward { 1 } else { -1 };
+        let mut cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "sent_at": order, "_id": order })
+            .limit(safe_limit)
+            .await?;
+        let mut messages = Vec::new();
+        while let Some(msg) = cursor.try_next().await? {
+            messages.push(msg);
+        }
+        if forward {
+            messages.reverse();
+        }
+        Ok(messages)
+    }
+}
+
+fn bound_filter(bound: &HistoryBound, op: &str) -> Document {
+    match bound {
+        HistoryBound::Cursor(cursor) => {
+            let sent_at = datetime::to_bson(&cursor.sent_at);
+            doc! {
+                "$or": [
+                    { "sent_at": { op: sent_at.clone() } },
+                    { "sent_at": sent_at, "_id": { op: cursor.id } },
+                ]
+            }
+        }
+        // Dates are stored to the millisecond: a time inside a millisecond is still
+        // after the messages sent during it.
+        HistoryBound::Time(time)
+            if op == "$lt" && time.timestamp_subsec_nanos() % 1_000_000 > 0 =>
+        {
+            doc! { "sent_at": { "$lte": datetime::to_bson(time) } }
+        }
+        HistoryBound::Time(time) => doc! { "sent_at": { op: datetime::to_bson(time) } },
+    }
+}
+
+/// Converts `field` where it still holds an RFC 3339 string into a BSON date.
+async fn backfill_dates<T: Send + Sync>(collection: &Collection<T>, field: &str) -> Result<()> {
+    let legacy = doc! { field: { "$type": "string" } };
+    let backfill = vec![doc! { "$set": { field: { "$toDate": format!("${}", field) } } }];
+    collection.update_many(legacy, backfill).await?;
+    Ok(())
 }
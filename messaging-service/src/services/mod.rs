@@ -1,2 +1,5 @@
+pub mod cache;
 pub mod conversation;
 pub mod messaging;
+pub mod presence;
+pub mod realtime;
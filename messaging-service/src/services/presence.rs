@@ -0,0 +1,171 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use deadpool_redis::{redis, Pool};
+
+use crate::models::presence::{Presence, PresenceStatus};
+
+const LAST_SEEN_TTL_SECS: u64 = 30 * 24 * 3600;
+
+// Each socket is a member of `presence:{user}` scored by its expiry, so a user stays
+// online while any instance keeps one of their sockets alive, and crashed instances age out.
+pub struct PresenceService {
+    redis: Arc<Pool>,
+    pub ttl: Duration,
+    pub typing_ttl: Duration,
+}
+
+impl PresenceService {
+    pub fn new(redis: Arc<Pool>, ttl: Duration, typing_ttl: Duration) -> Self {
+        Self {
+            redis,
+            ttl,
+            typing_ttl,
+        }
+    }
+
+    /// Registers or refreshes a socket; returns true if the user just came online.
+    pub async fn heartbeat(&self, user_id: &str, socket_id: &str) -> Result<bool> {
+        let mut conn = self.redis.get().await?;
+        let now = Utc::now();
+        let key = presence_key(user_id);
+
+        let (online,): (i64,) = redis::pipe()
+            .cmd("ZREMRANGEBYSCORE")
+            .arg(&key)
+            .arg("-inf")
+            .arg(now.timestamp())
+            .ignore()
+            .cmd("ZCARD")
+            .arg(&key)
+            .cmd("ZADD")
+            .arg(&key)
+            .arg(now.timestamp() + self.ttl.as_secs() as i64)
+            .arg(socket_id)
+            .ignore()
+            .cmd("EXPIRE")
+            .arg(&key)
+            .arg(self.ttl.as_secs())
+            .ignore()
+            .cmd("SET")
+            .arg(last_seen_key(user_id))
+            .arg(now.timestamp())
+            .arg("EX")
+            .arg(LAST_SEEN_TTL_SECS)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        // The count includes this socket once it has been registered.
+        Ok(online == 0)
+    }
+
+    /// Removes a socket; returns true if it was the user's last one.
+    pub async fn disconnect(&self, user_id: &str, socket_id: &str) -> Result<bool> {
+        let mut conn = self.redis.get().await?;
+        let now = Utc::now();
+        let key = presence_key(user_id);
+
+        let (remaining,): (i64,) = redis::pipe()
+            .cmd("ZREM")
+            .arg(&key)
+            .arg(socket_id)
+            .ignore()
+            .cmd("ZREMRANGEBYSCORE")
+            .arg(&key)
+            .arg("-inf")
+            .arg(now.timestamp())
+            .ignore()
+            .cmd("ZCARD")
+            .arg(&key)
+            .cmd("SET")
+            .arg(last_seen_key(user_id))
+            .arg(now.timestamp())
+            .arg("EX")
+            .arg(LAST_SEEN_TTL_SECS)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(remaining == 0)
+    }
+
+    pub async fn get(&self, user_id: &str) -> Result<Presence> {
+        let mut conn = self.redis.get().await?;
+        let now = Utc::now().timestamp();
+
+        let (online, last_seen): (i64, Option<i64>) = redis::pipe()
+            .cmd("ZCOUNT")
+            .arg(presence_key(user_id))
+            .arg(format!("({}", now))
+            .arg("+inf")
+            .cmd("GET")
+            .arg(last_seen_key(user_id))
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(Presence {
+            user_id: user_id.to_string(),
+            status: if online > 0 {
+                PresenceStatus::Online
+            } else {
+                PresenceStatus::Offline
+            },
+            last_seen: last_seen.and_then(|ts| Utc.timestamp_opt(ts, 0).single()),
+        })
+    }
+
+    /// Marks the user as typing; returns the expiry if this started a new typing period.
+    pub async fn start_typing(
+        &self,
+        conversation_id: &str,
+        user_id: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let mut conn = self.redis.get().await?;
+        let key = typing_key(conversation_id, user_id);
+
+        let started: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("EX")
+            .arg(self.typing_ttl.as_secs())
+            .arg("NX")
+            .query_async(&mut conn)
+            .await?;
+        if started.is_none() {
+            redis::cmd("EXPIRE")
+                .arg(&key)
+                .arg(self.typing_ttl.as_secs())
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            return Ok(None);
+        }
+
+        Ok(Some(
+            Utc::now() + chrono::Duration::from_std(self.typing_ttl)?,
+        ))
+    }
+
+    /// Clears the typing flag; returns true if the user was typing.
+    pub async fn stop_typing(&self, conversation_id: &str, user_id: &str) -> Result<bool> {
+        let mut conn = self.redis.get().await?;
+        let deleted: i64 = redis::cmd("DEL")
+            .arg(typing_key(conversation_id, user_id))
+            .query_async(&mut conn)
+            .await?;
+        Ok(deleted > 0)
+    }
+}
+
+fn presence_key(user_id: &str) -> String {
+    format!("presence:{}", user_id)
+}
+
+fn last_seen_key(user_id: &str) -> String {
+    format!("presence:{}:last_seen", user_id)
+}
+
+fn typing_key(conversation_id: &str, user_id: &str) -> String {
+    format!("typing:{}:{}", conversation_id, user_id)
+}
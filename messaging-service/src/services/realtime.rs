@@ -0,0 +1,390 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock, Weak,
+    },
+    time::Duration,
+};
+
+use deadpool_redis::{redis as pool_redis, Pool};
+use futures::StreamExt;
+use redis::{
+    aio::{PubSubSink, PubSubStream},
+    Msg,
+};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::models::event::RealtimeEvent;
+
+const SOCKET_BUFFER: usize = 256;
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+const CHANNEL_PREFIX: &str = "messaging:conversation:";
+
+type EventSender = mpsc::Sender<Arc<RealtimeEvent>>;
+
+struct LocalSocket {
+    user_id: String,
+    sender: EventSender,
+}
+
+enum Command {
+    Subscribe(String),
+    Unsubscribe(String),
+    /// Answered once every command queued before it has been applied.
+    Flush(oneshot::Sender<()>),
+}
+
+pub struct RealtimeHub {
+    pub ping_interval: Duration,
+    pub idle_timeout: Duration,
+    redis: Arc<Pool>,
+    publish_timeout: Duration,
+    subscriber: redis::Client,
+    next_socket_id: AtomicU64,
+    /// Local sockets by conversation id. The instance is subscribed in Redis to
+    /// exactly these conversations.
+    channels: Mutex<HashMap<String, HashMap<u64, LocalSocket>>>,
+    commands: mpsc::UnboundedSender<Command>,
+    pending_commands: Mutex<Option<mpsc::UnboundedReceiver<Command>>>,
+    listener: OnceLock<JoinHandle<()>>,
+}
+
+pub struct Subscription {
+    hub: Arc<RealtimeHub>,
+    socket_id: u64,
+    user_id: String,
+    sender: EventSender,
+    conversations: HashSet<String>,
+    pub events: mpsc::Receiver<Arc<RealtimeEvent>>,
+}
+
+impl RealtimeHub {
+    /// Publishes through `redis`, giving up after `publish_timeout`, and subscribes
+    /// with a dedicated `subscriber` connection that can change its channels while
+    /// it receives messages.
+    pub fn new(
+        redis: Arc<Pool>,
+        subscriber: redis::Client,
+        ping_interval: Duration,
+        idle_timeout: Duration,
+        publish_timeout: Duration,
+    ) -> Self {
+        let (commands, pending_commands) = mpsc::unbounded_channel();
+        Self {
+            ping_interval,
+            idle_timeout,
+            redis,
+            publish_timeout,
+            subscriber,
+            next_socket_id: AtomicU64::new(0),
+            channels: Mutex::new(HashMap::new()),
+            commands,
+            pending_commands: Mutex::new(Some(pending_commands)),
+            listener: OnceLock::new(),
+        }
+    }
+
+    /// Starts the instance's Redis subscriber. It subscribes to a conversation when
+    /// its first local socket joins and unsubscribes when the last one leaves.
+    pub fn spawn_listener(self: &Arc<Self>) {
+        self.listener.get_or_init(|| {
+            let commands = self
+                .pending_commands
+                .lock()
+                .unwrap()
+                .take()
+                .expect("listener is only spawned once");
+            tokio::spawn(listen(
+                Arc::downgrade(self),
+                self.subscriber.clone(),
+                commands,
+            ))
+        });
+    }
+
+    pub fn connect(self: &Arc<Self>, user_id: &str) -> Subscription {
+        let (sender, events) = mpsc::channel(SOCKET_BUFFER);
+        Subscription {
+            hub: self.clone(),
+            socket_id: self.next_socket_id.fetch_add(1, Ordering::Relaxed),
+            user_id: user_id.to_string(),
+            sender,
+            conversations: HashSet::new(),
+            events,
+        }
+    }
+
+    /// Resolves once the Redis subscriptions requested so far are active.
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        if self.commands.send(Command::Flush(done)).is_ok() {
+            let _ = flushed.await;
+        }
+    }
+
+    pub async fn publish(&self, event: RealtimeEvent) {
+        let channel = channel_name(&event.conversation_id());
+        let payload = serde_json::to_string(&event).expect("events always serialize");
+
+        // Write handlers await this, so a Redis that stops answering must not hold them.
+        let published = tokio::time::timeout(self.publish_timeout, async {
+            let mut conn = self.redis.get().await.map_err(|e| e.to_string())?;
+            pool_redis::cmd("PUBLISH")
+                .arg(&channel)
+                .arg(payload)
+                .query_async::<_, i64>(&mut conn)
+                .await
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .unwrap_or_else(|_| Err("timed out".to_string()));
+
+        // Without Redis, at least the sockets connected to this instance get the event.
+        if let Err(e) = published {
+            tracing::warn!("Failed to publish realtime event to {}: {}", channel, e);
+            self.dispatch(&event.conversation_id(), Arc::new(event));
+        }
+    }
+
+    fn dispatch(&self, conversation_id: &str, event: Arc<RealtimeEvent>) {
+        let mut channels = self.channels.lock().unwrap();
+        let Some(sockets) = channels.get_mut(conversation_id) else {
+            return;
+        };
+
+        for (socket_id, socket) in sockets.iter() {
+            // A socket that can't keep up misses events; it catches up with `since` on reconnect.
+            if socket.sender.try_send(event.clone()).is_err() {
+                tracing::warn!("Dropping realtime event for slow socket {}", socket_id);
+            }
+        }
+
+        // Removed members get the event itself, then nothing more from the conversation.
+        sockets.retain(|_, socket| !event.revokes(&socket.user_id));
+        if sockets.is_empty() {
+            channels.remove(conversation_id);
+            self.send(Command::Unsubscribe(conversation_id.to_string()));
+        }
+    }
+
+    fn receive(&self, msg: Msg) {
+        let Some(conversation_id) = msg.get_channel_name().strip_prefix(CHANNEL_PREFIX) else {
+            return;
+        };
+        let Ok(payload) = msg.get_payload::<String>() else {
+            return;
+        };
+        match serde_json::from_str::<RealtimeEvent>(&payload) {
+            Ok(event) => self.dispatch(conversation_id, Arc::new(event)),
+            Err(e) => tracing::warn!("Ignoring malformed realtime event: {}", e),
+        }
+    }
+
+    fn conversation_ids(&self) -> Vec<String> {
+        self.channels.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Events published while the subscriber was down are lost; tell every local
+    /// socket so clients refetch with `since`.
+    fn resync(&self, conversation_ids: &[String]) {
+        if conversation_ids.is_empty() {
+            return;
+        }
+
+        tracing::warn!(
+            "Realtime subscription restored; asking clients of {} conversations to resync",
+            conversation_ids.len()
+        );
+        for conversation_id in conversation_ids {
+            let event = RealtimeEvent::SyncRequired {
+                conversation_id: conversation_id.clone(),
+            };
+            self.dispatch(conversation_id, Arc::new(event));
+        }
+    }
+
+    fn leave(&self, socket_id: u64, conversations: &HashSet<String>) {
+        let mut channels = self.channels.lock().unwrap();
+        for conversation_id in conversations {
+            let Some(sockets) = channels.get_mut(conversation_id) else {
+                continue;
+            };
+            sockets.remove(&socket_id);
+            if sockets.is_empty() {
+                channels.remove(conversation_id);
+                self.send(Command::Unsubscribe(conversation_id.clone()));
+            }
+        }
+    }
+
+    // Called with `channels` locked, so commands are queued in the order the
+    // channel set changed.
+    fn send(&self, command: Command) {
+        // Before the listener runs, queued commands are covered by its first
+        // subscription to the current channel set.
+        let _ = self.commands.send(command);
+    }
+}
+
+impl Drop for RealtimeHub {
+    fn drop(&mut self) {
+        if let Some(listener) = self.listener.get() {
+            listener.abort();
+        }
+    }
+}
+
+impl Subscription {
+    pub fn join(&mut self, conversation_id: String) {
+        self.conversations.insert(conversation_id.clone());
+
+        let mut channels = self.hub.channels.lock().unwrap();
+        let sockets = channels.entry(conversation_id.clone()).or_insert_with(|| {
+            self.hub.send(Command::Subscribe(conversation_id));
+            HashMap::new()
+        });
+        sockets.insert(
+            self.socket_id,
+            LocalSocket {
+                user_id: self.user_id.clone(),
+                sender: self.sender.clone(),
+            },
+        );
+    }
+
+    /// Whether the socket still receives the conversation; membership events can
+    /// remove it after `join`.
+    pub fn is_joined(&self, conversation_id: &str) -> bool {
+        self.hub
+            .channels
+            .lock()
+            .unwrap()
+            .get(conversation_id)
+            .is_some_and(|sockets| sockets.contains_key(&self.socket_id))
+    }
+
+    /// Forgets a conversation the hub already removed this socket from.
+    pub fn forget(&mut self, conversation_id: &str) {
+        self.conversations.remove(conversation_id);
+    }
+
+    pub fn conversations(&self) -> &HashSet<String> {
+        &self.conversations
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.hub.leave(self.socket_id, &self.conversations);
+    }
+}
+
+async fn listen(
+    hub: Weak<RealtimeHub>,
+    client: redis::Client,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    let mut restored = false;
+    loop {
+        match client.get_async_pubsub().await {
+            Ok(pubsub) => {
+                let (mut sink, mut messages) = pubsub.split();
+                match resubscribe(&hub, &mut sink, &mut commands, restored).await {
+                    Ok(true) => run(&hub, &mut sink, &mut messages, &mut commands).await,
+                    Ok(false) => return,
+                    Err(e) => tracing::warn!("Realtime subscription failed: {}", e),
+                }
+                if hub.strong_count() == 0 {
+                    return;
+                }
+            }
+            Err(e) => tracing::warn!("Realtime subscription failed: {}", e),
+        }
+
+        restored = true;
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+/// Subscribes a fresh connection to the current channel set. Commands queued
+/// until now are superseded by it; returns false once the hub is gone.
+async fn resubscribe(
+    hub: &Weak<RealtimeHub>,
+    sink: &mut PubSubSink,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+    restored: bool,
+) -> Result<bool, redis::RedisError> {
+    let mut flushes = Vec::new();
+    while let Ok(command) = commands.try_recv() {
+        if let Command::Flush(done) = command {
+            flushes.push(done);
+        }
+    }
+
+    let Some(conversation_ids) = hub.upgrade().map(|hub| hub.conversation_ids()) else {
+        return Ok(false);
+    };
+    for conversation_id in &conversation_ids {
+        sink.subscribe(channel_name(conversation_id)).await?;
+    }
+    for done in flushes {
+        let _ = done.send(());
+    }
+
+    if restored {
+        let Some(hub) = hub.upgrade() else {
+            return Ok(false);
+        };
+        hub.resync(&conversation_ids);
+    }
+    Ok(true)
+}
+
+async fn run(
+    hub: &Weak<RealtimeHub>,
+    sink: &mut PubSubSink,
+    messages: &mut PubSubStream,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+) {
+    loop {
+        tokio::select! {
+            command = commands.recv() => {
+                let applied = match command {
+                    Some(Command::Subscribe(conversation_id)) => {
+                        sink.subscribe(channel_name(&conversation_id)).await
+                    }
+                    Some(Command::Unsubscribe(conversation_id)) => {
+                        sink.unsubscribe(channel_name(&conversation_id)).await
+                    }
+                    Some(Command::Flush(done)) => {
+                        let _ = done.send(());
+                        Ok(())
+                    }
+                    None => return,
+                };
+                if let Err(e) = applied {
+                    tracing::warn!("Realtime subscription failed: {}", e);
+                    return;
+                }
+            }
+            msg = messages.next() => {
+                let Some(msg) = msg else {
+                    tracing::warn!("Realtime subscription closed");
+                    return;
+                };
+                let Some(hub) = hub.upgrade() else {
+                    return;
+                };
+                hub.receive(msg);
+            }
+        }
+    }
+}
+
+fn channel_name(conversation_id: &str) -> String {
+    format!("{}{}", CHANNEL_PREFIX, conversation_id)
+}
@@ -0,0 +1,27 @@
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+
+use messaging_service::models::message::Message;
+
+/// A stored message from alice, sent now; tests override what they need with
+/// `Message { .., ..message(conversation_id) }`.
+pub fn message(conversation_id: ObjectId) -> Message {
+    Message {
+        id: Some(ObjectId::new()),
+        conversation_id,
+        sender_id: "alice".to_string(),
+        content: "hello".to_string(),
+        sent_at: Utc::now(),
+        read: false,
+        read_by: Vec::new(),
+        delivered_to: Vec::new(),
+        edited_at: None,
+        user_reactions: Vec::new(),
+        reactions: Vec::new(),
+        reply_to: None,
+        quoted: None,
+        thread_id: None,
+        reply_count: 0,
+        last_reply_at: None,
+    }
+}
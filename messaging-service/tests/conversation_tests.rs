@@ -0,0 +1,165 @@
+mod common;
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::{self, oid::ObjectId, Bson};
+use serde_json::json;
+
+use messaging_service::models::conversation::{is_valid_user_id, Conversation, ReceiptMarker};
+use messaging_service::models::message::Message;
+
+fn message_from(sender_id: &str, sent_at: DateTime<Utc>) -> Message {
+    Message {
+        sender_id: sender_id.to_string(),
+        sent_at,
+        ..common::message(ObjectId::new())
+    }
+}
+
+fn conversation() -> Conversation {
+    serde_json::from_value(json!({
+        "_id": { "$oid": ObjectId::new().to_hex() },
+        "participants": ["alice", "bob", "carol"],
+        "created_at": Utc::now(),
+        "updated_at": Utc::now(),
+    }))
+    .unwrap()
+}
+
+fn marker(message: &Message) -> ReceiptMarker {
+    ReceiptMarker::new(message).unwrap()
+}
+
+#[test]
+fn test_marker_covers_earlier_and_same_message() {
+    let now = Utc::now();
+    let read = message_from("alice", now);
+    let marker = marker(&read);
+
+    assert!(marker.covers(read.sent_at, read.id.unwrap()));
+    assert!(marker.covers(now - Duration::seconds(1), ObjectId::new()));
+    assert!(!marker.covers(now + Duration::seconds(1), ObjectId::new()));
+}
+
+#[test]
+fn test_marker_breaks_timestamp_ties_by_message_id() {
+    let now = Utc::now();
+    let first = message_from("alice", now);
+    let second = message_from("alice", now);
+
+    assert!(marker(&second).covers(first.sent_at, first.id.unwrap()));
+    assert!(!marker(&first).covers(second.sent_at, second.id.unwrap()));
+}
+
+#[test]
+fn test_apply_receipts_lists_covered_participants() {
+    let now = Utc::now();
+    let earlier = message_from("alice", now - Duration::seconds(10));
+    let latest = message_from("alice", now);
+
+    let mut conversation = conversation();
+    conversation.read_markers = HashMap::from([
+        ("carol".to_string(), marker(&latest)),
+        ("bob".to_string(), marker(&earlier)),
+    ]);
+    conversation.delivered_markers = HashMap::from([
+        ("bob".to_string(), marker(&latest)),
+        ("carol".to_string(), marker(&latest)),
+    ]);
+
+    let mut target = earlier.clone();
+    conversation.apply_receipts(&mut target);
+    assert_eq!(target.read_by, vec!["bob", "carol"]);
+    assert_eq!(target.delivered_to, vec!["bob", "carol"]);
+    assert!(target.read);
+
+    let mut target = latest.clone();
+    conversation.apply_receipts(&mut target);
+    assert_eq!(target.read_by, vec!["carol"]);
+    assert_eq!(target.delivered_to, vec!["bob", "carol"]);
+}
+
+#[test]
+fn test_apply_receipts_ignores_sender_marker() {
+    let sent = message_from("alice", Utc::now());
+
+    let mut conversation = conversation();
+    conversation.read_markers = HashMap::from([("alice".to_string(), marker(&sent))]);
+
+    let mut target = sent.clone();
+    conversation.apply_receipts(&mut target);
+    assert!(target.read_by.is_empty());
+    assert!(!target.read);
+}
+
+#[test]
+fn test_inbox_entry_reports_unread_count_of_user() {
+    let mut conversation = conversation();
+    conversation.unread_counts = HashMap::from([("bob".to_string(), 3), ("carol".to_string(), -1)]);
+
+    assert_eq!(conversation.inbox_entry("bob").unwrap().unread_count, 3);
+    assert_eq!(conversation.inbox_entry("carol").unwrap().unread_count, 0);
+    assert_eq!(conversation.inbox_entry("alice").unwrap().unread_count, 0);
+}
+
+#[test]
+fn test_inbox_entry_falls_back_to_updated_at() {
+    let mut conversation = conversation();
+    let entry = conversation.inbox_entry("bob").unwrap();
+    assert_eq!(entry.last_activity_at, conversation.updated_at);
+    assert!(entry.last_message.is_none());
+
+    let activity = conversation.updated_at + Duration::minutes(5);
+    conversation.last_activity_at = Some(activity);
+    let entry = conversation.inbox_entry("bob").unwrap();
+    assert_eq!(entry.last_activity_at, activity);
+    assert_eq!(entry.conversation_id, conversation.id.unwrap());
+}
+
+#[test]
+fn test_last_activity_is_stored_as_a_date() {
+    let mut conversation = conversation();
+    let activity = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+    conversation.last_activity_at = Some(activity);
+
+    let stored = bson::to_raw_document_buf(&conversation)
+        .unwrap()
+        .to_document()
+        .unwrap();
+    assert!(matches!(
+        stored.get("last_activity_at"),
+        Some(Bson::DateTime(_))
+    ));
+    let read: Conversation = bson::from_document(stored).unwrap();
+    assert_eq!(read.last_activity_at, Some(activity));
+
+    // The API keeps RFC 3339 strings.
+    let value = serde_json::to_value(&conversation).unwrap();
+    assert!(value["last_activity_at"].is_string());
+}
+
+#[test]
+fn test_string_dates_written_before_are_still_read() {
+    let stored = bson::doc! {
+        "_id": ObjectId::new(),
+        "participants": ["alice", "bob"],
+        "created_at": "2024-01-01T00:00:00.5Z",
+        "updated_at": "2024-01-01T00:00:00.5Z",
+        "last_activity_at": "2024-01-02T00:00:00Z",
+    };
+    let read: Conversation = bson::from_document(stored).unwrap();
+    assert_eq!(
+        read.last_activity_at,
+        Some("2024-01-02T00:00:00Z".parse().unwrap())
+    );
+}
+
+#[test]
+fn test_user_ids_that_are_not_plain_keys_are_rejected() {
+    assert!(is_valid_user_id("65a1f0c2e4b0a1b2c3d4e5f6"));
+    assert!(!is_valid_user_id(""));
+    assert!(!is_valid_user_id("alice.unread"));
+    assert!(!is_valid_user_id("$where"));
+    assert!(!is_valid_user_id("bob\0"));
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::{self, oid::ObjectId, Bson};
+
+use messaging_service::handlers::messaging::{page_cursors, page_key};
+use messaging_service::models::message::{HistoryBound, Message, MessageCursor, MessageRevision};
+
+fn message_at(sent_at: DateTime<Utc>) -> Message {
+    Message {
+        sent_at,
+        ..common::message(ObjectId::new())
+    }
+}
+
+fn header(headers: &axum::http::HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .map(|value| value.to_str().unwrap().to_string())
+}
+
+#[test]
+fn test_cursor_round_trips() {
+    let cursor = MessageCursor::new(&message_at(Utc::now())).unwrap();
+
+    let encoded = cursor.encode();
+    assert_eq!(encoded.len(), 40);
+    assert_eq!(MessageCursor::decode(&encoded), Some(cursor));
+}
+
+#[test]
+fn test_cursor_rejects_ids_and_garbage() {
+    assert_eq!(MessageCursor::decode(&ObjectId::new().to_hex()), None);
+    assert_eq!(MessageCursor::decode("2024-01-01T00:00:00Z"), None);
+    assert_eq!(MessageCursor::decode(&"z".repeat(40)), None);
+    assert_eq!(MessageCursor::decode(""), None);
+}
+
+#[test]
+fn test_page_cursors_point_at_page_edges() {
+    let now = Utc::now();
+    let messages = vec![
+        message_at(now),
+        message_at(now - Duration::seconds(1)),
+        message_at(now - Duration::seconds(2)),
+    ];
+
+    let headers = page_cursors(&messages, 3);
+    let prev = MessageCursor::new(&messages[0]).unwrap().encode();
+    let next = MessageCursor::new(&messages[2]).unwrap().encode();
+    assert_eq!(header(&headers, "x-prev-cursor"), Some(prev));
+    assert_eq!(header(&headers, "x-next-cursor"), Some(next));
+}
+
+#[test]
+fn test_page_cursors_omit_next_on_last_page() {
+    let messages = vec![message_at(Utc::now())];
+
+    let headers = page_cursors(&messages, 50);
+    assert!(header(&headers, "x-prev-cursor").is_some());
+    assert_eq!(header(&headers, "x-next-cursor"), None);
+
+    assert!(page_cursors(&[], 50).is_empty());
+}
+
+#[test]
+fn test_page_key_distinguishes_pages() {
+    let now = Utc::now();
+    let cursor = HistoryBound::Cursor(MessageCursor::new(&message_at(now)).unwrap());
+    let time = HistoryBound::Time(now);
+
+    let keys = [
+        page_key(0, 50, None, None),
+        page_key(50, 50, None, None),
+        page_key(0, 20, None, None),
+        page_key(0, 50, Some(&cursor), None),
+        page_key(0, 50, None, Some(&cursor)),
+        page_key(0, 50, Some(&time), None),
+    ];
+    let unique: std::collections::HashSet<_> = keys.iter().collect();
+    assert_eq!(unique.len(), keys.len());
+
+    assert_eq!(
+        page_key(0, 50, Some(&cursor), None),
+        page_key(0, 50, Some(&cursor), None)
+    );
+}
+
+#[test]
+fn test_sent_at_is_stored_as_a_date() {
+    let sent = message_at(DateTime::from_timestamp_millis(1_700_000_000_250).unwrap());
+
+    let stored = bson::to_raw_document_buf(&sent)
+        .unwrap()
+        .to_document()
+        .unwrap();
+    assert!(matches!(stored.get("sent_at"), Some(Bson::DateTime(_))));
+    let read: Message = bson::from_document(stored).unwrap();
+    assert_eq!(read.sent_at, sent.sent_at);
+
+    let value = serde_json::to_value(&sent).unwrap();
+    assert_eq!(value["sent_at"], "2023-11-14T22:13:20.250Z");
+}
+
+#[test]
+fn test_edit_dates_are_stored_as_dates() {
+    let sent_at = DateTime::from_timestamp_millis(1_700_000_000_250).unwrap();
+    let edited = Message {
+        edited_at: Some(sent_at + Duration::seconds(5)),
+        ..message_at(sent_at)
+    };
+    let stored = bson::to_raw_document_buf(&edited)
+        .unwrap()
+        .to_document()
+        .unwrap();
+    assert!(matches!(stored.get("edited_at"), Some(Bson::DateTime(_))));
+    let read: Message = bson::from_document(stored).unwrap();
+    assert_eq!(read.edited_at, edited.edited_at);
+
+    let revision = MessageRevision {
+        id: None,
+        message_id: ObjectId::new(),
+        content: "hello".to_string(),
+        written_at: sent_at,
+        replaced_at: sent_at + Duration::seconds(5),
+    };
+    let stored = bson::to_raw_document_buf(&revision)
+        .unwrap()
+        .to_document()
+        .unwrap();
+    assert!(matches!(stored.get("written_at"), Some(Bson::DateTime(_))));
+    assert!(matches!(stored.get("replaced_at"), Some(Bson::DateTime(_))));
+
+    // Revisions written before were stored as strings.
+    let legacy = bson::doc! {
+        "message_id": revision.message_id,
+        "content": "hello",
+        "written_at": "2023-11-14T22:13:20.250Z",
+        "replaced_at": "2023-11-14T22:13:25.250Z",
+    };
+    let read: MessageRevision = bson::from_document(legacy).unwrap();
+    assert_eq!(read.written_at, revision.written_at);
+    assert_eq!(read.replaced_at, revision.replaced_at);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::{oid::ObjectId, Bson};
+use serde_json::json;
+
+use messaging_service::models::datetime;
+use messaging_service::models::event::RealtimeEvent;
+use messaging_service::models::message::{is_valid_emoji, Message, Reaction};
+
+fn message_with_reactions(reactions: &[(&str, &str)]) -> Message {
+    let user_reactions = reactions
+        .iter()
+        .map(|(user_id, emoji)| Reaction {
+            emoji: emoji.to_string(),
+            user_id: user_id.to_string(),
+            reacted_at: Utc::now(),
+        })
+        .collect();
+    Message {
+        user_reactions,
+        ..common::message(ObjectId::new())
+    }
+}
+
+#[test]
+fn test_apply_reactions_counts_in_order_of_first_use() {
+    let mut message = message_with_reactions(&[
+        ("bob", "👍"),
+        ("carol", "🎉"),
+        ("carol", "👍"),
+        ("alice", "❤️"),
+    ]);
+
+    message.apply_reactions(Some("carol"));
+    let summary: Vec<_> = message
+        .reactions
+        .iter()
+        .map(|r| (r.emoji.as_str(), r.count, r.reacted_by_me))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("👍", 2, true), ("🎉", 1, true), ("❤️", 1, false)]
+    );
+    assert!(message.user_reactions.is_empty());
+}
+
+#[test]
+fn test_apply_reactions_without_viewer() {
+    let mut message = message_with_reactions(&[("bob", "👍")]);
+
+    message.apply_reactions(None);
+    assert_eq!(message.reactions.len(), 1);
+    assert!(!message.reactions[0].reacted_by_me);
+
+    let mut empty = message_with_reactions(&[]);
+    empty.apply_reactions(Some("bob"));
+    assert!(empty.reactions.is_empty());
+}
+
+#[test]
+fn test_valid_emojis() {
+    for emoji in ["👍", "❤️", "👍🏽", "👨‍👩‍👧", "🇫🇷", "1️⃣", "#️⃣"]
+    {
+        assert!(is_valid_emoji(emoji), "{emoji} should be accepted");
+    }
+}
+
+#[test]
+fn test_invalid_emojis() {
+    for emoji in ["", "a", "1", "ok", "👍 👍", "👍a", " 👍"] {
+        assert!(!is_valid_emoji(emoji), "{emoji:?} should be rejected");
+    }
+    assert!(!is_valid_emoji(&"👍".repeat(17)));
+}
+
+#[test]
+fn test_reaction_events() {
+    let added = RealtimeEvent::ReactionAdded {
+        conversation_id: "c1".to_string(),
+        message_id: "m1".to_string(),
+        user_id: "bob".to_string(),
+        emoji: "👍".to_string(),
+    };
+    assert_eq!(added.conversation_id(), "c1");
+    assert_eq!(
+        serde_json::to_value(&added).unwrap(),
+        json!({
+            "type": "reaction.added",
+            "conversation_id": "c1",
+            "message_id": "m1",
+            "user_id": "bob",
+            "emoji": "👍",
+        })
+    );
+
+    let payload = serde_json::to_string(&RealtimeEvent::ReactionRemoved {
+        conversation_id: "c1".to_string(),
+        message_id: "m1".to_string(),
+        user_id: "bob".to_string(),
+        emoji: "👍".to_string(),
+    })
+    .unwrap();
+    let decoded: RealtimeEvent = serde_json::from_str(&payload).unwrap();
+    assert!(matches!(
+        decoded,
+        RealtimeEvent::ReactionRemoved { emoji, .. } if emoji == "👍"
+    ));
+}
+
+#[test]
+fn test_reacted_at_is_stored_as_a_date() {
+    let reaction = Reaction {
+        emoji: "👍".to_string(),
+        user_id: "bob".to_string(),
+        reacted_at: DateTime::from_timestamp_millis(1_700_000_000_250).unwrap(),
+    };
+
+    let Bson::Document(stored) = datetime::to_stored(&reaction).unwrap() else {
+        panic!("a reaction is stored as a document");
+    };
+    assert!(matches!(stored.get("reacted_at"), Some(Bson::DateTime(_))));
+    let read: Reaction = mongodb::bson::from_document(stored).unwrap();
+    assert_eq!(read.reacted_at, reaction.reacted_at);
+}
@@ -0,0 +1,274 @@
+use std::{sync::Arc, time::Duration};
+
+use deadpool_redis::{Config as RedisConfig, Runtime};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{tcp::OwnedReadHalf, TcpListener},
+    sync::mpsc,
+};
+
+use messaging_service::models::event::RealtimeEvent;
+use messaging_service::services::realtime::RealtimeHub;
+
+const WAIT: Duration = Duration::from_secs(5);
+
+enum Push {
+    Message(String, String),
+    Close,
+}
+
+/// Just enough of a Redis server for one pub/sub connection at a time: it
+/// records (UN)SUBSCRIBE commands and pushes messages on demand.
+struct FakeRedis {
+    url: String,
+    commands: mpsc::UnboundedReceiver<Vec<String>>,
+    push: mpsc::UnboundedSender<Push>,
+}
+
+impl FakeRedis {
+    async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+        let (commands_tx, commands) = mpsc::unbounded_channel();
+        let (push, mut pushes) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (read, mut write) = stream.into_split();
+                let (received_tx, mut received) = mpsc::unbounded_channel();
+                tokio::spawn(read_commands(read, received_tx));
+
+                loop {
+                    tokio::select! {
+                        command = received.recv() => {
+                            let Some(command) = command else { break };
+                            write.write_all(&reply(&command)).await.unwrap();
+                            let _ = commands_tx.send(command);
+                        }
+                        push = pushes.recv() => match push {
+                            Some(Push::Message(channel, payload)) => {
+                                let frame = array(&["message", &channel, &payload]);
+                                write.write_all(&frame).await.unwrap();
+                            }
+                            Some(Push::Close) | None => break,
+                        },
+                    }
+                }
+            }
+        });
+
+        Self {
+            url,
+            commands,
+            push,
+        }
+    }
+
+    /// Next SUBSCRIBE or UNSUBSCRIBE, skipping connection setup commands.
+    async fn next_subscription(&mut self) -> Vec<String> {
+        tokio::time::timeout(WAIT, async {
+            loop {
+                let command = self.commands.recv().await.unwrap();
+                if matches!(command[0].as_str(), "SUBSCRIBE" | "UNSUBSCRIBE") {
+                    return command;
+                }
+            }
+        })
+        .await
+        .expect("no subscription change")
+    }
+
+    async fn assert_no_subscription(&mut self) {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        while let Ok(command) = self.commands.try_recv() {
+            assert!(
+                !matches!(command[0].as_str(), "SUBSCRIBE" | "UNSUBSCRIBE"),
+                "unexpected {:?}",
+                command
+            );
+        }
+    }
+}
+
+async fn read_commands(read: OwnedReadHalf, received: mpsc::UnboundedSender<Vec<String>>) {
+    let mut reader = BufReader::new(read);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        let count: usize = line.trim_end()[1..].parse().unwrap();
+        let mut command = Vec::with_capacity(count);
+        for _ in 0..count {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            let len: usize = line.trim_end()[1..].parse().unwrap();
+            let mut data = vec![0; len + 2];
+            reader.read_exact(&mut data).await.unwrap();
+            data.truncate(len);
+            command.push(String::from_utf8(data).unwrap());
+        }
+        command[0] = command[0].to_ascii_uppercase();
+        if received.send(command).is_err() {
+            return;
+        }
+    }
+}
+
+fn reply(command: &[String]) -> Vec<u8> {
+    match command[0].as_str() {
+        "SUBSCRIBE" | "UNSUBSCRIBE" => command[1..]
+            .iter()
+            .flat_map(|channel| {
+                let kind = command[0].to_ascii_lowercase();
+                let mut frame = format!("*3\r\n${}\r\n{}\r\n", kind.len(), kind).into_bytes();
+                frame.extend(format!("${}\r\n{}\r\n:1\r\n", channel.len(), channel).bytes());
+                frame
+            })
+            .collect(),
+        _ => b"+OK\r\n".to_vec(),
+    }
+}
+
+fn array(items: &[&str]) -> Vec<u8> {
+    let mut frame = format!("*{}\r\n", items.len());
+    for item in items {
+        frame.push_str(&format!("${}\r\n{}\r\n", item.len(), item));
+    }
+    frame.into_bytes()
+}
+
+// Publishing goes to a closed port and falls back to local dispatch.
+fn hub(subscriber_url: &str) -> Arc<RealtimeHub> {
+    let redis = RedisConfig::from_url("redis://127.0.0.1:1")
+        .create_pool(Some(Runtime::Tokio1))
+        .unwrap();
+    let hub = Arc::new(RealtimeHub::new(
+        Arc::new(redis),
+        redis::Client::open(subscriber_url).unwrap(),
+        Duration::from_secs(30),
+        Duration::from_secs(90),
+        Duration::from_millis(200),
+    ));
+    hub.spawn_listener();
+    hub
+}
+
+async fn flush(hub: &RealtimeHub) {
+    tokio::time::timeout(WAIT, hub.flush())
+        .await
+        .expect("subscriptions not applied");
+}
+
+fn subscribe(channel: &str) -> Vec<String> {
+    vec!["SUBSCRIBE".to_string(), channel.to_string()]
+}
+
+fn unsubscribe(channel: &str) -> Vec<String> {
+    vec!["UNSUBSCRIBE".to_string(), channel.to_string()]
+}
+
+fn deleted(conversation_id: &str) -> String {
+    serde_json::to_string(&RealtimeEvent::MessageDeleted {
+        conversation_id: conversation_id.to_string(),
+        message_id: "m1".to_string(),
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_subscribes_on_first_join_and_unsubscribes_on_last_leave() {
+    let mut redis = FakeRedis::start().await;
+    let hub = hub(&redis.url);
+
+    let mut alice = hub.connect("alice");
+    let mut bob = hub.connect("bob");
+    alice.join("c1".to_string());
+    bob.join("c1".to_string());
+    flush(&hub).await;
+    assert_eq!(
+        redis.next_subscription().await,
+        subscribe("messaging:conversation:c1")
+    );
+    redis.assert_no_subscription().await;
+
+    drop(alice);
+    flush(&hub).await;
+    redis.assert_no_subscription().await;
+
+    drop(bob);
+    flush(&hub).await;
+    assert_eq!(
+        redis.next_subscription().await,
+        unsubscribe("messaging:conversation:c1")
+    );
+}
+
+#[tokio::test]
+async fn test_delivers_messages_of_subscribed_conversations() {
+    let mut redis = FakeRedis::start().await;
+    let hub = hub(&redis.url);
+
+    let mut alice = hub.connect("alice");
+    alice.join("c1".to_string());
+    flush(&hub).await;
+    redis.next_subscription().await;
+
+    redis
+        .push
+        .send(Push::Message(
+            "messaging:conversation:c1".to_string(),
+            deleted("c1"),
+        ))
+        .unwrap();
+    let event = tokio::time::timeout(WAIT, alice.events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(*event, RealtimeEvent::MessageDeleted { .. }));
+}
+
+#[tokio::test]
+async fn test_removed_member_unsubscribes_when_no_socket_is_left() {
+    let mut redis = FakeRedis::start().await;
+    let hub = hub(&redis.url);
+
+    let mut bob = hub.connect("bob");
+    bob.join("c1".to_string());
+    flush(&hub).await;
+    redis.next_subscription().await;
+
+    hub.publish(RealtimeEvent::MemberRemoved {
+        conversation_id: "c1".to_string(),
+        user_id: "bob".to_string(),
+    })
+    .await;
+    flush(&hub).await;
+    assert_eq!(
+        redis.next_subscription().await,
+        unsubscribe("messaging:conversation:c1")
+    );
+}
+
+#[tokio::test]
+async fn test_resubscribes_after_reconnect_and_asks_clients_to_resync() {
+    let mut redis = FakeRedis::start().await;
+    let hub = hub(&redis.url);
+
+    let mut alice = hub.connect("alice");
+    alice.join("c1".to_string());
+    flush(&hub).await;
+    redis.next_subscription().await;
+
+    redis.push.send(Push::Close).unwrap();
+    assert_eq!(
+        redis.next_subscription().await,
+        subscribe("messaging:conversation:c1")
+    );
+    let event = tokio::time::timeout(WAIT, alice.events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(*event, RealtimeEvent::SyncRequired { .. }));
+}
@@ -0,0 +1,305 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use deadpool_redis::{Config as RedisConfig, Runtime};
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+use messaging_service::models::event::{ClientFrame, RealtimeEvent};
+use messaging_service::models::presence::{Presence, PresenceStatus};
+use messaging_service::services::realtime::RealtimeHub;
+
+// Nothing listens on port 1, so publishing falls back to local dispatch.
+fn hub() -> Arc<RealtimeHub> {
+    let redis = RedisConfig::from_url("redis://127.0.0.1:1")
+        .create_pool(Some(Runtime::Tokio1))
+        .unwrap();
+    Arc::new(RealtimeHub::new(
+        Arc::new(redis),
+        redis::Client::open("redis://127.0.0.1:1").unwrap(),
+        Duration::from_secs(30),
+        Duration::from_secs(90),
+        Duration::from_millis(200),
+    ))
+}
+
+fn deleted(conversation_id: &str) -> RealtimeEvent {
+    RealtimeEvent::MessageDeleted {
+        conversation_id: conversation_id.to_string(),
+        message_id: ObjectId::new().to_hex(),
+    }
+}
+
+#[test]
+fn test_event_type_tags() {
+    let conversation_id = ObjectId::new();
+
+    let created = serde_json::to_value(RealtimeEvent::MessageCreated {
+        message: common::message(conversation_id),
+    })
+    .unwrap();
+    assert_eq!(created["type"], "message.created");
+    assert_eq!(created["message"]["content"], "hello");
+
+    let deleted = serde_json::to_value(deleted("c1")).unwrap();
+    assert_eq!(deleted["type"], "message.deleted");
+    assert_eq!(deleted["conversation_id"], "c1");
+
+    let read = serde_json::to_value(RealtimeEvent::MessageRead {
+        conversation_id: "c1".to_string(),
+        message_id: "m1".to_string(),
+        reader_id: "bob".to_string(),
+        read_at: Utc::now(),
+    })
+    .unwrap();
+    assert_eq!(read["type"], "message.read");
+    assert_eq!(read["reader_id"], "bob");
+
+    let sync = serde_json::to_value(RealtimeEvent::SyncRequired {
+        conversation_id: "c1".to_string(),
+    })
+    .unwrap();
+    assert_eq!(
+        sync,
+        json!({ "type": "sync.required", "conversation_id": "c1" })
+    );
+
+    let removed = serde_json::to_value(RealtimeEvent::MemberRemoved {
+        conversation_id: "c1".to_string(),
+        user_id: "bob".to_string(),
+    })
+    .unwrap();
+    assert_eq!(removed["type"], "member.removed");
+    assert_eq!(removed["user_id"], "bob");
+}
+
+#[test]
+fn test_event_round_trips_through_redis_payload() {
+    let conversation_id = ObjectId::new();
+    let event = RealtimeEvent::MessageCreated {
+        message: common::message(conversation_id),
+    };
+
+    let payload = serde_json::to_string(&event).unwrap();
+    let decoded: RealtimeEvent = serde_json::from_str(&payload).unwrap();
+    assert_eq!(decoded.conversation_id(), conversation_id.to_hex());
+    assert!(matches!(decoded, RealtimeEvent::MessageCreated { .. }));
+}
+
+#[test]
+fn test_client_frames() {
+    assert!(matches!(
+        serde_json::from_str::<ClientFrame>(r#"{"type":"ping"}"#).unwrap(),
+        ClientFrame::Ping
+    ));
+    assert!(matches!(
+        serde_json::from_str::<ClientFrame>(r#"{"type":"subscribe","conversation_id":"c1"}"#)
+            .unwrap(),
+        ClientFrame::Subscribe { conversation_id } if conversation_id == "c1"
+    ));
+    assert!(serde_json::from_str::<ClientFrame>(r#"{"type":"subscribe"}"#).is_err());
+    assert!(serde_json::from_str::<ClientFrame>(r#"{"type":"shout"}"#).is_err());
+}
+
+#[tokio::test]
+async fn test_events_reach_only_joined_sockets() {
+    let hub = hub();
+    let mut alice = hub.connect("alice");
+    let mut bob = hub.connect("bob");
+    alice.join("c1".to_string());
+    bob.join("c2".to_string());
+
+    hub.publish(deleted("c1")).await;
+
+    let event = alice.events.try_recv().unwrap();
+    assert_eq!(event.conversation_id(), "c1");
+    assert!(bob.events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_other_sockets_keep_receiving_after_one_leaves() {
+    let hub = hub();
+    let mut alice = hub.connect("alice");
+    alice.join("c1".to_string());
+    let bob = {
+        let mut bob = hub.connect("bob");
+        bob.join("c1".to_string());
+        bob
+    };
+    drop(bob);
+
+    hub.publish(deleted("c1")).await;
+    assert!(alice.events.try_recv().is_ok());
+}
+
+#[tokio::test]
+async fn test_slow_socket_drops_events_without_blocking() {
+    let hub = hub();
+    let mut slow = hub.connect("slow");
+    slow.join("c1".to_string());
+
+    let published = tokio::time::timeout(Duration::from_secs(10), async {
+        for _ in 0..300 {
+            hub.publish(deleted("c1")).await;
+        }
+    })
+    .await;
+    assert!(published.is_ok());
+
+    let mut received = 0;
+    while slow.events.try_recv().is_ok() {
+        received += 1;
+    }
+    assert_eq!(received, 256);
+}
+
+#[tokio::test]
+async fn test_unresponsive_redis_falls_back_to_local_dispatch() {
+    // Accepts connections and never answers.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("redis://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+
+    let redis = RedisConfig::from_url(url.as_str())
+        .create_pool(Some(Runtime::Tokio1))
+        .unwrap();
+    let hub = Arc::new(RealtimeHub::new(
+        Arc::new(redis),
+        redis::Client::open(url.as_str()).unwrap(),
+        Duration::from_secs(30),
+        Duration::from_secs(90),
+        Duration::from_millis(200),
+    ));
+    let mut alice = hub.connect("alice");
+    alice.join("c1".to_string());
+
+    let published = tokio::time::timeout(Duration::from_secs(2), hub.publish(deleted("c1"))).await;
+    assert!(published.is_ok());
+    assert!(alice.events.try_recv().is_ok());
+}
+
+#[tokio::test]
+async fn test_removed_member_stops_receiving_conversation() {
+    let hub = hub();
+    let mut alice = hub.connect("alice");
+    let mut bob = hub.connect("bob");
+    let mut bob_phone = hub.connect("bob");
+    for subscription in [&mut alice, &mut bob, &mut bob_phone] {
+        subscription.join("c1".to_string());
+        subscription.join("c2".to_string());
+    }
+
+    hub.publish(RealtimeEvent::MemberRemoved {
+        conversation_id: "c1".to_string(),
+        user_id: "bob".to_string(),
+    })
+    .await;
+    for subscription in [&mut alice, &mut bob, &mut bob_phone] {
+        let event = subscription.events.try_recv().unwrap();
+        assert!(matches!(*event, RealtimeEvent::MemberRemoved { .. }));
+    }
+    assert!(alice.is_joined("c1"));
+    assert!(!bob.is_joined("c1"));
+    assert!(!bob_phone.is_joined("c1"));
+    assert!(bob.is_joined("c2"));
+
+    hub.publish(deleted("c1")).await;
+    assert!(alice.events.try_recv().is_ok());
+    assert!(bob.events.try_recv().is_err());
+    assert!(bob_phone.events.try_recv().is_err());
+
+    hub.publish(deleted("c2")).await;
+    assert!(bob.events.try_recv().is_ok());
+}
+
+#[tokio::test]
+async fn test_deleted_conversation_reaches_no_socket() {
+    let hub = hub();
+    let mut alice = hub.connect("alice");
+    alice.join("c1".to_string());
+
+    hub.publish(RealtimeEvent::ConversationDeleted {
+        conversation_id: "c1".to_string(),
+    })
+    .await;
+    let event = alice.events.try_recv().unwrap();
+    assert!(matches!(*event, RealtimeEvent::ConversationDeleted { .. }));
+    assert!(!alice.is_joined("c1"));
+
+    hub.publish(deleted("c1")).await;
+    assert!(alice.events.try_recv().is_err());
+}
+
+#[test]
+fn test_typing_frames() {
+    assert!(matches!(
+        serde_json::from_str::<ClientFrame>(r#"{"type":"typing","conversation_id":"c1"}"#).unwrap(),
+        ClientFrame::Typing { conversation_id } if conversation_id == "c1"
+    ));
+    assert!(matches!(
+        serde_json::from_str::<ClientFrame>(r#"{"type":"stop_typing","conversation_id":"c1"}"#)
+            .unwrap(),
+        ClientFrame::StopTyping { conversation_id } if conversation_id == "c1"
+    ));
+}
+
+#[test]
+fn test_typing_and_presence_events() {
+    let started = serde_json::to_value(RealtimeEvent::TypingStarted {
+        conversation_id: "c1".to_string(),
+        user_id: "alice".to_string(),
+        expires_at: Utc::now(),
+    })
+    .unwrap();
+    assert_eq!(started["type"], "typing.started");
+    assert!(started["expires_at"].is_string());
+
+    let stopped = serde_json::to_value(RealtimeEvent::TypingStopped {
+        conversation_id: "c1".to_string(),
+        user_id: "alice".to_string(),
+    })
+    .unwrap();
+    assert_eq!(stopped["type"], "typing.stopped");
+
+    let presence = RealtimeEvent::PresenceUpdated {
+        conversation_id: "c1".to_string(),
+        presence: Presence {
+            user_id: "alice".to_string(),
+            status: PresenceStatus::Online,
+            last_seen: None,
+        },
+    };
+    assert_eq!(presence.conversation_id(), "c1");
+    let presence = serde_json::to_value(presence).unwrap();
+    assert_eq!(presence["type"], "presence.updated");
+    assert_eq!(presence["presence"]["status"], "online");
+}
+
+#[test]
+fn test_message_edited_event() {
+    let conversation_id = ObjectId::new();
+    let mut edited = common::message(conversation_id);
+    edited.content = "hello again".to_string();
+    edited.edited_at = Some(Utc::now());
+
+    let event = RealtimeEvent::MessageEdited { message: edited };
+    assert_eq!(event.conversation_id(), conversation_id.to_hex());
+
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(value["type"], "message.edited");
+    assert_eq!(value["message"]["content"], "hello again");
+    assert!(value["message"]["edited_at"].is_string());
+
+    let decoded: RealtimeEvent = serde_json::from_value(value).unwrap();
+    assert!(matches!(
+        decoded,
+        RealtimeEvent::MessageEdited { message } if message.edited_at.is_some()
+    ));
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use chrono::{DateTime, Duration, SubsecRound, Utc};
+use mongodb::{
+    bson::{self, oid::ObjectId, Bson},
+    Client as MongoClient,
+};
+
+use messaging_service::models::message::Message;
+use messaging_service::services::messaging::MessageService;
+
+fn message_with_content(conversation_id: ObjectId, content: &str) -> Message {
+    Message {
+        content: content.to_string(),
+        ..common::message(conversation_id)
+    }
+}
+
+fn reply(target: &Message, content: &str) -> Message {
+    let mut reply = message_with_content(target.conversation_id, content);
+    assert!(reply.set_reply_target(target));
+    reply
+}
+
+// Uses the database in `MONGODB_URI`, like the service; skipped when it isn't set.
+async fn service() -> Option<MessageService> {
+    let Ok(uri) = std::env::var("MONGODB_URI") else {
+        eprintln!("MONGODB_URI is not set, skipping");
+        return None;
+    };
+    let client = MongoClient::with_uri_str(&uri)
+        .await
+        .expect("Failed to connect to MongoDB");
+    Some(MessageService::new(&client))
+}
+
+async fn insert(service: &MessageService, message: &Message) {
+    service.send_message(message.clone()).await.unwrap();
+}
+
+async fn find(service: &MessageService, message: &Message) -> Message {
+    service
+        .find_by_id(message.id.unwrap())
+        .await
+        .unwrap()
+        .expect("message exists")
+}
+
+#[test]
+fn test_reply_quotes_target_and_starts_thread() {
+    let root = message_with_content(ObjectId::new(), "root");
+    let answer = reply(&root, "answer");
+
+    assert_eq!(answer.reply_to, root.id);
+    assert_eq!(answer.thread_id, root.id);
+    let quoted = answer.quoted.unwrap();
+    assert_eq!(quoted.message_id, root.id.unwrap());
+    assert_eq!(quoted.preview, "root");
+}
+
+#[test]
+fn test_reply_to_reply_joins_root_thread() {
+    let root = message_with_content(ObjectId::new(), "root");
+    let answer = reply(&root, "answer");
+    let nested = reply(&answer, "nested");
+
+    assert_eq!(nested.reply_to, answer.id);
+    assert_eq!(nested.thread_id, root.id);
+    assert_eq!(nested.quoted.unwrap().preview, "answer");
+}
+
+#[test]
+fn test_reply_target_must_be_in_same_conversation() {
+    let target = message_with_content(ObjectId::new(), "elsewhere");
+    let mut answer = message_with_content(ObjectId::new(), "answer");
+
+    assert!(!answer.set_reply_target(&target));
+    assert!(answer.reply_to.is_none());
+    assert!(answer.thread_id.is_none());
+    assert!(answer.quoted.is_none());
+}
+
+#[test]
+fn test_last_reply_at_is_stored_as_a_date() {
+    let root = Message {
+        reply_count: 1,
+        last_reply_at: DateTime::from_timestamp_millis(1_700_000_000_250),
+        ..message_with_content(ObjectId::new(), "root")
+    };
+
+    let stored = bson::to_raw_document_buf(&root)
+        .unwrap()
+        .to_document()
+        .unwrap();
+    assert!(matches!(
+        stored.get("last_reply_at"),
+        Some(Bson::DateTime(_))
+    ));
+    let read: Message = bson::from_document(stored).unwrap();
+    assert_eq!(read.last_reply_at, root.last_reply_at);
+}
+
+#[tokio::test]
+async fn test_reply_count_goes_up_and_down() {
+    let Some(service) = service().await else {
+        return;
+    };
+    let root = message_with_content(ObjectId::new(), "root");
+    insert(&service, &root).await;
+    let root_id = root.id.unwrap();
+
+    let sent_at = (Utc::now() + Duration::seconds(1)).trunc_subsecs(3);
+    service.record_reply(root_id, sent_at).await.unwrap();
+    service.record_reply(root_id, sent_at).await.unwrap();
+    let found = find(&service, &root).await;
+    assert_eq!(found.reply_count, 2);
+    assert_eq!(found.last_reply_at, Some(sent_at));
+
+    service.forget_reply(root_id).await.unwrap();
+    assert_eq!(find(&service, &root).await.reply_count, 1);
+    service.forget_reply(root_id).await.unwrap();
+    service.forget_reply(root_id).await.unwrap();
+    assert_eq!(find(&service, &root).await.reply_count, 0);
+
+    service.delete_message(root_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_deleting_message_empties_quotes_of_replies() {
+    let Some(service) = service().await else {
+        return;
+    };
+    let root = message_with_content(ObjectId::new(), "root");
+    let answer = reply(&root, "secret");
+    let nested = reply(&answer, "nested");
+    for message in [&root, &answer, &nested] {
+        insert(&service, message).await;
+    }
+
+    service.delete_message(answer.id.unwrap()).await.unwrap();
+    let nested = find(&service, &nested).await;
+    let quoted = nested.quoted.unwrap();
+    assert_eq!(quoted.message_id, answer.id.unwrap());
+    assert!(quoted.preview.is_empty());
+    assert_eq!(nested.thread_id, root.id);
+
+    service.delete_message(root.id.unwrap()).await.unwrap();
+    service.delete_message(nested.id.unwrap()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_deleting_thread_root_releases_replies() {
+    let Some(service) = service().await else {
+        return;
+    };
+    let root = message_with_content(ObjectId::new(), "root");
+    let answer = reply(&root, "answer");
+    insert(&service, &root).await;
+    insert(&service, &answer).await;
+
+    service.delete_message(root.id.unwrap()).await.unwrap();
+    let answer = find(&service, &answer).await;
+    assert!(answer.thread_id.is_none());
+    assert_eq!(answer.reply_to, root.id);
+    assert!(answer.quoted.unwrap().preview.is_empty());
+
+    service.delete_message(answer.id.unwrap()).await.unwrap();
+}
@@ -0,0 +1,24 @@
+//! Purges of the gateway's response cache, shared by the gateway and the backends
+//! that publish them.
+
+use serde::{Deserialize, Serialize};
+
+/// Redis channel every gateway replica subscribes to, so that a purge published
+/// here drops the entries of all of them.
+pub const PURGE_CHANNEL: &str = "gateway:cache:purge";
+
+/// Message published on `PURGE_CHANNEL`, also accepted by `POST /internal/cache/purge`:
+/// drops the responses tagged with any of `tags`, or every response if `all` is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachePurge {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub all: bool,
+}
+
+impl CachePurge {
+    pub fn tags(tags: Vec<String>) -> Self {
+        Self { tags, all: false }
+    }
+}
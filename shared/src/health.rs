@@ -0,0 +1,5 @@
+use axum::http::StatusCode;
+
+pub async fn health_check() -> (StatusCode, &'static str) {
+    (StatusCode::OK, "OK")
+}
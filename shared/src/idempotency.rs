@@ -0,0 +1,350 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderName, HeaderValue, Request, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use deadpool_redis::{redis, Pool};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tower::{Layer, Service};
+
+use crate::{error::ErrorBody, jwt::authenticated_user_from_headers};
+
+pub static IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+pub static IDEMPOTENT_REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_LOCK_TTL: Duration = Duration::from_secs(60);
+const MAX_KEY_LEN: usize = 255;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum IdempotencyRecord {
+    InFlight {
+        fingerprint: String,
+    },
+    Completed {
+        fingerprint: String,
+        response: StoredResponse,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Clone)]
+pub struct IdempotencyLayer {
+    pool: Arc<Pool>,
+    ttl: Duration,
+    lock_ttl: Duration,
+}
+
+impl IdempotencyLayer {
+    pub fn new(pool: Arc<Pool>) -> Self {
+        Self {
+            pool,
+            ttl: DEFAULT_TTL,
+            lock_ttl: DEFAULT_LOCK_TTL,
+        }
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn lock_ttl(mut self, lock_ttl: Duration) -> Self {
+        self.lock_ttl = lock_ttl;
+        self
+    }
+}
+
+impl IdempotencyLayer {
+    // Stores the completed response, or releases the key so the client can retry.
+    async fn finish(&self, redis_key: &str, record: Option<IdempotencyRecord>) {
+        let mut conn = match self.pool.get().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::warn!("Failed to finish idempotent request: {}", e);
+                return;
+            }
+        };
+
+        let result = match record {
+            Some(record) => {
+                redis::cmd("SET")
+                    .arg(redis_key)
+                    .arg(serde_json::to_string(&record).unwrap_or_default())
+                    .arg("EX")
+                    .arg(self.ttl.as_secs().max(1))
+                    .query_async::<_, ()>(&mut conn)
+                    .await
+            }
+            None => {
+                redis::cmd("DEL")
+                    .arg(redis_key)
+                    .query_async::<_, ()>(&mut conn)
+                    .await
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to finish idempotent request: {}", e);
+        }
+    }
+}
+
+impl<S> Layer<S> for IdempotencyLayer {
+    type Service = IdempotencyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        IdempotencyService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IdempotencyService<S> {
+    inner: S,
+    layer: IdempotencyLayer,
+}
+
+impl<S> Service<Request<Body>> for IdempotencyService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        let layer = self.layer.clone();
+
+        Box::pin(async move {
+            let Some(key) = req.headers().get(&IDEMPOTENCY_KEY_HEADER).cloned() else {
+                return inner.call(req).await;
+            };
+            let Some(key) = key.to_str().ok().filter(|key| is_valid_key(key)) else {
+                return Ok(error(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid Idempotency-Key header",
+                ));
+            };
+
+            // Unauthenticated requests are rejected by the handler itself.
+            let Ok(user) = authenticated_user_from_headers(req.headers()) else {
+                return inner.call(req).await;
+            };
+
+            let (parts, body) = req.into_parts();
+            let body = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+                Ok(body) => body,
+                Err(_) => {
+                    return Ok(error(
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        "Request body too large",
+                    ))
+                }
+            };
+            let fingerprint = fingerprint(parts.method.as_str(), parts.uri.path(), &body);
+            let req = Request::from_parts(parts, Body::from(body));
+
+            let redis_key = format!("idempotency:{}:{}", user.0.sub, key);
+            let mut conn = match layer.pool.get().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    tracing::warn!("Idempotency store unavailable, skipping: {}", e);
+                    return inner.call(req).await;
+                }
+            };
+
+            let in_flight = IdempotencyRecord::InFlight {
+                fingerprint: fingerprint.clone(),
+            };
+            let acquired = redis::cmd("SET")
+                .arg(&redis_key)
+                .arg(serde_json::to_string(&in_flight).unwrap_or_default())
+                .arg("NX")
+                .arg("EX")
+                .arg(layer.lock_ttl.as_secs().max(1))
+                .query_async::<_, Option<String>>(&mut conn)
+                .await;
+
+            match acquired {
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    let record = redis::cmd("GET")
+                        .arg(&redis_key)
+                        .query_async::<_, Option<String>>(&mut conn)
+                        .await
+                        .ok()
+                        .flatten()
+                        .and_then(|record| serde_json::from_str(&record).ok());
+                    return Ok(existing_response(record, &fingerprint));
+                }
+                Err(e) => {
+                    tracing::warn!("Idempotency store unavailable, skipping: {}", e);
+                    return inner.call(req).await;
+                }
+            }
+            // The handler may need pooled connections of its own.
+            drop(conn);
+
+            let res = inner.call(req).await?;
+
+            if res.status().is_server_error() {
+                layer.finish(&redis_key, None).await;
+                return Ok(res);
+            }
+
+            let (parts, body) = res.into_parts();
+            let body = match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+                Ok(body) => body,
+                Err(_) => {
+                    layer.finish(&redis_key, None).await;
+                    return Ok(error(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to read response body",
+                    ));
+                }
+            };
+            let res = Response::from_parts(parts, Body::from(body.clone()));
+
+            let completed = StoredResponse::from_parts(&res, &body).map(|response| {
+                IdempotencyRecord::Completed {
+                    fingerprint,
+                    response,
+                }
+            });
+            layer.finish(&redis_key, completed).await;
+
+            Ok(res)
+        })
+    }
+}
+
+impl StoredResponse {
+    pub fn from_parts(res: &Response, body: &Bytes) -> Option<Self> {
+        let body = String::from_utf8(body.to_vec()).ok()?;
+        let headers = res
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name != header::CONTENT_LENGTH && *name != header::DATE)
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+
+        Some(Self {
+            status: res.status().as_u16(),
+            headers,
+            body,
+        })
+    }
+
+    pub fn to_response(&self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut res = (status, self.body.clone()).into_response();
+
+        let stored: Vec<(HeaderName, HeaderValue)> = self
+            .headers
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::try_from(name.as_str()).ok()?,
+                    HeaderValue::from_str(value).ok()?,
+                ))
+            })
+            .collect();
+        // Stored headers replace the defaults (`content-type`), and a header sent
+        // several times (`set-cookie`, `vary`) keeps every value.
+        let headers = res.headers_mut();
+        for (name, _) in &stored {
+            headers.remove(name);
+        }
+        for (name, value) in stored {
+            headers.append(name, value);
+        }
+        headers.insert(
+            IDEMPOTENT_REPLAYED_HEADER.clone(),
+            HeaderValue::from_static("true"),
+        );
+
+        res
+    }
+}
+
+pub fn fingerprint(method: &str, path: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update(b" ");
+    hasher.update(path.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn existing_response(record: Option<IdempotencyRecord>, fingerprint: &str) -> Response {
+    let conflict = || {
+        error(
+            StatusCode::CONFLICT,
+            "A request with this Idempotency-Key is already in progress",
+        )
+    };
+
+    let (stored, response) = match record {
+        Some(IdempotencyRecord::Completed {
+            fingerprint,
+            response,
+        }) => (fingerprint, Some(response)),
+        Some(IdempotencyRecord::InFlight { fingerprint }) => (fingerprint, None),
+        None => return conflict(),
+    };
+
+    if stored != fingerprint {
+        return error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key was already used with a different request",
+        );
+    }
+
+    match response {
+        Some(response) => response.to_response(),
+        None => conflict(),
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= MAX_KEY_LEN && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(ErrorBody::new(status.as_u16(), message))).into_response()
+}
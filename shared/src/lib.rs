@@ -1,5 +1,15 @@
+pub mod cache_purge;
+pub mod error;
+pub mod health;
+pub mod idempotency;
 pub mod jwt;
+pub mod openapi;
+pub mod request_id;
 pub mod token;
 
+pub use error::ErrorBody;
+pub use health::health_check;
+pub use idempotency::{IdempotencyLayer, IDEMPOTENCY_KEY_HEADER};
 pub use jwt::AuthenticatedUser;
+pub use request_id::{RequestId, RequestIdLayer, REQUEST_ID_HEADER};
 pub use token::{generate_token, validate_token, Claims};
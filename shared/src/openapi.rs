@@ -0,0 +1,34 @@
+use serde::Serialize;
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, ToSchema,
+};
+
+pub const BEARER_SCHEME: &str = "bearer_auth";
+
+pub struct BearerSecurity;
+
+/// Documents how `bson::oid::ObjectId` fields appear in JSON responses
+/// (`{"$oid": "..."}`); use it as `#[schema(value_type = ObjectIdJson)]`.
+#[derive(Serialize, ToSchema)]
+#[schema(as = ObjectId)]
+pub struct ObjectIdJson {
+    #[serde(rename = "$oid")]
+    #[schema(pattern = "^[0-9a-f]{24}$", example = "665f1c2e9b1d8a3f4c2e7a10")]
+    pub oid: String,
+}
+
+impl Modify for BearerSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            BEARER_SCHEME,
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderName, HeaderValue, Request, StatusCode},
+    response::Response,
+};
+use futures::StreamExt;
+use tower::{Layer, Service};
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::error::ErrorBody;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl<S> FromRequestParts<S> for RequestId
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<RequestId>()
+            .cloned()
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RequestIdLayer;
+
+impl RequestIdLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestIdService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestIdService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestIdService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for RequestIdService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(&REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| is_valid_request_id(v))
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+        let header_value =
+            HeaderValue::from_str(&request_id).expect("request id is a valid header value");
+        req.headers_mut()
+            .insert(REQUEST_ID_HEADER.clone(), header_value.clone());
+        req.extensions_mut().insert(RequestId(request_id.clone()));
+
+        let span = tracing::info_span!(
+            "request",
+            request_id = %request_id,
+            method = %req.method(),
+            path = %req.uri().path(),
+        );
+
+        let started = Instant::now();
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+
+        Box::pin(
+            async move {
+                let res = inner.call(req).await?;
+                let mut res = if res.status().is_client_error() || res.status().is_server_error() {
+                    with_error_body(res, &request_id).await
+                } else {
+                    res
+                };
+
+                tracing::info!(
+                    status = res.status().as_u16(),
+                    latency_ms = started.elapsed().as_millis() as u64,
+                    "request completed"
+                );
+
+                res.headers_mut()
+                    .insert(REQUEST_ID_HEADER.clone(), header_value);
+                Ok(res)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+async fn with_error_body(res: Response, request_id: &str) -> Response {
+    let (mut parts, body) = res.into_parts();
+
+    let is_event_stream = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+    if is_event_stream {
+        return Response::from_parts(parts, body);
+    }
+
+    // Bodies too large to rewrite are passed through untouched rather than dropped.
+    if body.size_hint().lower() > MAX_ERROR_BODY_BYTES as u64 {
+        return Response::from_parts(parts, body);
+    }
+
+    let mut stream = body.into_data_stream();
+    let mut chunks = Vec::new();
+    let mut len = 0;
+    while let Some(chunk) = stream.next().await {
+        let Ok(chunk) = chunk else {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            return Response::from_parts(parts, Body::empty());
+        };
+        len += chunk.len();
+        chunks.push(chunk);
+        if len > MAX_ERROR_BODY_BYTES {
+            let read = futures::stream::iter(chunks.into_iter().map(Ok::<_, axum::Error>));
+            return Response::from_parts(parts, Body::from_stream(read.chain(stream)));
+        }
+    }
+    let bytes = chunks.concat();
+
+    let mut error_body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.insert(
+                "request_id".to_string(),
+                serde_json::Value::String(request_id.to_string()),
+            );
+            object
+                .entry("status")
+                .or_insert_with(|| parts.status.as_u16().into());
+            serde_json::Value::Object(object)
+        }
+        Ok(_) | Err(_) => {
+            let message = String::from_utf8_lossy(&bytes).trim().to_string();
+            let message = if message.is_empty() {
+                parts
+                    .status
+                    .canonical_reason()
+                    .unwrap_or("Error")
+                    .to_string()
+            } else {
+                message
+            };
+            serde_json::to_value(ErrorBody {
+                error: message,
+                status: parts.status.as_u16(),
+                request_id: Some(request_id.to_string()),
+            })
+            .unwrap_or_default()
+        }
+    };
+
+    if error_body.get("error").is_none() {
+        if let serde_json::Value::Object(object) = &mut error_body {
+            let reason = parts.status.canonical_reason().unwrap_or("Error");
+            object.insert("error".to_string(), reason.into());
+        }
+    }
+
+    let body = serde_json::to_vec(&error_body).unwrap_or_default();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+
+    Response::from_parts(parts, Body::from(body))
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+    Router,
+};
+use deadpool_redis::{Config as RedisConfig, Runtime};
+use http_body_util::BodyExt;
+use shared::idempotency::{
+    existing_response, fingerprint, IdempotencyRecord, StoredResponse, IDEMPOTENT_REPLAYED_HEADER,
+};
+use shared::{generate_token, IdempotencyLayer, IDEMPOTENCY_KEY_HEADER};
+use tower::ServiceExt;
+
+const SECRET: &str = "idempotency-test-secret";
+
+fn test_app() -> Router {
+    std::env::set_var("JWT_SECRET", SECRET);
+
+    // Nothing listens on this port, so every Redis call fails.
+    let pool = RedisConfig::from_url("redis://127.0.0.1:1")
+        .create_pool(Some(Runtime::Tokio1))
+        .unwrap();
+
+    Router::new().route(
+        "/posts",
+        post(|body: String| async move { (StatusCode::CREATED, body) })
+            .layer(IdempotencyLayer::new(Arc::new(pool))),
+    )
+}
+
+fn completed(fingerprint: &str) -> IdempotencyRecord {
+    IdempotencyRecord::Completed {
+        fingerprint: fingerprint.to_string(),
+        response: StoredResponse {
+            status: 201,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: r#"{"id":"1"}"#.to_string(),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_invalid_key() {
+    let response = test_app()
+        .oneshot(
+            Request::post("/posts")
+                .header(&IDEMPOTENCY_KEY_HEADER, "has spaces")
+                .body(Body::from("hello"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_falls_back_to_handler_when_redis_is_unavailable() {
+    let token = generate_token("user-1", "user@example.com", SECRET).unwrap();
+
+    let response = test_app()
+        .oneshot(
+            Request::post("/posts")
+                .header("authorization", format!("Bearer {}", token))
+                .header(&IDEMPOTENCY_KEY_HEADER, "retry-1")
+                .body(Body::from("hello"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"hello");
+}
+
+#[tokio::test]
+async fn test_replays_completed_response_for_same_request() {
+    let fingerprint = fingerprint("POST", "/posts", b"hello");
+
+    let response = existing_response(Some(completed(&fingerprint)), &fingerprint);
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(
+        response.headers().get(&IDEMPOTENT_REPLAYED_HEADER).unwrap(),
+        "true"
+    );
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json"
+    );
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], br#"{"id":"1"}"#);
+}
+
+#[tokio::test]
+async fn test_replay_keeps_repeated_headers() {
+    let original = axum::http::Response::builder()
+        .status(StatusCode::CREATED)
+        .header("content-type", "application/json")
+        .header("set-cookie", "a=1")
+        .header("set-cookie", "b=2")
+        .body(Body::empty())
+        .unwrap();
+    let body = axum::body::Bytes::from_static(br#"{"id":"1"}"#);
+
+    let stored = StoredResponse::from_parts(&original, &body).unwrap();
+    let replayed = stored.to_response();
+
+    let cookies: Vec<_> = replayed.headers().get_all("set-cookie").iter().collect();
+    assert_eq!(cookies, ["a=1", "b=2"]);
+    let content_types: Vec<_> = replayed.headers().get_all("content-type").iter().collect();
+    assert_eq!(content_types, ["application/json"]);
+}
+
+#[tokio::test]
+async fn test_conflicting_and_in_flight_duplicates() {
+    let original = fingerprint("POST", "/posts", b"hello");
+    let different = fingerprint("POST", "/posts", b"goodbye");
+    assert_ne!(original, different);
+
+    let response = existing_response(Some(completed(&original)), &different);
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let in_flight = IdempotencyRecord::InFlight {
+        fingerprint: original.clone(),
+    };
+    let response = existing_response(Some(in_flight), &original);
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
@@ -0,0 +1,134 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use http_body_util::BodyExt;
+use shared::{RequestId, RequestIdLayer};
+use tower::ServiceExt;
+
+fn test_app() -> Router {
+    Router::new()
+        .route("/ok", get(|RequestId(id): RequestId| async move { id }))
+        .route(
+            "/fail",
+            get(|| async {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "Invalid conversation id".to_string(),
+                )
+            }),
+        )
+        .route(
+            "/fail-large",
+            get(|| async { (StatusCode::BAD_GATEWAY, large_body()) }),
+        )
+        .route(
+            "/fail-large-stream",
+            get(|| async {
+                let chunks = large_body()
+                    .into_bytes()
+                    .chunks(8 * 1024)
+                    .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+                    .collect::<Vec<_>>();
+                (
+                    StatusCode::BAD_GATEWAY,
+                    Body::from_stream(futures::stream::iter(chunks)),
+                )
+            }),
+        )
+        .layer(RequestIdLayer::new())
+}
+
+fn large_body() -> String {
+    "x".repeat(100 * 1024)
+}
+
+#[tokio::test]
+async fn test_generates_request_id_when_missing() {
+    let response = test_app()
+        .oneshot(Request::builder().uri("/ok").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let header = response.headers()["x-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    assert!(!header.is_empty());
+    assert_eq!(body, header.as_bytes());
+}
+
+#[tokio::test]
+async fn test_propagates_incoming_request_id() {
+    let response = test_app()
+        .oneshot(
+            Request::builder()
+                .uri("/ok")
+                .header("x-request-id", "abc-123")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.headers()["x-request-id"], "abc-123");
+}
+
+#[tokio::test]
+async fn test_replaces_invalid_request_id() {
+    let response = test_app()
+        .oneshot(
+            Request::builder()
+                .uri("/ok")
+                .header("x-request-id", "not valid!")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_ne!(response.headers()["x-request-id"], "not valid!");
+}
+
+#[tokio::test]
+async fn test_error_body_contains_request_id() {
+    let response = test_app()
+        .oneshot(
+            Request::builder()
+                .uri("/fail")
+                .header("x-request-id", "req-42")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(error["error"], "Invalid conversation id");
+    assert_eq!(error["status"], 400);
+    assert_eq!(error["request_id"], "req-42");
+}
+
+#[tokio::test]
+async fn test_large_error_body_is_passed_through() {
+    for uri in ["/fail-large", "/fail-large-stream"] {
+        let response = test_app()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert!(response.headers().contains_key("x-request-id"));
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, large_body().as_bytes());
+    }
+}
@@ -5,6 +5,7 @@ pub struct Config {
     pub port: u16,
     pub mongo_uri: String,
     pub redis_uri: String,
+    pub idempotency_ttl_secs: u64,
 }
 
 impl Config {
@@ -17,7 +18,11 @@ impl Config {
                 .parse()
                 .expect("Port must be a number"),
             mongo_uri: env::var("MONGO_URI").expect("MONGO_URI must be set"),
-            redis_uri: env::var("REDIS_URI").expect("REDIS_URI must be set"),
+            redis_uri: env::var("REDIS_URL").expect("REDIS_URL must be set"),
+            idempotency_ttl_secs: env::var("IDEMPOTENCY_TTL_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .expect("IDEMPOTENCY_TTL_SECS must be a number"),
         }
     }
 }
@@ -6,18 +6,33 @@ use axum::{
     http::StatusCode,
 };
 use mongodb::bson::oid::ObjectId;
-use shared::jwt::AuthenticatedUser;
+use shared::{jwt::AuthenticatedUser, ErrorBody};
 
 use crate::{
     models::post::{CreatePostRequest, Post},
-    services::post::PostService,
+    services::{cache_purge::CachePurger, post::PostService},
 };
 
 #[derive(Clone)]
 pub struct PostAppState {
     pub post_service: Arc<PostService>,
+    pub cache_purger: Arc<CachePurger>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/posts",
+    tag = "posts",
+    request_body = CreatePostRequest,
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response when a retry reuses the same key")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Post created", body = Post),
+        (status = 401, description = "Missing or invalid token", body = ErrorBody),
+        (status = 409, description = "A request with this Idempotency-Key is in progress", body = ErrorBody),
+        (status = 422, description = "Idempotency-Key reused with a different body", body = ErrorBody),
+    )
+)]
 pub async fn create_post(
     State(state): State<PostAppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -35,7 +50,12 @@ pub async fn create_post(
     };
 
     match state.post_service.create(post).await {
-        Ok(created_post) => Ok((StatusCode::CREATED, Json(created_post))),
+        Ok(created_post) => {
+            state
+                .cache_purger
+                .purge(vec![format!("user-posts:{}", created_post.user_id)]);
+            Ok((StatusCode::CREATED, Json(created_post)))
+        }
         Err(_) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             "Failed to create post".to_string(),
@@ -43,6 +63,18 @@ pub async fn create_post(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/posts/{post_id}",
+    tag = "posts",
+    params(("post_id" = String, Path, description = "Post id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Post", body = Post),
+        (status = 400, description = "Invalid post id", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+    )
+)]
 pub async fn get_post_by_id(
     State(state): State<PostAppState>,
     AuthenticatedUser(_user): AuthenticatedUser,
@@ -71,6 +103,18 @@ pub async fn get_post_by_id(
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/posts/{post_id}",
+    tag = "posts",
+    params(("post_id" = String, Path, description = "Post id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Post deleted"),
+        (status = 403, description = "Not the author", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody),
+    )
+)]
 pub async fn delete_post(
     State(state): State<PostAppState>,
     AuthenticatedUser(user): AuthenticatedUser,
@@ -106,7 +150,13 @@ pub async fn delete_post(
     }
 
     match state.post_service.delete(object_id).await {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Ok(_) => {
+            state.cache_purger.purge(vec![
+                format!("post:{}", post_id),
+                format!("user-posts:{}", user.sub),
+            ]);
+            Ok(StatusCode::NO_CONTENT)
+        }
         Err(_) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             "Failed to delete post".to_string(),
@@ -114,6 +164,16 @@ pub async fn delete_post(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/posts",
+    tag = "posts",
+    params(("user_id" = String, Path, description = "Author id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Posts of the user", body = [Post]),
+    )
+)]
 pub async fn get_user_posts(
     State(state): State<PostAppState>,
     AuthenticatedUser(_user): AuthenticatedUser,
@@ -1,11 +1,55 @@
-mod config;
-use config::Config;
-mod handlers;
-mod models;
-mod services;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use deadpool_redis::{Config as RedisConfig, Runtime};
+use mongodb::Client as MongoClient;
+use shared::{IdempotencyLayer, RequestIdLayer};
+use std::{sync::Arc, time::Duration};
+
+use social_service::config::Config;
+use social_service::handlers::post::{
+    create_post, delete_post, get_post_by_id, get_user_posts, PostAppState,
+};
+use social_service::openapi;
+use social_service::services::{cache_purge::CachePurger, post::PostService};
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
     let config = Config::from_env();
+
+    let mongo_client = MongoClient::with_uri_str(&config.mongo_uri)
+        .await
+        .expect("Failed to connect to MongoDB");
+
+    let redis_pool = Arc::new(
+        RedisConfig::from_url(&config.redis_uri)
+            .create_pool(Some(Runtime::Tokio1))
+            .expect("Failed to create Redis pool"),
+    );
+    let idempotency = IdempotencyLayer::new(redis_pool.clone())
+        .ttl(Duration::from_secs(config.idempotency_ttl_secs));
+
+    let post_service = Arc::new(PostService::new(&mongo_client));
+    let cache_purger = Arc::new(CachePurger::new(redis_pool));
+    let state = PostAppState {
+        post_service,
+        cache_purger,
+    };
+
+    let app = Router::new()
+        .route("/health", get(shared::health_check))
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/posts", post(create_post).layer(idempotency))
+        .route("/posts/{post_id}", get(get_post_by_id).delete(delete_post))
+        .route("/users/{user_id}/posts", get(get_user_posts))
+        .with_state(state)
+        .layer(RequestIdLayer::new());
+
+    let addr = format!("0.0.0.0:{}", config.port);
+    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    tracing::info!("Social service listening on {}", addr);
+
+    axum::serve(listener, app).await.unwrap();
 }
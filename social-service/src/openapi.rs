@@ -0,0 +1,25 @@
+use axum::Json;
+use shared::{openapi::BearerSecurity, ErrorBody};
+use utoipa::OpenApi;
+
+use crate::handlers::post;
+use crate::models::post::{CreatePostRequest, Post};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Staki Social Service"),
+    paths(
+        post::create_post,
+        post::get_post_by_id,
+        post::delete_post,
+        post::get_user_posts
+    ),
+    components(schemas(Post, CreatePostRequest, ErrorBody)),
+    modifiers(&BearerSecurity),
+    tags((name = "posts", description = "User posts"))
+)]
+pub struct ApiDoc;
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
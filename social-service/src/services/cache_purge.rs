@@ -0,0 +1,61 @@
+use std::{sync::Arc, time::Duration};
+
+use deadpool_redis::{redis, Pool};
+use shared::cache_purge::{CachePurge, PURGE_CHANNEL};
+
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+pub struct CachePurger {
+    redis: Option<Arc<Pool>>,
+}
+
+impl CachePurger {
+    pub fn new(redis: Arc<Pool>) -> Self {
+        Self { redis: Some(redis) }
+    }
+
+    pub fn disabled() -> Self {
+        Self { redis: None }
+    }
+
+    pub fn purge(&self, tags: Vec<String>) {
+        let Some(pool) = self.redis.clone() else {
+            return;
+        };
+        let payload = match serde_json::to_string(&CachePurge::tags(tags)) {
+            Ok(payload) => payload,
+            Err(e) => {
+                tracing::warn!("Failed to encode cache purge: {}", e);
+                return;
+            }
+        };
+
+        tokio::spawn(async move {
+            for attempt in 1..=MAX_ATTEMPTS {
+                match publish(&pool, &payload).await {
+                    Ok(()) => return,
+                    Err(e) if attempt == MAX_ATTEMPTS => {
+                        tracing::warn!(
+                            "Failed to purge gateway cache after {} attempts: {}",
+                            attempt,
+                            e
+                        );
+                    }
+                    Err(_) => tokio::time::sleep(RETRY_DELAY * attempt).await,
+                }
+            }
+        });
+    }
+}
+
+async fn publish(pool: &Pool, payload: &str) -> Result<(), String> {
+    let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+    redis::cmd("PUBLISH")
+        .arg(PURGE_CHANNEL)
+        .arg(payload)
+        .query_async::<_, i64>(&mut conn)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
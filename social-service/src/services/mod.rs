@@ -1 +1,2 @@
+pub mod cache_purge;
 pub mod post;
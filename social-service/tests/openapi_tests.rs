@@ -0,0 +1,30 @@
+use chrono::Utc;
+use mongodb::bson::oid::ObjectId;
+use utoipa::OpenApi;
+
+use social_service::models::post::Post;
+use social_service::openapi::ApiDoc;
+
+#[test]
+fn test_object_id_schema_matches_serialized_shape() {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+    let schemas = &spec["components"]["schemas"];
+
+    let id = &schemas["Post"]["properties"]["_id"];
+    let refs = id.to_string();
+    assert!(refs.contains("#/components/schemas/ObjectId"), "{}", refs);
+    assert_eq!(schemas["ObjectId"]["properties"]["$oid"]["type"], "string");
+
+    let post = Post {
+        id: Some(ObjectId::new()),
+        user_id: "user-1".to_string(),
+        content: "hello".to_string(),
+        likes_count: 0,
+        comments_count: 0,
+        replies_count: 0,
+        is_deleted: false,
+        created_at: Utc::now(),
+    };
+    let json = serde_json::to_value(&post).unwrap();
+    assert!(json["_id"]["$oid"].is_string());
+}
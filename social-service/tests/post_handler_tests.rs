@@ -12,7 +12,7 @@ use tower::ServiceExt;
 use social_service::handlers::post::{
     create_post, delete_post, get_post_by_id, get_user_posts, PostAppState,
 };
-use social_service::services::post::PostService;
+use social_service::services::{cache_purge::CachePurger, post::PostService};
 
 async fn setup_test_app() -> Router {
     // Set JWT_SECRET for tests
@@ -25,7 +25,10 @@ async fn setup_test_app() -> Router {
 
     let post_service = Arc::new(PostService::new(&mongo_client));
 
-    let state = PostAppState { post_service };
+    let state = PostAppState {
+        post_service,
+        cache_purger: Arc::new(CachePurger::disabled()),
+    };
 
     Router::new()
         .route("/posts", axum::routing::post(create_post))
@@ -191,5 +194,6 @@ async fn test_get_user_posts() {
     let body = response.into_body().collect().await.unwrap().to_bytes();
     let posts: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
 
-    // Should return an array (verified by the type itself)
+    // Only the requested user's posts are listed.
+    assert!(posts.iter().all(|post| post["user_id"] == "test_user_123"));
 }